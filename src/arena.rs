@@ -0,0 +1,514 @@
+//! An alternative engine storing the whole graph in one contiguous `Vec`.
+//!
+//! A [`Graph`] owns its nodes, with data, grad and op inline and operands referenced by
+//! index; a [`Var`] is a `Copy` handle of (graph, index). Nodes are only ever appended
+//! and an operand always precedes its result, so the index order is a topological order
+//! and `backward` is a single reverse sweep, with no allocation per node and no visited
+//! set. The trade-off is lifetime: nodes are never freed individually, so a training loop
+//! keeps the parameters at the front and `truncate`s the rest away between steps.
+//!
+//! [`Var`] has the ops of [`Value`](crate::Value) with fixed arity, with the same data
+//! and grads; the binary cross-entropies are methods rather than `losses` functions.
+//! `Value::sum` and `Value::mean` are left out: a node stores its operands inline so the
+//! arena stays one flat `Vec` of `Copy` nodes, which has no room for a variable number
+//! of them. Chain `+` and divide by the count instead.
+//!
+//! ```
+//! use micrograd::arena::Graph;
+//!
+//! let g = Graph::new();
+//! let x = g.leaf(3.0);
+//! let y = x * x + x;
+//! y.backward();
+//! assert_eq!(y.data(), 12.0);
+//! assert_eq!(x.grad(), 7.0);
+//! ```
+
+use crate::losses::check_binary_target;
+use crate::shared::Slot;
+use crate::value::{
+    bce_loss, bce_with_logits_loss, clamp_probability, gelu, gelu_grad, stable_sigmoid,
+};
+use crate::{Activation, Scalar, MLP};
+
+/// Operation producing a node, operands referenced by index
+#[derive(Debug, Clone, Copy)]
+enum NodeOp {
+    Leaf,
+    Plus(usize, usize),
+    Sub(usize, usize),
+    Mul(usize, usize),
+    MulAdd(usize, usize, usize),
+    Div(usize, usize),
+    Max(usize, usize),
+    Pow(usize, Scalar),
+    PowV(usize, usize),
+    Tanh(usize),
+    Exp(usize),
+    Ln(usize),
+    ReLU(usize),
+    LeakyReLU(usize, Scalar),
+    Sigmoid(usize),
+    Gelu(usize),
+    Neg(usize),
+    Bce(usize, Scalar),
+    BceWithLogits(usize, Scalar),
+    Abs(usize),
+    Clamp(usize, Scalar, Scalar),
+    Sin(usize),
+    Cos(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    data: Scalar,
+    grad: Scalar,
+    op: NodeOp,
+}
+
+/// Arena of nodes; see the module docs
+pub struct Graph {
+    nodes: Slot<Vec<Node>>,
+}
+
+/// Handle to a node of a [`Graph`]
+#[derive(Clone, Copy)]
+pub struct Var<'g> {
+    graph: &'g Graph,
+    index: usize,
+}
+
+impl Default for Graph {
+    fn default() -> Self {
+        Graph::with_capacity(0)
+    }
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Room for `capacity` nodes before the arena reallocates
+    pub fn with_capacity(capacity: usize) -> Self {
+        Graph {
+            nodes: Slot::new(Vec::with_capacity(capacity)),
+        }
+    }
+
+    pub fn leaf(&self, data: Scalar) -> Var<'_> {
+        self.push(data, NodeOp::Leaf)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every node from index `len` on, keeping the capacity. Handles to the dropped
+    /// nodes must not be used afterwards: they panic or, once the slot is reused, refer
+    /// to the new node.
+    pub fn truncate(&self, len: usize) {
+        self.nodes.borrow_mut().truncate(len);
+    }
+
+    /// A leaf per parameter of `mlp`, in `MLP::get_parameters` order
+    pub fn load_parameters(&self, mlp: &MLP) -> Vec<Var<'_>> {
+        mlp.get_parameters()
+            .iter()
+            .map(|p| self.leaf(p.data()))
+            .collect()
+    }
+
+    /// Write the data of `params`, as returned by `load_parameters`, back into `mlp`
+    pub fn store_parameters(&self, mlp: &MLP, params: &[Var<'_>]) {
+        let targets = mlp.get_parameters();
+        assert_eq!(
+            params.len(),
+            targets.len(),
+            "got {} parameters for a network with {}",
+            params.len(),
+            targets.len()
+        );
+        for (p, v) in targets.iter().zip(params) {
+            p.set_data(v.data());
+        }
+    }
+
+    /// `MLP::apply` in this arena, with `params` from `load_parameters` standing in for
+    /// the network's own. Like `MLP::predict`, dropout is never applied.
+    pub fn apply_mlp<'g>(&'g self, mlp: &MLP, params: &[Var<'g>], x: &[Var<'g>]) -> Vec<Var<'g>> {
+        assert_eq!(
+            params.len(),
+            mlp.num_parameters(),
+            "got {} parameters for a network with {}",
+            params.len(),
+            mlp.num_parameters()
+        );
+        let mut params = params.iter().copied();
+        let mut x = x.to_vec();
+        for layer in mlp.layers() {
+            x = layer
+                .neurons()
+                .iter()
+                .map(|n| {
+                    assert_eq!(
+                        x.len(),
+                        n.nin(),
+                        "neuron expects {} inputs, got {}",
+                        n.nin(),
+                        x.len()
+                    );
+                    let w: Vec<Var<'g>> = params.by_ref().take(n.nin()).collect();
+                    let b = n.bias().map(|_| params.next().unwrap());
+                    // the multiply-add chain of `Neuron::apply`, so the data matches bit
+                    // for bit
+                    let mut terms = x.iter().zip(w);
+                    let start = match (b, terms.next()) {
+                        (Some(b), Some((&xi, wi))) => xi.mul_add(wi, b),
+                        (None, Some((&xi, wi))) => xi * wi,
+                        (Some(b), None) => b,
+                        (None, None) => self.leaf(0.0),
+                    };
+                    terms
+                        .fold(start, |s, (&xi, wi)| xi.mul_add(wi, s))
+                        .activate(n.activation())
+                })
+                .collect();
+        }
+        x
+    }
+
+    fn push(&self, data: Scalar, op: NodeOp) -> Var<'_> {
+        let mut nodes = self.nodes.borrow_mut();
+        nodes.push(Node {
+            data,
+            grad: 0.0,
+            op,
+        });
+        Var {
+            graph: self,
+            index: nodes.len() - 1,
+        }
+    }
+
+    fn data(&self, index: usize) -> Scalar {
+        self.nodes.borrow()[index].data
+    }
+
+    fn unary(&self, a: usize, data: impl FnOnce(Scalar) -> Scalar, op: NodeOp) -> Var<'_> {
+        let x = self.data(a);
+        self.push(data(x), op)
+    }
+
+    fn backward(&self, root: usize) {
+        let mut nodes = self.nodes.borrow_mut();
+        for n in &mut nodes[..=root] {
+            n.grad = 0.0;
+        }
+        nodes[root].grad = 1.0;
+        for i in (0..=root).rev() {
+            let Node { data, grad, op } = nodes[i];
+            match op {
+                NodeOp::Leaf => {}
+                NodeOp::Plus(a, b) => {
+                    nodes[a].grad += grad;
+                    nodes[b].grad += grad;
+                }
+                NodeOp::Sub(a, b) => {
+                    nodes[a].grad += grad;
+                    nodes[b].grad += -grad;
+                }
+                NodeOp::Mul(a, b) => {
+                    let (x, y) = (nodes[a].data, nodes[b].data);
+                    nodes[a].grad += grad * y;
+                    nodes[b].grad += grad * x;
+                }
+                NodeOp::MulAdd(a, b, c) => {
+                    let (x, y) = (nodes[a].data, nodes[b].data);
+                    nodes[a].grad += grad * y;
+                    nodes[b].grad += grad * x;
+                    nodes[c].grad += grad;
+                }
+                NodeOp::Div(a, b) => {
+                    let (x, d) = (nodes[a].data, nodes[b].data);
+                    nodes[a].grad += grad / d;
+                    nodes[b].grad += -grad * x / (d * d);
+                }
+                NodeOp::Max(a, b) => {
+                    if nodes[a].data >= nodes[b].data {
+                        nodes[a].grad += grad;
+                    } else {
+                        nodes[b].grad += grad;
+                    }
+                }
+                NodeOp::Pow(a, e) => {
+                    let x = nodes[a].data;
+                    nodes[a].grad += grad * e * x.powf(e - 1.0);
+                }
+                NodeOp::PowV(a, b) => {
+                    let (x, e) = (nodes[a].data, nodes[b].data);
+                    nodes[a].grad += grad * e * x.powf(e - 1.0);
+                    if x > 0.0 {
+                        nodes[b].grad += grad * data * x.ln();
+                    }
+                }
+                NodeOp::Tanh(a) => nodes[a].grad += grad * (1.0 - data * data),
+                NodeOp::Exp(a) => nodes[a].grad += grad * data,
+                NodeOp::Ln(a) => {
+                    let x = nodes[a].data;
+                    nodes[a].grad += grad / x;
+                }
+                NodeOp::ReLU(a) => {
+                    if nodes[a].data > 0.0 {
+                        nodes[a].grad += grad;
+                    }
+                }
+                NodeOp::LeakyReLU(a, slope) => {
+                    let d = if nodes[a].data > 0.0 { 1.0 } else { slope };
+                    nodes[a].grad += grad * d;
+                }
+                NodeOp::Sigmoid(a) => nodes[a].grad += grad * data * (1.0 - data),
+                NodeOp::Gelu(a) => nodes[a].grad += grad * gelu_grad(nodes[a].data),
+                NodeOp::Neg(a) => nodes[a].grad += -grad,
+                NodeOp::Bce(a, t) => {
+                    let p = clamp_probability(nodes[a].data);
+                    nodes[a].grad += grad * (p - t) / (p * (1.0 - p));
+                }
+                NodeOp::BceWithLogits(a, t) => {
+                    nodes[a].grad += grad * (stable_sigmoid(nodes[a].data) - t);
+                }
+                NodeOp::Abs(a) => {
+                    let x = nodes[a].data;
+                    if x != 0.0 {
+                        nodes[a].grad += grad * x.signum();
+                    }
+                }
+                NodeOp::Clamp(a, lo, hi) => {
+                    let x = nodes[a].data;
+                    if lo < x && x < hi {
+                        nodes[a].grad += grad;
+                    }
+                }
+                NodeOp::Sin(a) => nodes[a].grad += grad * nodes[a].data.cos(),
+                NodeOp::Cos(a) => nodes[a].grad += -grad * nodes[a].data.sin(),
+            }
+        }
+    }
+}
+
+impl<'g> Var<'g> {
+    pub fn data(&self) -> Scalar {
+        self.graph.data(self.index)
+    }
+
+    pub fn grad(&self) -> Scalar {
+        self.graph.nodes.borrow()[self.index].grad
+    }
+
+    /// Overwrite a node's data, e.g. for an optimizer step on a parameter leaf. Nodes
+    /// computed from it are not recomputed.
+    pub fn set_data(&self, data: Scalar) {
+        self.graph.nodes.borrow_mut()[self.index].data = data;
+    }
+
+    /// Position in the arena
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Back-propagate from this node, resetting the grads of every node up to it first.
+    /// Nodes appended after it are left alone.
+    pub fn backward(&self) {
+        self.graph.backward(self.index);
+    }
+
+    /// `self * b + c` as one node, with the data of `Value::mul_add`
+    pub fn mul_add(self, b: Var<'g>, c: Var<'g>) -> Var<'g> {
+        assert!(
+            std::ptr::eq(self.graph, b.graph) && std::ptr::eq(self.graph, c.graph),
+            "operands belong to different graphs"
+        );
+        let data = self.data() * b.data() + c.data();
+        self.graph
+            .push(data, NodeOp::MulAdd(self.index, b.index, c.index))
+    }
+
+    pub fn pow(self, exponent: Scalar) -> Var<'g> {
+        self.graph.unary(
+            self.index,
+            |x| x.powf(exponent),
+            NodeOp::Pow(self.index, exponent),
+        )
+    }
+
+    /// `Value::powv`: base raised to an exponent that is itself a node
+    pub fn powv(self, exponent: Var<'g>) -> Var<'g> {
+        self.binary(
+            exponent,
+            Scalar::powf,
+            NodeOp::PowV(self.index, exponent.index),
+        )
+    }
+
+    /// `Value::max`: the grad goes to the larger operand, to `self` on a tie
+    pub fn max(self, other: Var<'g>) -> Var<'g> {
+        self.binary(other, Scalar::max, NodeOp::Max(self.index, other.index))
+    }
+
+    pub fn abs(self) -> Var<'g> {
+        self.graph
+            .unary(self.index, Scalar::abs, NodeOp::Abs(self.index))
+    }
+
+    /// `Value::clamp`: the grad passes only strictly inside `[lo, hi]`
+    pub fn clamp(self, lo: Scalar, hi: Scalar) -> Var<'g> {
+        assert!(lo <= hi, "clamp bounds out of order: lo {} > hi {}", lo, hi);
+        self.graph.unary(
+            self.index,
+            |x| x.clamp(lo, hi),
+            NodeOp::Clamp(self.index, lo, hi),
+        )
+    }
+
+    pub fn sin(self) -> Var<'g> {
+        self.graph
+            .unary(self.index, Scalar::sin, NodeOp::Sin(self.index))
+    }
+
+    pub fn cos(self) -> Var<'g> {
+        self.graph
+            .unary(self.index, Scalar::cos, NodeOp::Cos(self.index))
+    }
+
+    /// `losses::binary_cross_entropy` of this probability against `target`
+    pub fn binary_cross_entropy(self, target: Scalar) -> Var<'g> {
+        check_binary_target(target);
+        self.graph.unary(
+            self.index,
+            |p| bce_loss(p, target),
+            NodeOp::Bce(self.index, target),
+        )
+    }
+
+    /// `losses::bce_with_logits` of this logit against `target`
+    pub fn bce_with_logits(self, target: Scalar) -> Var<'g> {
+        check_binary_target(target);
+        self.graph.unary(
+            self.index,
+            |x| bce_with_logits_loss(x, target),
+            NodeOp::BceWithLogits(self.index, target),
+        )
+    }
+
+    pub fn tanh(self) -> Var<'g> {
+        self.graph
+            .unary(self.index, Scalar::tanh, NodeOp::Tanh(self.index))
+    }
+
+    pub fn exp(self) -> Var<'g> {
+        self.graph
+            .unary(self.index, Scalar::exp, NodeOp::Exp(self.index))
+    }
+
+    pub fn ln(self) -> Var<'g> {
+        self.graph
+            .unary(self.index, Scalar::ln, NodeOp::Ln(self.index))
+    }
+
+    pub fn relu(self) -> Var<'g> {
+        self.graph
+            .unary(self.index, |x| x.max(0.0), NodeOp::ReLU(self.index))
+    }
+
+    pub fn leaky_relu(self, slope: Scalar) -> Var<'g> {
+        self.graph.unary(
+            self.index,
+            |x| if x > 0.0 { x } else { slope * x },
+            NodeOp::LeakyReLU(self.index, slope),
+        )
+    }
+
+    pub fn sigmoid(self) -> Var<'g> {
+        self.graph
+            .unary(self.index, stable_sigmoid, NodeOp::Sigmoid(self.index))
+    }
+
+    pub fn gelu(self) -> Var<'g> {
+        self.graph.unary(self.index, gelu, NodeOp::Gelu(self.index))
+    }
+
+    /// `Activation::apply` for arena nodes
+    pub fn activate(self, act: Activation) -> Var<'g> {
+        match act {
+            Activation::Tanh => self.tanh(),
+            Activation::ReLU => self.relu(),
+            Activation::LeakyReLU(slope) => self.leaky_relu(slope),
+            Activation::Sigmoid => self.sigmoid(),
+            Activation::Gelu => self.gelu(),
+            Activation::Linear => self,
+        }
+    }
+
+    fn binary(
+        self,
+        other: Var<'g>,
+        data: impl FnOnce(Scalar, Scalar) -> Scalar,
+        op: NodeOp,
+    ) -> Var<'g> {
+        assert!(
+            std::ptr::eq(self.graph, other.graph),
+            "operands belong to different graphs"
+        );
+        let (a, b) = (self.data(), other.data());
+        self.graph.push(data(a, b), op)
+    }
+}
+
+impl std::fmt::Debug for Var<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Var(index={}, data={}, grad={})",
+            self.index,
+            self.data(),
+            self.grad()
+        )
+    }
+}
+
+macro_rules! var_op_impl {
+    ($trait:ident, $method:ident, $variant:ident, $op:tt) => {
+        impl<'g> std::ops::$trait<Var<'g>> for Var<'g> {
+            type Output = Var<'g>;
+
+            fn $method(self, other: Var<'g>) -> Var<'g> {
+                self.binary(other, |a, b| a $op b, NodeOp::$variant(self.index, other.index))
+            }
+        }
+
+        impl<'g> std::ops::$trait<Scalar> for Var<'g> {
+            type Output = Var<'g>;
+
+            fn $method(self, other: Scalar) -> Var<'g> {
+                self $op self.graph.leaf(other)
+            }
+        }
+    };
+}
+
+var_op_impl!(Add, add, Plus, +);
+var_op_impl!(Sub, sub, Sub, -);
+var_op_impl!(Mul, mul, Mul, *);
+var_op_impl!(Div, div, Div, /);
+
+impl<'g> std::ops::Neg for Var<'g> {
+    type Output = Var<'g>;
+
+    fn neg(self) -> Var<'g> {
+        self.graph.push(-self.data(), NodeOp::Neg(self.index))
+    }
+}
@@ -0,0 +1,360 @@
+//! Saving and restoring trained [`MLP`] weights.
+//!
+//! Only the structure and the parameter data are stored: no grads, ids or labels.
+//! - `MLP::to_json` / `MLP::from_json` (with the `serde` feature) describe the whole
+//!   network, and loading builds fresh leaves labeled the same way as [`MLP::new`].
+//! - `MLP::load_state_dict_json` (with the `serde` feature) reads the weights of a
+//!   PyTorch-style export into an existing network.
+//! - [`MLP::save_weights`] / [`MLP::load_weights`] use a compact binary format and load
+//!   into an existing network of the same shape, keeping its `Value`s.
+//! - `Checkpoint` (with the `serde` feature) adds the optimizer state, the epoch and the
+//!   shuffling seed to the network, to resume a training run.
+//!
+//! The binary format is, all integers little-endian `u32`:
+//! magic `b"MGRD"`, version, bytes per scalar, number of sizes, the sizes (see
+//! [`MLP::sizes`]), the bias flags layer by layer as one byte per neuron (1 if it has a
+//! bias), [`MLP::architecture_hash`] as a little-endian `u64`, then every parameter in
+//! `get_parameters` order as a little-endian [`Scalar`]. Version 2 files have no hash,
+//! and version 1 files have no bias flags either, read as every neuron having a bias.
+
+#[cfg(feature = "serde")]
+mod json;
+
+#[cfg(feature = "serde")]
+pub use json::Checkpoint;
+
+use std::io;
+use std::path::Path;
+
+use crate::{Scalar, MLP};
+
+const MAGIC: &[u8; 4] = b"MGRD";
+const VERSION: u32 = 3;
+const SCALAR_BYTES: usize = std::mem::size_of::<Scalar>();
+
+/// Errors reported while loading stored weights
+#[derive(Debug)]
+pub enum LoadError {
+    /// the file could not be read
+    Io(io::Error),
+
+    /// not a weights file
+    BadMagic,
+
+    UnsupportedVersion(u32),
+
+    /// the file was written with a different `Scalar` type, e.g. by an `f64` build
+    ScalarWidth {
+        expected: usize,
+        got: usize,
+    },
+
+    /// the file ends before all parameters were read
+    Truncated,
+
+    /// bytes left over after the last parameter
+    TrailingBytes(usize),
+
+    /// the stored sizes don't match the network being loaded into
+    Architecture {
+        expected: Vec<usize>,
+        got: Vec<usize>,
+    },
+
+    /// the input is not valid JSON for a stored network
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error),
+
+    /// `sizes` must hold the input size followed by one width per layer
+    LayerCount {
+        sizes: usize,
+        layers: usize,
+    },
+
+    /// the stored weights give a neuron a bias where the network has none, or the other
+    /// way around; `expected` is whether the network's neuron has one
+    Bias {
+        layer: usize,
+        neuron: usize,
+        expected: bool,
+    },
+
+    /// the weights were saved from a network whose `MLP::architecture_hash` differs, e.g.
+    /// one with other activations
+    ArchitectureHash {
+        expected: u64,
+        got: u64,
+    },
+
+    /// a layer stores a different number of neurons than its declared width
+    LayerWidth {
+        layer: usize,
+        expected: usize,
+        got: usize,
+    },
+
+    /// a neuron stores a different number of weights than the layer's input size
+    NeuronInputs {
+        layer: usize,
+        neuron: usize,
+        expected: usize,
+        got: usize,
+    },
+
+    /// a state dict lacks an entry the network needs, e.g. "layers.1.bias"
+    #[cfg(feature = "serde")]
+    MissingKey(String),
+
+    /// a state dict entry that matches no parameter of the network
+    #[cfg(feature = "serde")]
+    UnexpectedKey(String),
+
+    /// a checkpoint's optimizer refers to a parameter past the end of its network
+    #[cfg(feature = "serde")]
+    ParameterIndex {
+        index: usize,
+        count: usize,
+    },
+
+    /// a checkpoint's optimizer momentum outside `[0, 1)`
+    #[cfg(feature = "serde")]
+    Momentum(Scalar),
+
+    /// a state dict tensor has the wrong shape, `[nout, nin]` for weights and `[nout]`
+    /// for biases
+    #[cfg(feature = "serde")]
+    TensorShape {
+        key: String,
+        expected: Vec<usize>,
+        got: Vec<usize>,
+    },
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(e) => write!(f, "cannot read weights: {}", e),
+            LoadError::BadMagic => write!(f, "not a weights file"),
+            LoadError::UnsupportedVersion(v) => write!(f, "unsupported weights version {}", v),
+            LoadError::ScalarWidth { expected, got } => write!(
+                f,
+                "weights are stored as {}-byte scalars, expected {}",
+                got, expected
+            ),
+            LoadError::Truncated => write!(f, "weights file is truncated"),
+            LoadError::TrailingBytes(n) => {
+                write!(f, "{} unexpected bytes after the last weight", n)
+            }
+            LoadError::Architecture { expected, got } => write!(
+                f,
+                "weights are for sizes {:?}, network has {:?}",
+                got, expected
+            ),
+            LoadError::Bias {
+                layer,
+                neuron,
+                expected,
+            } => write!(
+                f,
+                "neuron {} of layer {} has {}, the weights have {}",
+                neuron,
+                layer,
+                if *expected { "a bias" } else { "no bias" },
+                if *expected { "none" } else { "one" }
+            ),
+            LoadError::ArchitectureHash { expected, got } => write!(
+                f,
+                "weights are for architecture {:016x}, network has {:016x}",
+                got, expected
+            ),
+            #[cfg(feature = "serde")]
+            LoadError::Json(e) => write!(f, "invalid network description: {}", e),
+            LoadError::LayerCount { sizes, layers } => write!(
+                f,
+                "{} sizes cannot describe {} layers, expected {}",
+                sizes,
+                layers,
+                layers + 1
+            ),
+            LoadError::LayerWidth {
+                layer,
+                expected,
+                got,
+            } => write!(
+                f,
+                "layer {} should have {} neurons, got {}",
+                layer, expected, got
+            ),
+            LoadError::NeuronInputs {
+                layer,
+                neuron,
+                expected,
+                got,
+            } => write!(
+                f,
+                "neuron {} of layer {} should have {} weights, got {}",
+                neuron, layer, expected, got
+            ),
+            #[cfg(feature = "serde")]
+            LoadError::MissingKey(key) => write!(f, "state dict has no {:?}", key),
+            #[cfg(feature = "serde")]
+            LoadError::UnexpectedKey(key) => {
+                write!(f, "state dict entry {:?} matches no parameter", key)
+            }
+            #[cfg(feature = "serde")]
+            LoadError::ParameterIndex { index, count } => write!(
+                f,
+                "optimizer refers to parameter {}, network has {}",
+                index, count
+            ),
+            #[cfg(feature = "serde")]
+            LoadError::Momentum(m) => write!(f, "momentum must be in [0, 1), got {}", m),
+            #[cfg(feature = "serde")]
+            LoadError::TensorShape { key, expected, got } => write!(
+                f,
+                "state dict entry {:?} has shape {:?}, expected {:?}",
+                key, got, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(e) => Some(e),
+            #[cfg(feature = "serde")]
+            LoadError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        LoadError::Io(e)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for LoadError {
+    fn from(e: serde_json::Error) -> Self {
+        LoadError::Json(e)
+    }
+}
+
+/// Reads the binary format front to back
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], LoadError> {
+        if self.bytes.len() < n {
+            return Err(LoadError::Truncated);
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32, LoadError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn scalar(&mut self) -> Result<Scalar, LoadError> {
+        let bytes = self.take(SCALAR_BYTES)?;
+        Ok(Scalar::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+impl MLP {
+    pub fn save_weights(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let sizes = self.sizes();
+        let params = self.get_parameters();
+        let mut bytes = Vec::with_capacity(16 + 4 * sizes.len() + SCALAR_BYTES * params.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(SCALAR_BYTES as u32).to_le_bytes());
+        bytes.extend_from_slice(&(sizes.len() as u32).to_le_bytes());
+        for s in sizes {
+            bytes.extend_from_slice(&(s as u32).to_le_bytes());
+        }
+        for layer in self.layers() {
+            bytes.extend(layer.neurons().iter().map(|n| u8::from(n.bias().is_some())));
+        }
+        bytes.extend_from_slice(&self.architecture_hash().to_le_bytes());
+        for p in params {
+            bytes.extend_from_slice(&p.data().to_le_bytes());
+        }
+        std::fs::write(path, bytes)
+    }
+
+    /// Copy stored weights into this network's existing parameters, so anything keyed by
+    /// `Value::id` (e.g. optimizer state) stays valid. The whole file is checked before
+    /// any parameter changes.
+    pub fn load_weights(&mut self, path: impl AsRef<Path>) -> Result<(), LoadError> {
+        let bytes = std::fs::read(path)?;
+        let mut reader = Reader { bytes: &bytes };
+        if reader.take(MAGIC.len()).map_err(|_| LoadError::BadMagic)? != MAGIC {
+            return Err(LoadError::BadMagic);
+        }
+        let version = reader.u32()?;
+        if !(1..=VERSION).contains(&version) {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+        let width = reader.u32()? as usize;
+        if width != SCALAR_BYTES {
+            return Err(LoadError::ScalarWidth {
+                expected: SCALAR_BYTES,
+                got: width,
+            });
+        }
+        let count = reader.u32()? as usize;
+        // a corrupt count must not turn into a huge allocation
+        if count > reader.bytes.len() / 4 {
+            return Err(LoadError::Truncated);
+        }
+        let got = (0..count)
+            .map(|_| reader.u32().map(|s| s as usize))
+            .collect::<Result<Vec<_>, _>>()?;
+        let expected = self.sizes();
+        if got != expected {
+            return Err(LoadError::Architecture { expected, got });
+        }
+        for (layer, l) in self.layers().iter().enumerate() {
+            for (neuron, n) in l.neurons().iter().enumerate() {
+                let stored = version == 1 || reader.take(1)?[0] != 0;
+                let expected = n.bias().is_some();
+                if stored != expected {
+                    return Err(LoadError::Bias {
+                        layer,
+                        neuron,
+                        expected,
+                    });
+                }
+            }
+        }
+        if version >= 3 {
+            let got = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+            let expected = self.architecture_hash();
+            if got != expected {
+                return Err(LoadError::ArchitectureHash { expected, got });
+            }
+        }
+
+        let params = self.get_parameters();
+        let data = params
+            .iter()
+            .map(|_| reader.scalar())
+            .collect::<Result<Vec<_>, _>>()?;
+        if !reader.bytes.is_empty() {
+            return Err(LoadError::TrailingBytes(reader.bytes.len()));
+        }
+        for (p, d) in params.iter().zip(data) {
+            p.set_data(d);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,368 @@
+//! JSON description of an MLP: layer sizes, activations, init schemes and parameter
+//! data, import of PyTorch-style state dicts, and full training checkpoints
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::LoadError;
+use crate::nn::{Layer, Neuron};
+use crate::optim::Sgd;
+use crate::{Activation, Init, Scalar, Value, MLP};
+
+/// Plain-data description of an MLP
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct MlpState {
+    /// input size followed by the width of every layer
+    sizes: Vec<usize>,
+    layers: Vec<LayerState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LayerState {
+    activation: Activation,
+
+    /// see `Layer::init`, left out when unknown
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    init: Option<Init>,
+    neurons: Vec<NeuronState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct NeuronState {
+    w: Vec<Scalar>,
+
+    /// left out for a neuron without a bias
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    b: Option<Scalar>,
+}
+
+impl MlpState {
+    fn of(mlp: &MLP) -> Self {
+        let sizes = mlp.sizes();
+        let layers = mlp
+            .layers()
+            .iter()
+            .map(|l| LayerState {
+                activation: l
+                    .neurons()
+                    .first()
+                    .map_or(Activation::default(), Neuron::activation),
+                init: l.init(),
+                neurons: l
+                    .neurons()
+                    .iter()
+                    .map(|n| NeuronState {
+                        w: n.weights().iter().map(Value::data).collect(),
+                        b: n.bias().map(Value::data),
+                    })
+                    .collect(),
+            })
+            .collect();
+        MlpState { sizes, layers }
+    }
+
+    /// Check every shape before building anything, so a bad file can't reach the
+    /// input-size assert in `Neuron::apply`
+    fn validate(&self) -> Result<(), LoadError> {
+        if self.sizes.len() != self.layers.len() + 1 {
+            return Err(LoadError::LayerCount {
+                sizes: self.sizes.len(),
+                layers: self.layers.len(),
+            });
+        }
+        for (i, (layer, io)) in self.layers.iter().zip(self.sizes.windows(2)).enumerate() {
+            if layer.neurons.len() != io[1] {
+                return Err(LoadError::LayerWidth {
+                    layer: i,
+                    expected: io[1],
+                    got: layer.neurons.len(),
+                });
+            }
+            for (j, n) in layer.neurons.iter().enumerate() {
+                if n.w.len() != io[0] {
+                    return Err(LoadError::NeuronInputs {
+                        layer: i,
+                        neuron: j,
+                        expected: io[0],
+                        got: n.w.len(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn build(self) -> Result<MLP, LoadError> {
+        self.validate()?;
+        Ok(MLP::from_layers(
+            self.layers
+                .into_iter()
+                .map(|l| {
+                    Layer::from_neurons(
+                        l.neurons
+                            .into_iter()
+                            .map(|n| Neuron::from_parts(n.w, n.b, l.activation))
+                            .collect(),
+                    )
+                    .with_init(l.init)
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl MLP {
+    /// Layer sizes, activations, init schemes and parameter data as JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&MlpState::of(self)).expect("network state is plain data")
+    }
+
+    pub fn from_json(json: &str) -> Result<MLP, LoadError> {
+        serde_json::from_str::<MlpState>(json)?.build()
+    }
+}
+
+/// A state dict entry: a weight matrix or a bias vector
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum Tensor {
+    Matrix(Vec<Vec<Scalar>>),
+    Vector(Vec<Scalar>),
+}
+
+impl Tensor {
+    fn shape(&self) -> Vec<usize> {
+        match self {
+            Tensor::Matrix(rows) => {
+                let width = rows.first().map_or(0, Vec::len);
+                // a ragged matrix reports its first row that differs
+                let width = rows
+                    .iter()
+                    .map(Vec::len)
+                    .find(|&w| w != width)
+                    .unwrap_or(width);
+                vec![rows.len(), width]
+            }
+            Tensor::Vector(v) => vec![v.len()],
+        }
+    }
+
+    fn into_flat(self) -> Vec<Scalar> {
+        match self {
+            Tensor::Matrix(rows) => rows.into_iter().flatten().collect(),
+            Tensor::Vector(v) => v,
+        }
+    }
+}
+
+/// Whether a state dict holds a bias entry for `layer`
+fn has_bias(layer: &Layer) -> bool {
+    layer.neurons().iter().any(|n| n.bias().is_some())
+}
+
+impl MLP {
+    /// Copy the weights of a PyTorch-style state dict,
+    /// `{"layers.0.weight": [[..], ..], "layers.0.bias": [..], ..}`, into this network's
+    /// existing parameters. Weights are `[nout, nin]` row-major, so row `j` holds the
+    /// weights of neuron `j`. Layers without biases take no bias entry. Every entry is
+    /// checked before any parameter changes.
+    pub fn load_state_dict_json(&mut self, json: &str) -> Result<(), LoadError> {
+        let mut dict: BTreeMap<String, Tensor> = serde_json::from_str(json)?;
+        let sizes = self.sizes();
+        let mut data = Vec::with_capacity(self.num_parameters());
+        let mut tensors = vec![];
+        for (i, (io, layer)) in sizes.windows(2).zip(self.layers()).enumerate() {
+            let (nin, nout) = (io[0], io[1]);
+            let mut entries = vec![("weight", vec![nout, nin])];
+            if has_bias(layer) {
+                entries.push(("bias", vec![nout]));
+            }
+            for (name, expected) in entries {
+                let key = format!("layers.{}.{}", i, name);
+                let tensor = dict
+                    .remove(&key)
+                    .ok_or_else(|| LoadError::MissingKey(key.clone()))?;
+                // an empty bias parses as an empty matrix
+                let got = match (&tensor, expected.len()) {
+                    (Tensor::Matrix(rows), 1) if rows.is_empty() => vec![0],
+                    _ => tensor.shape(),
+                };
+                if got != expected {
+                    return Err(LoadError::TensorShape { key, expected, got });
+                }
+                tensors.push(tensor.into_flat());
+            }
+        }
+        if let Some(key) = dict.into_keys().next() {
+            return Err(LoadError::UnexpectedKey(key));
+        }
+
+        // `get_parameters` order: per neuron, its weights then its bias
+        let mut tensors = tensors.into_iter();
+        for (nin, layer) in sizes.iter().zip(self.layers()) {
+            let w = tensors.next().unwrap();
+            let b = if has_bias(layer) {
+                tensors.next()
+            } else {
+                None
+            };
+            for (j, n) in layer.neurons().iter().enumerate() {
+                data.extend_from_slice(&w[j * nin..(j + 1) * nin]);
+                if n.bias().is_some() {
+                    data.push(b.as_ref().unwrap()[j]);
+                }
+            }
+        }
+        for (p, d) in self.get_parameters().iter().zip(data) {
+            p.set_data(d);
+        }
+        Ok(())
+    }
+}
+
+/// Version of the `Checkpoint` format, bumped on any incompatible change
+const CHECKPOINT_VERSION: u32 = 1;
+
+/// Everything needed to resume a training run where it stopped: the network, the
+/// optimizer with its momentum buffers, how many epochs ran and the seed of the
+/// mini-batch shuffling. Resuming with `BatchIter::new(.., seed)` followed by
+/// `skip_epochs(epoch)` gives the same batches an uninterrupted run would have seen.
+///
+/// Stored as versioned JSON. The optimizer's parameters are recorded by their position
+/// in `model.get_parameters()`, so they have to be parameters of `model`. Like
+/// `MLP::to_json`, dropout is not stored.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub model: MLP,
+    pub optimizer: Sgd,
+
+    /// epochs completed
+    pub epoch: usize,
+    pub seed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CheckpointState {
+    version: u32,
+    model: MlpState,
+    optimizer: SgdState,
+    epoch: usize,
+    seed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SgdState {
+    lr: Scalar,
+    momentum: Scalar,
+    weight_decay: Scalar,
+    params: Vec<SgdParam>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SgdParam {
+    /// position in `MLP::get_parameters`
+    index: usize,
+    velocity: Scalar,
+}
+
+/// Only the version, read first so other versions are rejected whatever their layout
+#[derive(Deserialize)]
+struct Versioned {
+    version: u32,
+}
+
+impl Checkpoint {
+    pub fn new(model: MLP, optimizer: Sgd, epoch: usize, seed: u64) -> Self {
+        Checkpoint {
+            model,
+            optimizer,
+            epoch,
+            seed,
+        }
+    }
+
+    /// Fails with `InvalidInput` if the optimizer updates a value that is not a parameter
+    /// of the model
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let index: HashMap<Value, usize> = self
+            .model
+            .get_parameters()
+            .into_iter()
+            .enumerate()
+            .map(|(i, p)| (p, i))
+            .collect();
+        let params = self
+            .optimizer
+            .params()
+            .iter()
+            .map(|p| match index.get(p) {
+                Some(&index) => Ok(SgdParam {
+                    index,
+                    velocity: self.optimizer.velocity(p),
+                }),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("optimizer parameter {} is not part of the model", p.id()),
+                )),
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let state = CheckpointState {
+            version: CHECKPOINT_VERSION,
+            model: MlpState::of(&self.model),
+            optimizer: SgdState {
+                lr: self.optimizer.lr(),
+                momentum: self.optimizer.momentum(),
+                weight_decay: self.optimizer.weight_decay(),
+                params,
+            },
+            epoch: self.epoch,
+            seed: self.seed,
+        };
+        let json = serde_json::to_string(&state).expect("checkpoint state is plain data");
+        std::fs::write(path, json)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Checkpoint, LoadError> {
+        let json = std::fs::read_to_string(path)?;
+        let Versioned { version } = serde_json::from_str(&json)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+        let state: CheckpointState = serde_json::from_str(&json)?;
+        let model = state.model.build()?;
+        let params = model.get_parameters();
+        let opt = state.optimizer;
+        if !(0.0..1.0).contains(&opt.momentum) {
+            return Err(LoadError::Momentum(opt.momentum));
+        }
+        if let Some(p) = opt.params.iter().find(|p| p.index >= params.len()) {
+            return Err(LoadError::ParameterIndex {
+                index: p.index,
+                count: params.len(),
+            });
+        }
+        let mut optimizer = Sgd::with_momentum(
+            opt.params.iter().map(|p| params[p.index].clone()).collect(),
+            opt.lr,
+            opt.momentum,
+        );
+        optimizer.set_weight_decay(opt.weight_decay);
+        for p in &opt.params {
+            optimizer.set_velocity(&params[p.index], p.velocity);
+        }
+        Ok(Checkpoint {
+            model,
+            optimizer,
+            epoch: state.epoch,
+            seed: state.seed,
+        })
+    }
+
+    /// The network, the optimizer over its parameters and the epochs completed
+    pub fn into_parts(self) -> (MLP, Sgd, usize) {
+        (self.model, self.optimizer, self.epoch)
+    }
+}
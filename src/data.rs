@@ -0,0 +1,341 @@
+use std::collections::BTreeMap;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::metrics::argmax_of;
+use crate::{Scalar, Value};
+
+/// Reasons a dataset cannot be split
+#[derive(Debug, Clone, PartialEq)]
+pub enum SplitError {
+    /// inputs and targets have different lengths
+    LengthMismatch { inputs: usize, targets: usize },
+
+    /// the test fraction is not strictly between 0 and 1
+    InvalidFraction(Scalar),
+
+    /// a class needs at least one sample on each side of the split
+    TooFewSamples { class: usize, count: usize },
+}
+
+impl std::fmt::Display for SplitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SplitError::LengthMismatch { inputs, targets } => {
+                write!(f, "got {} inputs but {} targets", inputs, targets)
+            }
+            SplitError::InvalidFraction(fraction) => {
+                write!(f, "test fraction must be in (0, 1), got {}", fraction)
+            }
+            SplitError::TooFewSamples { class, count } => {
+                write!(
+                    f,
+                    "class {} has {} sample(s), at least 2 are needed to stratify",
+                    class, count
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SplitError {}
+
+/// Train and test sides of a split dataset
+#[derive(Debug, Clone, Default)]
+pub struct Split {
+    pub train_inputs: Vec<Vec<Scalar>>,
+    pub train_targets: Vec<usize>,
+    pub test_inputs: Vec<Vec<Scalar>>,
+    pub test_targets: Vec<usize>,
+}
+
+/// Split a classification dataset so that both sides keep the class proportions.
+///
+/// Each class sends `round(count * test_fraction)` samples to the test side, clamped so
+/// that both sides get at least one sample of every class.
+pub fn stratified_split(
+    inputs: &[Vec<Scalar>],
+    class_targets: &[usize],
+    test_fraction: Scalar,
+    seed: u64,
+) -> Result<Split, SplitError> {
+    if inputs.len() != class_targets.len() {
+        return Err(SplitError::LengthMismatch {
+            inputs: inputs.len(),
+            targets: class_targets.len(),
+        });
+    }
+    if !(test_fraction > 0.0 && test_fraction < 1.0) {
+        return Err(SplitError::InvalidFraction(test_fraction));
+    }
+
+    let mut by_class: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (i, &class) in class_targets.iter().enumerate() {
+        by_class.entry(class).or_default().push(i);
+    }
+    if let Some((&class, indices)) = by_class.iter().find(|(_, indices)| indices.len() < 2) {
+        return Err(SplitError::TooFewSamples {
+            class,
+            count: indices.len(),
+        });
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut train = vec![];
+    let mut test = vec![];
+    for indices in by_class.values_mut() {
+        indices.shuffle(&mut rng);
+        let n_test = ((indices.len() as Scalar * test_fraction).round() as usize)
+            .clamp(1, indices.len() - 1);
+        test.extend_from_slice(&indices[..n_test]);
+        train.extend_from_slice(&indices[n_test..]);
+    }
+    // interleave the classes on each side
+    train.shuffle(&mut rng);
+    test.shuffle(&mut rng);
+
+    let mut split = Split::default();
+    for i in train {
+        split.train_inputs.push(inputs[i].clone());
+        split.train_targets.push(class_targets[i]);
+    }
+    for i in test {
+        split.test_inputs.push(inputs[i].clone());
+        split.test_targets.push(class_targets[i]);
+    }
+    Ok(split)
+}
+
+/// Cut a series into input windows and the `horizon` values that follow each one.
+///
+/// Windows start at `0, stride, 2 * stride, ..`; a window is only emitted if its whole
+/// horizon fits inside the series, so a trailing partial window is dropped.
+pub fn sliding_windows(
+    series: &[Scalar],
+    window: usize,
+    horizon: usize,
+    stride: usize,
+) -> (Vec<Vec<Scalar>>, Vec<Vec<Scalar>>) {
+    assert!(window > 0, "window must be at least 1");
+    assert!(stride > 0, "stride must be at least 1");
+    let mut inputs = vec![];
+    let mut targets = vec![];
+    let mut start = 0;
+    while start + window + horizon <= series.len() {
+        inputs.push(series[start..start + window].to_vec());
+        targets.push(series[start + window..start + window + horizon].to_vec());
+        start += stride;
+    }
+    (inputs, targets)
+}
+
+/// `num_classes` numbers, 1 at `label` and 0 elsewhere, e.g. as a target for `predict`
+pub fn one_hot_scalars(label: usize, num_classes: usize) -> Vec<Scalar> {
+    assert!(
+        label < num_classes,
+        "label {} out of range for {} classes",
+        label,
+        num_classes
+    );
+    let mut encoded = vec![0.0; num_classes];
+    encoded[label] = 1.0;
+    encoded
+}
+
+/// `one_hot_scalars` as leaf values, a target vector for the losses
+pub fn one_hot(label: usize, num_classes: usize) -> Vec<Value> {
+    one_hot_scalars(label, num_classes)
+        .into_iter()
+        .map(Value::new)
+        .collect()
+}
+
+/// `one_hot` of every label, e.g. the targets of `mse_batch`
+pub fn labels_to_targets(labels: &[usize], num_classes: usize) -> Vec<Vec<Value>> {
+    labels.iter().map(|&l| one_hot(l, num_classes)).collect()
+}
+
+/// Index of the largest value's data, the class an output vector decodes to. Ties go
+/// to the lowest index and NaNs are never picked.
+pub fn argmax(values: &[Value]) -> usize {
+    argmax_scalars(&values.iter().map(Value::data).collect::<Vec<_>>())
+}
+
+/// `argmax` over plain numbers, e.g. the output of `predict`
+pub fn argmax_scalars(values: &[Scalar]) -> usize {
+    assert!(!values.is_empty(), "argmax of no values");
+    argmax_of(values)
+}
+
+/// A batch of variable-length sequences padded to the longest one
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceBatch {
+    /// one row per sequence, padded with zeros
+    pub data: Vec<Vec<Scalar>>,
+
+    /// 1.0 for real steps and 0.0 for padding, same shape as `data`
+    pub mask: Vec<Vec<Scalar>>,
+
+    /// unpadded length of every sequence
+    pub lengths: Vec<usize>,
+}
+
+/// Batches whole sequences in order; the last batch may be smaller
+#[derive(Debug, Clone)]
+pub struct SequenceLoader {
+    sequences: Vec<Vec<Scalar>>,
+    batch_size: usize,
+    position: usize,
+}
+
+impl SequenceLoader {
+    pub fn new(sequences: Vec<Vec<Scalar>>, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "batch size must be at least 1");
+        SequenceLoader {
+            sequences,
+            batch_size,
+            position: 0,
+        }
+    }
+}
+
+impl Iterator for SequenceLoader {
+    type Item = SequenceBatch;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.sequences.len() {
+            return None;
+        }
+        let end = (self.position + self.batch_size).min(self.sequences.len());
+        let batch = &self.sequences[self.position..end];
+        self.position = end;
+
+        let width = batch.iter().map(Vec::len).max().unwrap_or(0);
+        let mut data = Vec::with_capacity(batch.len());
+        let mut mask = Vec::with_capacity(batch.len());
+        for s in batch {
+            let mut row = s.clone();
+            row.resize(width, 0.0);
+            data.push(row);
+            let mut m = vec![1.0; s.len()];
+            m.resize(width, 0.0);
+            mask.push(m);
+        }
+        Some(SequenceBatch {
+            data,
+            mask,
+            lengths: batch.iter().map(Vec::len).collect(),
+        })
+    }
+}
+
+/// What `BatchIter` does with the samples left over when the batch size doesn't divide
+/// the dataset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LastBatch {
+    /// yield them as a smaller final batch
+    #[default]
+    Keep,
+
+    /// skip them for this epoch; being reshuffled, they are likely used in the next
+    Drop,
+}
+
+/// Inputs and targets of one mini-batch
+pub type Batch = (Vec<Vec<Scalar>>, Vec<Vec<Scalar>>);
+
+/// Shuffled mini-batches of paired inputs and targets, reshuffled every epoch
+#[derive(Debug, Clone)]
+pub struct BatchIter<'a> {
+    xs: &'a [Vec<Scalar>],
+    ys: &'a [Vec<Scalar>],
+    batch_size: usize,
+    last_batch: LastBatch,
+    rng: StdRng,
+}
+
+impl<'a> BatchIter<'a> {
+    /// Panics unless xs and ys have the same length and every row of each has the width
+    /// of its first one. The same seed gives the same sequence of epochs.
+    pub fn new(xs: &'a [Vec<Scalar>], ys: &'a [Vec<Scalar>], batch_size: usize, seed: u64) -> Self {
+        assert!(batch_size > 0, "batch size must be at least 1");
+        assert_eq!(
+            xs.len(),
+            ys.len(),
+            "got {} inputs but {} targets",
+            xs.len(),
+            ys.len()
+        );
+        check_widths(xs, "input");
+        check_widths(ys, "target");
+        BatchIter {
+            xs,
+            ys,
+            batch_size,
+            last_batch: LastBatch::default(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn with_last_batch(mut self, last_batch: LastBatch) -> Self {
+        self.last_batch = last_batch;
+        self
+    }
+
+    /// Batches per epoch
+    pub fn num_batches(&self) -> usize {
+        match self.last_batch {
+            LastBatch::Keep => self.xs.len().div_ceil(self.batch_size),
+            LastBatch::Drop => self.xs.len() / self.batch_size,
+        }
+    }
+
+    /// Reshuffle and return the sample indices of every batch of the next epoch
+    pub fn epoch_indices(&mut self) -> Vec<Vec<usize>> {
+        let mut order: Vec<usize> = (0..self.xs.len()).collect();
+        order.shuffle(&mut self.rng);
+        order
+            .chunks(self.batch_size)
+            .take(self.num_batches())
+            .map(<[usize]>::to_vec)
+            .collect()
+    }
+
+    /// Advance the shuffling past `epochs` epochs, so an iterator rebuilt with the same
+    /// seed, e.g. when resuming from a `Checkpoint`, carries on with the same batches
+    pub fn skip_epochs(&mut self, epochs: usize) {
+        for _ in 0..epochs {
+            self.epoch_indices();
+        }
+    }
+
+    /// `epoch_indices` with the rows copied out, as (inputs, targets) per batch
+    pub fn epoch(&mut self) -> Vec<Batch> {
+        self.epoch_indices()
+            .into_iter()
+            .map(|batch| {
+                let xs = batch.iter().map(|&i| self.xs[i].clone()).collect();
+                let ys = batch.iter().map(|&i| self.ys[i].clone()).collect();
+                (xs, ys)
+            })
+            .collect()
+    }
+}
+
+fn check_widths(rows: &[Vec<Scalar>], what: &str) {
+    if let Some(first) = rows.first() {
+        for (i, row) in rows.iter().enumerate() {
+            assert_eq!(
+                row.len(),
+                first.len(),
+                "{} row {} has {} values, expected {}",
+                what,
+                i,
+                row.len(),
+                first.len()
+            );
+        }
+    }
+}
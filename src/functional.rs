@@ -0,0 +1,29 @@
+//! Helpers building common expressions out of the basic ops
+
+use crate::Value;
+
+/// `Σ a_i * b_i` as one product node per pair feeding a single `Op::Sum`. Empty slices
+/// give a zero leaf.
+pub fn dot(a: &[Value], b: &[Value]) -> Value {
+    assert_eq!(
+        a.len(),
+        b.len(),
+        "dot product of slices of length {} and {}",
+        a.len(),
+        b.len()
+    );
+    let products: Vec<Value> = a.iter().zip(b).map(|(x, y)| x * y).collect();
+    Value::sum(&products)
+}
+
+/// `Value::sum` that panics on an empty slice instead of giving a zero leaf
+pub fn sum(values: &[Value]) -> Value {
+    assert!(!values.is_empty(), "cannot sum no values");
+    Value::sum(values)
+}
+
+/// `Σ v_i / n` as a single `Op::Mean` node, so each element gets `1/n` of the grad.
+/// Panics on an empty slice.
+pub fn mean(values: &[Value]) -> Value {
+    Value::mean(values)
+}
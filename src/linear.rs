@@ -0,0 +1,135 @@
+use crate::{Activation, Layer, Neuron, Scalar, MLP};
+
+/// Ridge strength used when the normal equations are singular
+const FALLBACK_RIDGE: f64 = 1e-6;
+
+/// Pivots smaller than this are treated as zero during elimination
+const SINGULAR_EPS: f64 = 1e-10;
+
+/// Closed-form linear baseline: `y = weights · x + bias`
+#[derive(Debug, Clone)]
+pub struct LinearModel {
+    pub weights: Vec<Scalar>,
+    pub bias: Scalar,
+
+    /// set when the system was singular and ridge regularization had to be added
+    pub ridge_lambda: Option<Scalar>,
+}
+
+impl LinearModel {
+    pub fn predict(&self, x: &[Scalar]) -> Scalar {
+        assert_eq!(
+            x.len(),
+            self.weights.len(),
+            "input has {} features, model expects {}",
+            x.len(),
+            self.weights.len()
+        );
+        self.weights
+            .iter()
+            .zip(x)
+            .fold(self.bias, |acc, (w, xi)| acc + w * xi)
+    }
+
+    /// A single linear neuron holding these exact weights, so the baseline can go
+    /// through the same evaluation as the networks it is compared with. Its `predict`
+    /// adds in the same order, so the outputs are identical.
+    pub fn to_mlp(&self) -> MLP {
+        let neuron = Neuron::from_parts(self.weights.clone(), Some(self.bias), Activation::Linear);
+        MLP::from_layers(vec![Layer::from_neurons(vec![neuron])])
+    }
+}
+
+/// Fit a linear model by solving the normal equations `(AᵀA) β = Aᵀy`,
+/// where `A` is the input matrix with an extra column of ones for the bias.
+///
+/// Meant for small feature counts: the system is solved with naive Gaussian elimination.
+/// If it turns out to be singular, a tiny ridge term is added to the weight diagonal
+/// and the model records that in `ridge_lambda`.
+// the f64 casts are no-ops with the f64 feature
+#[allow(clippy::unnecessary_cast)]
+pub fn fit_linear_least_squares(inputs: &[Vec<Scalar>], targets: &[Scalar]) -> LinearModel {
+    assert_eq!(
+        inputs.len(),
+        targets.len(),
+        "got {} inputs but {} targets",
+        inputs.len(),
+        targets.len()
+    );
+    assert!(!inputs.is_empty(), "cannot fit a linear model to no data");
+    let nin = inputs[0].len();
+    for (i, x) in inputs.iter().enumerate() {
+        assert_eq!(
+            x.len(),
+            nin,
+            "input row {} has {} features, expected {}",
+            i,
+            x.len(),
+            nin
+        );
+    }
+
+    // augmented design row: [x_0, .., x_{n-1}, 1]
+    let n = nin + 1;
+    let mut ata = vec![vec![0.0f64; n]; n];
+    let mut aty = vec![0.0f64; n];
+    for (x, &y) in inputs.iter().zip(targets) {
+        let row: Vec<f64> = x
+            .iter()
+            .map(|&v| v as f64)
+            .chain(std::iter::once(1.0))
+            .collect();
+        for i in 0..n {
+            aty[i] += row[i] * y as f64;
+            for j in 0..n {
+                ata[i][j] += row[i] * row[j];
+            }
+        }
+    }
+
+    let (beta, ridge_lambda) = match solve(ata.clone(), aty.clone()) {
+        Some(beta) => (beta, None),
+        None => {
+            // the bias column is left unregularized
+            for (i, row) in ata.iter_mut().enumerate().take(nin) {
+                row[i] += FALLBACK_RIDGE;
+            }
+            let beta = solve(ata, aty).expect("ridge-regularized normal equations are singular");
+            (beta, Some(FALLBACK_RIDGE as Scalar))
+        }
+    };
+
+    LinearModel {
+        weights: beta[..nin].iter().map(|&b| b as Scalar).collect(),
+        bias: beta[nin] as Scalar,
+        ridge_lambda,
+    }
+}
+
+/// Gaussian elimination with partial pivoting; `None` if the matrix is singular
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < SINGULAR_EPS {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..n {
+            let factor = a[row][col] / a[col][col];
+            let (upper, lower) = a.split_at_mut(row);
+            for (dst, src) in lower[0][col..].iter_mut().zip(&upper[col][col..]) {
+                *dst -= factor * src;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let tail: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - tail) / a[row][row];
+    }
+    Some(x)
+}
@@ -0,0 +1,195 @@
+use crate::functional::mean;
+use crate::value::{bce_loss, bce_with_logits_loss};
+use crate::{Op, Scalar, Value};
+
+/// Weighted sum of named loss terms that keeps each term readable for logging
+#[derive(Debug, Clone, Default)]
+pub struct CompositeLoss {
+    terms: Vec<(String, Value, Scalar)>,
+}
+
+impl CompositeLoss {
+    pub fn new() -> Self {
+        CompositeLoss::default()
+    }
+
+    pub fn add_term(&mut self, name: &str, value: Value, weight: Scalar) {
+        self.terms.push((name.into(), value, weight));
+    }
+
+    /// The single graph node `Σ weight_i * term_i` to run backward from
+    pub fn total(&self) -> Value {
+        assert!(!self.terms.is_empty(), "composite loss has no terms");
+        let mut weighted = self
+            .terms
+            .iter()
+            .map(|(_, value, weight)| Value::new(*weight) * value.clone());
+        let first = weighted.next().unwrap();
+        weighted.fold(first, |acc, t| acc + t)
+    }
+
+    /// Current (unweighted) data of every term, in insertion order
+    pub fn terms(&self) -> Vec<(String, Scalar)> {
+        self.terms
+            .iter()
+            .map(|(name, value, _)| (name.clone(), value.data()))
+            .collect()
+    }
+}
+
+/// `Σ (y_i - t_i)²`
+pub fn sum_squared_error(predictions: &[Value], targets: &[Value]) -> Value {
+    assert_eq!(
+        predictions.len(),
+        targets.len(),
+        "got {} predictions but {} targets",
+        predictions.len(),
+        targets.len()
+    );
+    assert!(
+        !predictions.is_empty(),
+        "cannot compute a loss over no predictions"
+    );
+    let mut total = (&predictions[0] - &targets[0]).pow(2.0);
+    for (y, t) in predictions.iter().zip(targets).skip(1) {
+        total += (y - t).pow(2.0);
+    }
+    total
+}
+
+/// Mean squared error, `Σ (y_i - t_i)² / n`
+pub fn mse(predictions: &[Value], targets: &[Value]) -> Value {
+    sum_squared_error(predictions, targets) / predictions.len() as Scalar
+}
+
+/// Mean absolute error, `Σ |y_i - t_i| / n`: less pulled around by outliers than `mse`
+pub fn mae(predictions: &[Value], targets: &[Value]) -> Value {
+    assert_eq!(
+        predictions.len(),
+        targets.len(),
+        "got {} predictions but {} targets",
+        predictions.len(),
+        targets.len()
+    );
+    assert!(
+        !predictions.is_empty(),
+        "cannot compute a loss over no predictions"
+    );
+    let errors: Vec<Value> = predictions
+        .iter()
+        .zip(targets)
+        .map(|(y, t)| (y - t).abs())
+        .collect();
+    Value::sum(&errors) / predictions.len() as Scalar
+}
+
+/// `max(0, 1 - label * score)` for a label of ±1: zero once the score is on the right
+/// side by a margin of 1, with grad `-label` before that
+pub fn hinge(score: &Value, label: Scalar) -> Value {
+    (1.0 - label * score).relu()
+}
+
+/// `r² / 2` for a residual `r = prediction - target` with `|r| <= delta`, and
+/// `delta * (|r| - delta / 2)` beyond: MSE near the target, MAE-like for outliers. Both
+/// pieces have grad `delta * sign(r)` at `|r| = delta`, so it is continuous there.
+///
+/// Built as `c² / 2 + delta * (|r| - |c|)` with `c` the residual clamped to `±delta`, so
+/// the piece is picked whenever the graph is evaluated, and `forward()` after moving
+/// the prediction across `delta` stays correct.
+pub fn huber(prediction: &Value, target: Scalar, delta: Scalar) -> Value {
+    assert!(delta > 0.0, "huber delta must be positive, got {}", delta);
+    let r = prediction - target;
+    let c = r.clamp(-delta, delta);
+    0.5 * c.pow(2.0) + delta * (r.abs() - c.abs())
+}
+
+/// `lambda * Σ w²`, to add to the loss so large weights are penalized through the graph
+pub fn l2_penalty(params: &[Value], lambda: Scalar) -> Value {
+    let squares: Vec<Value> = params.iter().map(|w| w * w).collect();
+    Value::sum(&squares) * lambda
+}
+
+/// Mean squared error over every element of a batch, e.g. the output of
+/// `MLP::apply_batch`: a single node for the whole dataset
+pub fn mse_batch(predictions: &[Vec<Value>], targets: &[Vec<Value>]) -> Value {
+    assert_eq!(
+        predictions.len(),
+        targets.len(),
+        "got {} prediction rows but {} target rows",
+        predictions.len(),
+        targets.len()
+    );
+    assert!(
+        !predictions.is_empty(),
+        "cannot compute a loss over no predictions"
+    );
+    let mut errors = vec![];
+    for (y, t) in predictions.iter().zip(targets) {
+        assert_eq!(
+            y.len(),
+            t.len(),
+            "got {} predictions but {} targets",
+            y.len(),
+            t.len()
+        );
+        errors.extend(y.iter().zip(t).map(|(y, t)| (y - t).pow(2.0)));
+    }
+    mean(&errors)
+}
+
+/// Largest logit as a plain number, subtracted before exponentiating so nothing overflows
+fn max_logit(logits: &[Value]) -> Scalar {
+    assert!(!logits.is_empty(), "need at least one logit");
+    logits
+        .iter()
+        .map(Value::data)
+        .fold(Scalar::NEG_INFINITY, Scalar::max)
+}
+
+/// Class probabilities `e^x_i / Σ e^x_j`, computed on logits shifted by their max. Every
+/// output depends on every logit through the shared sum, so backward yields the full
+/// Jacobian `d s_i / d x_j = s_i (δ_ij - s_j)`.
+pub fn softmax(logits: &[Value]) -> Vec<Value> {
+    let m = max_logit(logits);
+    let exps: Vec<Value> = logits.iter().map(|x| (x - m).exp()).collect();
+    let sum = Value::sum(&exps);
+    exps.iter().map(|e| e / &sum).collect()
+}
+
+/// `-ln(softmax(logits)[target_index])`, as `logsumexp(logits) - logits[target_index]`
+/// so large logits stay finite in both passes
+pub fn cross_entropy(logits: &[Value], target_index: usize) -> Value {
+    assert!(
+        target_index < logits.len(),
+        "target index {} out of range for {} logits",
+        target_index,
+        logits.len()
+    );
+    let m = max_logit(logits);
+    let exps: Vec<Value> = logits.iter().map(|x| (x - m).exp()).collect();
+    Value::sum(&exps).ln() + m - &logits[target_index]
+}
+
+pub(crate) fn check_binary_target(target: Scalar) {
+    assert!(
+        (0.0..=1.0).contains(&target),
+        "binary target must be in [0, 1], got {}",
+        target
+    );
+}
+
+/// `-(t ln p + (1 - t) ln(1 - p))` as a single node. The probability is clamped to
+/// `[1e-7, 1 - 1e-7]` so a saturated prediction gives a large but finite loss.
+pub fn binary_cross_entropy(prediction: &Value, target: Scalar) -> Value {
+    check_binary_target(target);
+    let loss = bce_loss(prediction.data(), target);
+    Value::from_op(loss, Op::Bce(prediction.clone(), target))
+}
+
+/// `binary_cross_entropy(logit.sigmoid(), target)` fused into one node, evaluated as
+/// `max(x, 0) - x t + ln(1 + e^-|x|)` so it stays exact for large-magnitude logits
+pub fn bce_with_logits(logit: &Value, target: Scalar) -> Value {
+    check_binary_target(target);
+    let loss = bce_with_logits_loss(logit.data(), target);
+    Value::from_op(loss, Op::BceWithLogits(logit.clone(), target))
+}
@@ -0,0 +1,173 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::functional::dot;
+use crate::nn::standard_normal;
+use crate::Value;
+
+/// Shape errors reported by matrix operations
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapeError {
+    /// the element count does not match `rows * cols`
+    DataLength {
+        rows: usize,
+        cols: usize,
+        len: usize,
+    },
+
+    /// the operands of a binary operation have incompatible shapes
+    Mismatch {
+        op: &'static str,
+        left: (usize, usize),
+        right: (usize, usize),
+    },
+}
+
+impl std::fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShapeError::DataLength { rows, cols, len } => {
+                write!(
+                    f,
+                    "{}x{} matrix needs {} values, got {}",
+                    rows,
+                    cols,
+                    rows * cols,
+                    len
+                )
+            }
+            ShapeError::Mismatch { op, left, right } => {
+                write!(
+                    f,
+                    "{}: incompatible shapes {}x{} and {}x{}",
+                    op, left.0, left.1, right.0, right.1
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShapeError {}
+
+/// Row-major matrix of Values, built entirely on the scalar ops
+#[derive(Debug, Clone)]
+pub struct ValueMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub data: Vec<Value>,
+}
+
+impl ValueMatrix {
+    pub fn from_values(rows: usize, cols: usize, data: Vec<Value>) -> Result<Self, ShapeError> {
+        if data.len() != rows * cols {
+            return Err(ShapeError::DataLength {
+                rows,
+                cols,
+                len: data.len(),
+            });
+        }
+        Ok(ValueMatrix { rows, cols, data })
+    }
+
+    pub fn zeros(rows: usize, cols: usize) -> Self {
+        let data = (0..rows * cols).map(|_| Value::new(0.0)).collect();
+        ValueMatrix { rows, cols, data }
+    }
+
+    /// Standard-normal entries drawn from a seeded generator
+    pub fn randn(rows: usize, cols: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let data = (0..rows * cols)
+            .map(|_| Value::new(standard_normal(&mut rng)))
+            .collect();
+        ValueMatrix { rows, cols, data }
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&Value> {
+        if row < self.rows && col < self.cols {
+            self.data.get(row * self.cols + col)
+        } else {
+            None
+        }
+    }
+
+    /// Matrix product; each entry is the `dot` of a row and a column, one product per
+    /// pair feeding a single sum node
+    pub fn matmul(&self, rhs: &ValueMatrix) -> Result<ValueMatrix, ShapeError> {
+        if self.cols != rhs.rows || self.cols == 0 {
+            return Err(ShapeError::Mismatch {
+                op: "matmul",
+                left: self.shape(),
+                right: rhs.shape(),
+            });
+        }
+        let mut data = Vec::with_capacity(self.rows * rhs.cols);
+        let columns: Vec<Vec<Value>> = (0..rhs.cols)
+            .map(|j| (0..rhs.rows).map(|k| rhs[(k, j)].clone()).collect())
+            .collect();
+        for row in self.data.chunks(self.cols) {
+            for column in &columns {
+                data.push(dot(row, column));
+            }
+        }
+        Ok(ValueMatrix {
+            rows: self.rows,
+            cols: rhs.cols,
+            data,
+        })
+    }
+
+    /// Add a `1 x cols` row vector to every row, e.g. a bias
+    pub fn add_row_vector(&self, row: &ValueMatrix) -> Result<ValueMatrix, ShapeError> {
+        if row.rows != 1 || row.cols != self.cols {
+            return Err(ShapeError::Mismatch {
+                op: "add_row_vector",
+                left: self.shape(),
+                right: row.shape(),
+            });
+        }
+        let data = self
+            .data
+            .iter()
+            .enumerate()
+            .map(|(i, v)| v.clone() + row.data[i % self.cols].clone())
+            .collect();
+        Ok(ValueMatrix {
+            rows: self.rows,
+            cols: self.cols,
+            data,
+        })
+    }
+
+    /// Apply an element-wise function, e.g. an activation
+    pub fn map(&self, f: impl Fn(&Value) -> Value) -> ValueMatrix {
+        ValueMatrix {
+            rows: self.rows,
+            cols: self.cols,
+            data: self.data.iter().map(f).collect(),
+        }
+    }
+
+    pub fn flatten(self) -> Vec<Value> {
+        self.data
+    }
+}
+
+impl std::ops::Index<(usize, usize)> for ValueMatrix {
+    type Output = Value;
+    fn index(&self, (row, col): (usize, usize)) -> &Self::Output {
+        assert!(
+            row < self.rows && col < self.cols,
+            "index ({}, {}) out of bounds for {}x{} matrix",
+            row,
+            col,
+            self.rows,
+            self.cols
+        );
+        &self.data[row * self.cols + col]
+    }
+}
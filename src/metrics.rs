@@ -0,0 +1,310 @@
+use crate::{Scalar, MLP};
+
+/// Positive and negative counts, panicking unless both classes are present
+fn class_counts(scores: &[Scalar], labels: &[u8]) -> (usize, usize) {
+    assert_eq!(
+        scores.len(),
+        labels.len(),
+        "got {} scores but {} labels",
+        scores.len(),
+        labels.len()
+    );
+    assert!(
+        labels.iter().all(|&l| l <= 1),
+        "binary labels must be 0 or 1"
+    );
+    let positives = labels.iter().filter(|&&l| l == 1).count();
+    let negatives = labels.len() - positives;
+    assert!(
+        positives > 0 && negatives > 0,
+        "need both positive and negative labels, got {} positive and {} negative",
+        positives,
+        negatives
+    );
+    (positives, negatives)
+}
+
+/// (threshold, true positives, false positives) for every distinct score, highest first.
+/// A sample is predicted positive when its score is `>= threshold`, so tied scores
+/// always move together.
+fn confusion_by_threshold(scores: &[Scalar], labels: &[u8]) -> Vec<(Scalar, usize, usize)> {
+    let mut order: Vec<usize> = (0..scores.len()).collect();
+    order.sort_by(|&a, &b| scores[b].total_cmp(&scores[a]));
+
+    let mut points: Vec<(Scalar, usize, usize)> = vec![];
+    let (mut tp, mut fp) = (0, 0);
+    for (k, &i) in order.iter().enumerate() {
+        if labels[i] == 1 {
+            tp += 1;
+        } else {
+            fp += 1;
+        }
+        let last_of_tie = order.get(k + 1).is_none_or(|&j| scores[j] != scores[i]);
+        if last_of_tie {
+            points.push((scores[i], tp, fp));
+        }
+    }
+    points
+}
+
+/// ROC curve as (threshold, false positive rate, true positive rate) points.
+///
+/// The first point uses an infinite threshold (nothing predicted positive), the last one
+/// the lowest score (everything predicted positive).
+pub fn roc_curve(scores: &[Scalar], labels: &[u8]) -> Vec<(Scalar, Scalar, Scalar)> {
+    let (positives, negatives) = class_counts(scores, labels);
+    std::iter::once((Scalar::INFINITY, 0, 0))
+        .chain(confusion_by_threshold(scores, labels))
+        .map(|(t, tp, fp)| {
+            (
+                t,
+                fp as Scalar / negatives as Scalar,
+                tp as Scalar / positives as Scalar,
+            )
+        })
+        .collect()
+}
+
+/// Area under the ROC curve by the trapezoid rule.
+/// Tied scores form a diagonal segment, i.e. count as half a correct ordering.
+pub fn auc(scores: &[Scalar], labels: &[u8]) -> Scalar {
+    roc_curve(scores, labels)
+        .windows(2)
+        .map(|w| {
+            let (_, fpr0, tpr0) = w[0];
+            let (_, fpr1, tpr1) = w[1];
+            (fpr1 - fpr0) * (tpr0 + tpr1) / 2.0
+        })
+        .sum()
+}
+
+/// The threshold with the highest F1 score, returned as (threshold, f1)
+pub fn best_threshold_by_f1(scores: &[Scalar], labels: &[u8]) -> (Scalar, Scalar) {
+    let (positives, _) = class_counts(scores, labels);
+    confusion_by_threshold(scores, labels)
+        .into_iter()
+        .map(|(t, tp, fp)| {
+            let fn_ = positives - tp;
+            let f1 = 2.0 * tp as Scalar / (2 * tp + fp + fn_) as Scalar;
+            (t, f1)
+        })
+        .fold((Scalar::NAN, -1.0), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        })
+}
+
+/// Fraction of `xs` whose argmax output from `model.predict` is the label
+pub fn accuracy(model: &MLP, xs: &[Vec<Scalar>], labels: &[usize]) -> Scalar {
+    let classes = predicted_classes(model, xs, labels, argmax_of);
+    fraction_correct(&classes, labels)
+}
+
+/// `confusion[label][predicted]` over `xs`, predictions being argmax outputs
+pub fn confusion_matrix(
+    model: &MLP,
+    xs: &[Vec<Scalar>],
+    labels: &[usize],
+    num_classes: usize,
+) -> Vec<Vec<usize>> {
+    let classes = predicted_classes(model, xs, labels, argmax_of);
+    count_confusion(&classes, labels, num_classes)
+}
+
+/// `accuracy` for a model with one output, e.g. a sigmoid or tanh: class 1 when the
+/// output is `>= threshold`
+pub fn binary_accuracy(
+    model: &MLP,
+    xs: &[Vec<Scalar>],
+    labels: &[usize],
+    threshold: Scalar,
+) -> Scalar {
+    let classes = predicted_classes(model, xs, labels, |y| threshold_class(y, threshold));
+    fraction_correct(&classes, labels)
+}
+
+/// 2x2 `confusion_matrix` of a thresholded single output
+pub fn binary_confusion_matrix(
+    model: &MLP,
+    xs: &[Vec<Scalar>],
+    labels: &[usize],
+    threshold: Scalar,
+) -> Vec<Vec<usize>> {
+    let classes = predicted_classes(model, xs, labels, |y| threshold_class(y, threshold));
+    count_confusion(&classes, labels, 2)
+}
+
+/// Accuracy on the samples of each class, i.e. the diagonal of a `confusion_matrix` over
+/// its row sums; `None` for a class without samples
+pub fn per_class_accuracy(confusion: &[Vec<usize>]) -> Vec<Option<Scalar>> {
+    confusion
+        .iter()
+        .enumerate()
+        .map(|(class, row)| {
+            let total: usize = row.iter().sum();
+            (total > 0).then(|| row[class] as Scalar / total as Scalar)
+        })
+        .collect()
+}
+
+fn predicted_classes(
+    model: &MLP,
+    xs: &[Vec<Scalar>],
+    labels: &[usize],
+    class: impl Fn(&[Scalar]) -> usize,
+) -> Vec<usize> {
+    assert_eq!(
+        xs.len(),
+        labels.len(),
+        "got {} inputs but {} labels",
+        xs.len(),
+        labels.len()
+    );
+    xs.iter().map(|x| class(&model.predict(x))).collect()
+}
+
+fn threshold_class(outputs: &[Scalar], threshold: Scalar) -> usize {
+    assert_eq!(
+        outputs.len(),
+        1,
+        "binary metrics need a single output, got {}",
+        outputs.len()
+    );
+    (outputs[0] >= threshold) as usize
+}
+
+fn fraction_correct(predicted: &[usize], labels: &[usize]) -> Scalar {
+    assert!(!labels.is_empty(), "cannot score no samples");
+    let correct = predicted.iter().zip(labels).filter(|(p, l)| p == l).count();
+    correct as Scalar / labels.len() as Scalar
+}
+
+fn count_confusion(predicted: &[usize], labels: &[usize], num_classes: usize) -> Vec<Vec<usize>> {
+    let mut confusion = vec![vec![0; num_classes]; num_classes];
+    for (&p, &l) in predicted.iter().zip(labels) {
+        assert!(
+            p < num_classes && l < num_classes,
+            "class index out of range for {} classes",
+            num_classes
+        );
+        confusion[l][p] += 1;
+    }
+    confusion
+}
+
+/// Index of the largest value, the lowest one on ties; NaNs are never picked
+pub(crate) fn argmax_of<T: PartialOrd + Copy>(values: &[T]) -> usize {
+    let mut best: Option<(usize, T)> = None;
+    for (i, &v) in values.iter().enumerate() {
+        // `v == v` is false only for NaN
+        #[allow(clippy::eq_op)]
+        let better = best.map_or(v == v, |(_, b)| v > b);
+        if better {
+            best = Some((i, v));
+        }
+    }
+    best.map_or(0, |(i, _)| i)
+}
+
+/// Final numbers from a `StreamingMetrics` pass
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsReport {
+    /// number of `update` calls
+    pub samples: usize,
+    pub mse: Scalar,
+    pub mae: Scalar,
+
+    /// only for classification
+    pub accuracy: Option<Scalar>,
+
+    /// `confusion[target][predicted]`, only for classification
+    pub confusion: Option<Vec<Vec<usize>>>,
+}
+
+/// Running evaluation metrics in O(1) memory.
+///
+/// Errors are averaged over every output element with Welford-style mean updates in f64,
+/// so long runs don't lose precision the way an f32 running sum would.
+#[derive(Debug, Clone, Default)]
+pub struct StreamingMetrics {
+    samples: usize,
+    elements: u64,
+    mean_squared: f64,
+    mean_abs: f64,
+    correct: usize,
+    confusion: Option<Vec<Vec<usize>>>,
+}
+
+impl StreamingMetrics {
+    /// Regression metrics only
+    pub fn new() -> Self {
+        StreamingMetrics::default()
+    }
+
+    /// Also track accuracy and a confusion matrix. The class of a multi-output vector is
+    /// its argmax (lowest index on ties); a single output is class 1 when `>= 0.5`.
+    pub fn classification(num_classes: usize) -> Self {
+        assert!(num_classes >= 2, "need at least 2 classes");
+        StreamingMetrics {
+            confusion: Some(vec![vec![0; num_classes]; num_classes]),
+            ..Default::default()
+        }
+    }
+
+    pub fn update(&mut self, pred: &[Scalar], target: &[Scalar]) {
+        assert_eq!(
+            pred.len(),
+            target.len(),
+            "prediction has {} outputs but target has {}",
+            pred.len(),
+            target.len()
+        );
+        for (p, t) in pred.iter().zip(target) {
+            // a no-op cast with the f64 feature
+            #[allow(clippy::unnecessary_cast)]
+            let err = (*p - *t) as f64;
+            self.elements += 1;
+            let n = self.elements as f64;
+            self.mean_squared += (err * err - self.mean_squared) / n;
+            self.mean_abs += (err.abs() - self.mean_abs) / n;
+        }
+        self.samples += 1;
+
+        if let Some(confusion) = self.confusion.as_mut() {
+            let (predicted, actual) = (class_of(pred), class_of(target));
+            assert!(
+                predicted < confusion.len() && actual < confusion.len(),
+                "class index out of range for {} classes",
+                confusion.len()
+            );
+            confusion[actual][predicted] += 1;
+            if predicted == actual {
+                self.correct += 1;
+            }
+        }
+    }
+
+    pub fn finalize(&self) -> MetricsReport {
+        MetricsReport {
+            samples: self.samples,
+            mse: self.mean_squared as Scalar,
+            mae: self.mean_abs as Scalar,
+            accuracy: self
+                .confusion
+                .as_ref()
+                .map(|_| self.correct as Scalar / self.samples as Scalar),
+            confusion: self.confusion.clone(),
+        }
+    }
+}
+
+/// Class of an output or target vector, as `StreamingMetrics::classification` reads them
+pub(crate) fn class_of(outputs: &[Scalar]) -> usize {
+    match outputs {
+        [single] => (*single >= 0.5) as usize,
+        _ => argmax_of(outputs),
+    }
+}
@@ -0,0 +1,935 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::shared::Slot;
+use crate::value::consts::PI;
+#[cfg(feature = "rayon")]
+use crate::value::BuildContext;
+use crate::value::{gelu, stable_sigmoid, with_site_tag, Fnv1a};
+use crate::{backward_graph, no_grad, Scalar, Value};
+
+mod builder;
+
+pub use builder::{BuildError, LayerConfig, MlpBuilder};
+
+/// How weights (and biases) are drawn when a network is built. The Xavier and He schemes
+/// scale the weights by the fan-in, and Xavier also by the fan-out, so activations keep
+/// roughly unit variance through deep stacks; their biases start at zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Init {
+    /// weights and bias uniform in `[lo, hi]`
+    Uniform { lo: Scalar, hi: Scalar },
+
+    /// weights and bias normal with the given mean and std
+    Normal { mean: Scalar, std: Scalar },
+
+    /// uniform in `±sqrt(6 / (nin + nout))`, suited to tanh and sigmoid
+    XavierUniform,
+
+    /// normal with std `sqrt(2 / (nin + nout))`
+    XavierNormal,
+
+    /// uniform in `±sqrt(6 / nin)`, suited to ReLU
+    HeUniform,
+
+    /// normal with std `sqrt(2 / nin)`
+    HeNormal,
+}
+
+impl Default for Init {
+    /// The original scheme: everything uniform in [-1, 1]
+    fn default() -> Self {
+        Init::Uniform { lo: -1.0, hi: 1.0 }
+    }
+}
+
+impl Init {
+    /// Why the parameters of the scheme can't be drawn from, if they can't
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        match *self {
+            Init::Uniform { lo, hi } if lo < hi => Ok(()),
+            Init::Uniform { lo, hi } => {
+                Err(format!("uniform init needs lo < hi, got [{}, {}]", lo, hi))
+            }
+            Init::Normal { std, .. } if std > 0.0 && std.is_finite() => Ok(()),
+            Init::Normal { std, .. } => Err(format!(
+                "normal init needs a positive, finite std, got {}",
+                std
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn weight(&self, nin: usize, nout: usize, rng: &mut impl Rng) -> Scalar {
+        let (nin, nout) = (nin as Scalar, nout as Scalar);
+        match *self {
+            Init::Uniform { lo, hi } => rng.gen_range(lo..=hi),
+            Init::Normal { mean, std } => mean + std * standard_normal(rng),
+            Init::XavierUniform => {
+                let limit = (6.0 / (nin + nout)).sqrt();
+                rng.gen_range(-limit..=limit)
+            }
+            Init::XavierNormal => (2.0 / (nin + nout)).sqrt() * standard_normal(rng),
+            Init::HeUniform => {
+                let limit = (6.0 / nin).sqrt();
+                rng.gen_range(-limit..=limit)
+            }
+            Init::HeNormal => (2.0 / nin).sqrt() * standard_normal(rng),
+        }
+    }
+
+    fn bias(&self, rng: &mut impl Rng) -> Scalar {
+        match *self {
+            Init::Uniform { lo, hi } => rng.gen_range(lo..=hi),
+            Init::Normal { mean, std } => mean + std * standard_normal(rng),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Box-Muller transform
+pub(crate) fn standard_normal(rng: &mut impl Rng) -> Scalar {
+    let u1: Scalar = 1.0 - rng.gen::<Scalar>();
+    let u2: Scalar = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// Nonlinearity applied to a neuron's weighted sum
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Activation {
+    #[default]
+    Tanh,
+    ReLU,
+    /// ReLU with the given slope for negative inputs
+    LeakyReLU(Scalar),
+    Sigmoid,
+    /// GELU, tanh approximation
+    Gelu,
+
+    /// no activation, e.g. for a regression head
+    Linear,
+}
+
+impl Activation {
+    pub fn apply(&self, x: Value) -> Value {
+        match self {
+            Activation::Tanh => x.tanh(),
+            Activation::ReLU => x.relu(),
+            Activation::LeakyReLU(slope) => x.leaky_relu(*slope),
+            Activation::Sigmoid => x.sigmoid(),
+            Activation::Gelu => x.gelu(),
+            Activation::Linear => x,
+        }
+    }
+
+    /// Lowercase name, e.g. "relu"
+    pub fn name(&self) -> &'static str {
+        match self {
+            Activation::Tanh => "tanh",
+            Activation::ReLU => "relu",
+            Activation::LeakyReLU(_) => "leaky_relu",
+            Activation::Sigmoid => "sigmoid",
+            Activation::Gelu => "gelu",
+            Activation::Linear => "linear",
+        }
+    }
+
+    /// `apply` on a plain number, for graph-free inference
+    pub fn eval(&self, x: Scalar) -> Scalar {
+        match self {
+            Activation::Tanh => x.tanh(),
+            Activation::ReLU => x.max(0.0),
+            Activation::LeakyReLU(slope) => {
+                if x > 0.0 {
+                    x
+                } else {
+                    slope * x
+                }
+            }
+            Activation::Sigmoid => stable_sigmoid(x),
+            Activation::Gelu => gelu(x),
+            Activation::Linear => x,
+        }
+    }
+}
+
+/// A single neuron: `act(w · x + b)`, with tanh unless configured otherwise
+#[derive(Debug, Clone)]
+pub struct Neuron {
+    /// weights
+    w: Vec<Value>,
+
+    /// bias; `None` for a neuron built without one
+    b: Option<Value>,
+
+    act: Activation,
+}
+
+impl Neuron {
+    /// Weights and bias are drawn uniformly from [-1, 1] and labeled "w0", .., "b"
+    pub fn new(nin: usize) -> Self {
+        Neuron::new_with_rng(nin, &mut rand::thread_rng())
+    }
+
+    pub fn new_with_rng(nin: usize, rng: &mut impl Rng) -> Self {
+        Neuron::new_with_init(nin, 1, Init::default(), rng)
+    }
+
+    /// Weights and bias drawn uniformly from `[lo, hi]`; panics unless `lo < hi`
+    pub fn new_uniform(nin: usize, lo: Scalar, hi: Scalar) -> Self {
+        let init = Init::Uniform { lo, hi };
+        Neuron::new_with_init(nin, 1, init, &mut rand::thread_rng())
+    }
+
+    /// Weights and bias drawn from a normal distribution; panics unless `std > 0`
+    pub fn new_normal(nin: usize, mean: Scalar, std: Scalar) -> Self {
+        let init = Init::Normal { mean, std };
+        Neuron::new_with_init(nin, 1, init, &mut rand::thread_rng())
+    }
+
+    /// `act(w · x)`: like `new`, but without a bias
+    pub fn new_no_bias(nin: usize) -> Self {
+        Neuron::new(nin).without_bias()
+    }
+
+    /// `nout` is the width of the layer the neuron belongs to; only Xavier uses it.
+    /// Panics if `init` has an empty range or a non-positive std.
+    pub fn new_with_init(nin: usize, nout: usize, init: Init, rng: &mut impl Rng) -> Self {
+        if let Err(e) = init.validate() {
+            panic!("{}", e);
+        }
+        let w = (0..nin).map(|_| init.weight(nin, nout, rng)).collect();
+        Neuron::from_parts(w, Some(init.bias(rng)), Activation::default())
+    }
+
+    /// Fresh leaves holding the given weights and bias
+    pub(crate) fn from_parts(w: Vec<Scalar>, b: Option<Scalar>, act: Activation) -> Self {
+        Neuron {
+            w: w.into_iter()
+                .enumerate()
+                .map(|(i, wi)| Value::new(wi).with_label(format!("w{}", i)))
+                .collect(),
+            b: b.map(|b| Value::new(b).with_label("b")),
+            act,
+        }
+    }
+
+    pub fn nin(&self) -> usize {
+        self.w.len()
+    }
+
+    /// Weights plus the bias, if any
+    pub fn num_parameters(&self) -> usize {
+        self.w.len() + usize::from(self.b.is_some())
+    }
+
+    /// Drop the bias, leaving `act(w · x)`
+    pub fn without_bias(mut self) -> Self {
+        self.b = None;
+        self
+    }
+
+    pub fn with_activation(mut self, act: Activation) -> Self {
+        self.act = act;
+        self
+    }
+
+    pub fn activation(&self) -> Activation {
+        self.act
+    }
+
+    pub fn apply(&self, x: &[Value]) -> Value {
+        assert_eq!(
+            x.len(),
+            self.w.len(),
+            "neuron expects {} inputs, got {}",
+            self.w.len(),
+            x.len()
+        );
+        // one fused node per input, accumulated onto the bias
+        let sum = with_site_tag("neuron.dot", || {
+            let mut terms = x.iter().zip(&self.w);
+            let start = match (&self.b, terms.next()) {
+                (Some(b), Some((xi, wi))) => xi.mul_add(wi, b),
+                (None, Some((xi, wi))) => xi * wi,
+                (Some(b), None) => b.clone(),
+                (None, None) => Value::new(0.0),
+            };
+            terms.fold(start, |s, (xi, wi)| xi.mul_add(wi, &s))
+        });
+        with_site_tag("neuron.activation", || self.act.apply(sum))
+    }
+
+    /// Forward pass on plain numbers, reading the parameters' data without building a graph
+    pub fn predict(&self, x: &[Scalar]) -> Scalar {
+        assert_eq!(
+            x.len(),
+            self.w.len(),
+            "neuron expects {} inputs, got {}",
+            self.w.len(),
+            x.len()
+        );
+        // the association of `apply`, so the two agree exactly
+        let start = self.b.as_ref().map_or(0.0, Value::data);
+        self.act.eval(
+            x.iter()
+                .zip(&self.w)
+                .fold(start, |acc, (xi, wi)| xi * wi.data() + acc),
+        )
+    }
+
+    /// Weights followed by the bias, if any
+    pub fn get_parameters(&self) -> Vec<Value> {
+        let mut params = self.w.clone();
+        params.extend(self.b.clone());
+        params
+    }
+
+    pub fn weights(&self) -> &[Value] {
+        &self.w
+    }
+
+    pub fn bias(&self) -> Option<&Value> {
+        self.b.as_ref()
+    }
+
+    pub fn zero_grad(&self) {
+        for p in self.get_parameters() {
+            p.zero_grad();
+        }
+    }
+}
+
+/// Neurons, or batch rows, from which a layer goes parallel with the `rayon` feature
+const DEFAULT_PARALLEL_THRESHOLD: usize = 32;
+
+/// `f` over `items` in order; on the rayon pool when there are at least `threshold` of
+/// them, building values under the calling thread's `no_grad`/`with_tag` settings
+#[cfg(feature = "rayon")]
+fn map_items<T: Sync, R: Send>(
+    items: &[T],
+    threshold: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    if items.len() < threshold {
+        return items.iter().map(f).collect();
+    }
+    let context = BuildContext::current();
+    items.par_iter().map(|t| context.enter(|| f(t))).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+fn map_items<T, R>(items: &[T], _threshold: usize, f: impl Fn(&T) -> R) -> Vec<R> {
+    items.iter().map(f).collect()
+}
+
+fn check_batch_width(xs: &[Vec<Value>], nin: usize) {
+    if let Some((i, x)) = xs.iter().enumerate().find(|(_, x)| x.len() != nin) {
+        panic!("batch row {} has {} inputs, expected {}", i, x.len(), nin);
+    }
+}
+
+fn prefix_labels(params: &[Value], prefix: &str) {
+    for p in params {
+        p.set_label(format!("{}.{}", prefix, p.label()));
+    }
+}
+
+/// Inverted dropout: while training, every input is zeroed with probability `p` and the
+/// survivors are scaled by `1 / (1 - p)`, through plain `Op::Mul` nodes against constant
+/// leaves. In eval mode it is the identity.
+pub struct Dropout {
+    p: Scalar,
+    rng: Slot<StdRng>,
+    training: AtomicBool,
+}
+
+impl Dropout {
+    /// Starts in training mode
+    pub fn new(p: Scalar, seed: u64) -> Self {
+        assert!(
+            (0.0..1.0).contains(&p),
+            "dropout probability must be in [0, 1), got {}",
+            p
+        );
+        Dropout {
+            p,
+            rng: Slot::new(StdRng::seed_from_u64(seed)),
+            training: AtomicBool::new(true),
+        }
+    }
+
+    pub fn p(&self) -> Scalar {
+        self.p
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.training.load(Ordering::Relaxed)
+    }
+
+    pub fn set_training(&self, training: bool) {
+        self.training.store(training, Ordering::Relaxed);
+    }
+
+    pub fn apply(&self, x: &[Value]) -> Vec<Value> {
+        if !self.is_training() || self.p == 0.0 {
+            return x.to_vec();
+        }
+        let mut rng = self.rng.borrow_mut();
+        let scale = 1.0 / (1.0 - self.p);
+        x.iter()
+            .map(|xi| {
+                let keep = rng.gen::<Scalar>() >= self.p;
+                xi * Value::new(if keep { scale } else { 0.0 })
+            })
+            .collect()
+    }
+}
+
+impl Clone for Dropout {
+    /// The clone continues from the same random state, and mode
+    fn clone(&self) -> Self {
+        Dropout {
+            p: self.p,
+            rng: Slot::new(self.rng.borrow().clone()),
+            training: AtomicBool::new(self.is_training()),
+        }
+    }
+}
+
+impl std::fmt::Debug for Dropout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dropout")
+            .field("p", &self.p)
+            .field("training", &self.is_training())
+            .finish()
+    }
+}
+
+/// A fully connected layer of independent neurons, optionally followed by dropout
+#[derive(Debug, Clone)]
+pub struct Layer {
+    neurons: Vec<Neuron>,
+    dropout: Option<Dropout>,
+
+    /// how the parameters were drawn; `None` for a layer assembled from given neurons
+    init: Option<Init>,
+
+    /// see `with_parallel_threshold`
+    parallel_threshold: usize,
+}
+
+impl Layer {
+    /// Parameter labels are prefixed with the neuron index, e.g. "n2.w1"
+    pub fn new(nin: usize, nout: usize) -> Self {
+        Layer::new_with_rng(nin, nout, &mut rand::thread_rng())
+    }
+
+    pub fn new_with_rng(nin: usize, nout: usize, rng: &mut impl Rng) -> Self {
+        Layer::new_with_init(nin, nout, Init::default(), rng)
+    }
+
+    /// See `Neuron::new_uniform`
+    pub fn new_uniform(nin: usize, nout: usize, lo: Scalar, hi: Scalar) -> Self {
+        let init = Init::Uniform { lo, hi };
+        Layer::new_with_init(nin, nout, init, &mut rand::thread_rng())
+    }
+
+    /// See `Neuron::new_normal`
+    pub fn new_normal(nin: usize, nout: usize, mean: Scalar, std: Scalar) -> Self {
+        let init = Init::Normal { mean, std };
+        Layer::new_with_init(nin, nout, init, &mut rand::thread_rng())
+    }
+
+    pub fn new_with_init(nin: usize, nout: usize, init: Init, rng: &mut impl Rng) -> Self {
+        Layer::from_neurons(
+            (0..nout)
+                .map(|_| Neuron::new_with_init(nin, nout, init, rng))
+                .collect(),
+        )
+        .with_init(Some(init))
+    }
+
+    pub(crate) fn from_neurons(neurons: Vec<Neuron>) -> Self {
+        for (j, n) in neurons.iter().enumerate() {
+            prefix_labels(&n.get_parameters(), &format!("n{}", j));
+        }
+        Layer {
+            neurons,
+            dropout: None,
+            init: None,
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+        }
+    }
+
+    /// Record the scheme the neurons were drawn with, e.g. when restoring a saved layer
+    pub(crate) fn with_init(mut self, init: Option<Init>) -> Self {
+        self.init = init;
+        self
+    }
+
+    /// The scheme the parameters were drawn with, if the layer drew them
+    pub fn init(&self) -> Option<Init> {
+        self.init
+    }
+
+    pub fn new_with_activation(nin: usize, nout: usize, act: Activation) -> Self {
+        Layer::new(nin, nout).with_activation(act)
+    }
+
+    /// Use `act` for every neuron of the layer
+    pub fn with_activation(mut self, act: Activation) -> Self {
+        for n in &mut self.neurons {
+            n.act = act;
+        }
+        self
+    }
+
+    /// Drop the bias of every neuron of the layer
+    pub fn without_bias(mut self) -> Self {
+        self.neurons = self.neurons.into_iter().map(Neuron::without_bias).collect();
+        self
+    }
+
+    /// With the `rayon` feature, `apply` and `predict` run the neurons in parallel once
+    /// the layer has at least `threshold` of them, and `apply_batch` the rows once there
+    /// are that many, 32 by default; `usize::MAX` keeps everything serial. The results, and the graphs
+    /// built, are the same either way. Without the feature it has no effect.
+    pub fn with_parallel_threshold(mut self, threshold: usize) -> Self {
+        self.parallel_threshold = threshold;
+        self
+    }
+
+    /// Apply `dropout` to the layer's outputs
+    pub fn with_dropout(mut self, dropout: Dropout) -> Self {
+        self.dropout = Some(dropout);
+        self
+    }
+
+    pub fn dropout(&self) -> Option<&Dropout> {
+        self.dropout.as_ref()
+    }
+
+    /// Switch the dropout, if any, between training and eval mode
+    pub fn set_training(&self, training: bool) {
+        if let Some(d) = &self.dropout {
+            d.set_training(training);
+        }
+    }
+
+    pub fn apply(&self, x: &[Value]) -> Vec<Value> {
+        let out = map_items(&self.neurons, self.parallel_threshold, |n| n.apply(x));
+        match &self.dropout {
+            Some(d) => with_site_tag("layer.dropout", || d.apply(&out)),
+            None => out,
+        }
+    }
+
+    /// `apply` on every row, after checking all rows have the layer's input size
+    pub fn apply_batch(&self, xs: &[Vec<Value>]) -> Vec<Vec<Value>> {
+        if !self.neurons.is_empty() {
+            check_batch_width(xs, self.nin());
+        }
+        // dropout masks have to be drawn in row order
+        let threshold = match &self.dropout {
+            Some(d) if d.is_training() => usize::MAX,
+            _ => self.parallel_threshold,
+        };
+        map_items(xs, threshold, |x| self.apply(x))
+    }
+
+    /// Always evaluates as in eval mode, without dropout
+    pub fn predict(&self, x: &[Scalar]) -> Vec<Scalar> {
+        map_items(&self.neurons, self.parallel_threshold, |n| n.predict(x))
+    }
+
+    pub fn neurons(&self) -> &[Neuron] {
+        &self.neurons
+    }
+
+    /// Input size; 0 for a layer without neurons
+    pub fn nin(&self) -> usize {
+        self.neurons.first().map_or(0, Neuron::nin)
+    }
+
+    pub fn num_parameters(&self) -> usize {
+        self.neurons.iter().map(Neuron::num_parameters).sum()
+    }
+
+    pub fn get_parameters(&self) -> Vec<Value> {
+        self.neurons
+            .iter()
+            .flat_map(Neuron::get_parameters)
+            .collect()
+    }
+
+    pub fn weights(&self) -> Vec<Value> {
+        self.neurons
+            .iter()
+            .flat_map(|n| n.w.iter().cloned())
+            .collect()
+    }
+
+    pub fn biases(&self) -> Vec<Value> {
+        self.neurons.iter().filter_map(|n| n.b.clone()).collect()
+    }
+
+    pub fn zero_grad(&self) {
+        for n in &self.neurons {
+            n.zero_grad();
+        }
+    }
+}
+
+/// |grad| of the parameters of one layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerGradStats {
+    pub min: Scalar,
+    pub max: Scalar,
+    pub mean: Scalar,
+}
+
+impl LayerGradStats {
+    /// All zero for a layer without parameters
+    fn of(params: &[Value]) -> Self {
+        if params.is_empty() {
+            return LayerGradStats {
+                min: 0.0,
+                max: 0.0,
+                mean: 0.0,
+            };
+        }
+        let abs: Vec<Scalar> = params.iter().map(|p| p.grad().abs()).collect();
+        LayerGradStats {
+            min: abs.iter().copied().fold(Scalar::INFINITY, Scalar::min),
+            max: abs.iter().copied().fold(0.0, Scalar::max),
+            mean: abs.iter().sum::<Scalar>() / abs.len() as Scalar,
+        }
+    }
+}
+
+/// Per-layer grad statistics from `MLP::grad_stats`, displayed one line per layer, e.g.
+/// `layer 0: |grad| min 0.0012 max 0.5300 mean 0.1200`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradStats {
+    pub layers: Vec<LayerGradStats>,
+}
+
+impl std::fmt::Display for GradStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, l) in self.layers.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "layer {}: |grad| min {:.4} max {:.4} mean {:.4}",
+                i, l.min, l.max, l.mean
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Multi-layer perceptron: a stack of layers, each feeding the next
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone)]
+pub struct MLP {
+    layers: Vec<Layer>,
+}
+
+impl MLP {
+    /// `nouts` holds the width of every layer, the last entry being the output size.
+    /// Parameters are labeled by position, e.g. "l0.n2.w1" or "l1.n0.b".
+    pub fn new(nin: usize, nouts: &[usize]) -> Self {
+        MLP::new_with_rng(nin, nouts, &mut rand::thread_rng())
+    }
+
+    /// Reproducible initialization: the same seed gives identical parameters
+    pub fn new_with_seed(nin: usize, nouts: &[usize], seed: u64) -> Self {
+        MLP::new_with_rng(nin, nouts, &mut StdRng::seed_from_u64(seed))
+    }
+
+    pub fn new_with_rng(nin: usize, nouts: &[usize], rng: &mut impl Rng) -> Self {
+        MLP::new_with_init(nin, nouts, Init::default(), rng)
+    }
+
+    /// See `Neuron::new_uniform`
+    pub fn new_uniform(nin: usize, nouts: &[usize], lo: Scalar, hi: Scalar) -> Self {
+        let init = Init::Uniform { lo, hi };
+        MLP::new_with_init(nin, nouts, init, &mut rand::thread_rng())
+    }
+
+    /// See `Neuron::new_normal`
+    pub fn new_normal(nin: usize, nouts: &[usize], mean: Scalar, std: Scalar) -> Self {
+        let init = Init::Normal { mean, std };
+        MLP::new_with_init(nin, nouts, init, &mut rand::thread_rng())
+    }
+
+    pub fn new_with_init(nin: usize, nouts: &[usize], init: Init, rng: &mut impl Rng) -> Self {
+        let sizes: Vec<usize> = std::iter::once(nin).chain(nouts.iter().copied()).collect();
+        MLP::from_layers(
+            sizes
+                .windows(2)
+                .map(|w| Layer::new_with_init(w[0], w[1], init, rng))
+                .collect(),
+        )
+    }
+
+    pub(crate) fn from_layers(layers: Vec<Layer>) -> Self {
+        for (i, l) in layers.iter().enumerate() {
+            prefix_labels(&l.get_parameters(), &format!("l{}", i));
+        }
+        MLP { layers }
+    }
+
+    /// Tanh hidden layers and a linear output layer, so targets aren't limited to (-1, 1)
+    pub fn new_regressor(nin: usize, nouts: &[usize]) -> Self {
+        MLP::new(nin, nouts).into_regressor()
+    }
+
+    /// Drop the activation of the output layer
+    pub fn into_regressor(mut self) -> Self {
+        if let Some(last) = self.layers.pop() {
+            self.layers.push(last.with_activation(Activation::Linear));
+        }
+        self
+    }
+
+    /// Dropout with probability `p` after every hidden layer, seeded from `seed`, in
+    /// training mode
+    pub fn with_dropout(self, p: Scalar, seed: u64) -> Self {
+        let hidden = self.layers.len().saturating_sub(1);
+        MLP {
+            layers: self
+                .layers
+                .into_iter()
+                .enumerate()
+                .map(|(i, l)| {
+                    if i < hidden {
+                        l.with_dropout(Dropout::new(p, seed.wrapping_add(i as u64)))
+                    } else {
+                        l
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// `Layer::with_parallel_threshold` for every layer
+    pub fn with_parallel_threshold(self, threshold: usize) -> Self {
+        MLP {
+            layers: self
+                .layers
+                .into_iter()
+                .map(|l| l.with_parallel_threshold(threshold))
+                .collect(),
+        }
+    }
+
+    /// Training mode enables dropout; eval mode makes `apply` deterministic
+    pub fn set_training(&self, training: bool) {
+        for l in &self.layers {
+            l.set_training(training);
+        }
+    }
+
+    /// One activation per layer, e.g. `[ReLU, ReLU, Linear]`
+    pub fn new_with_activations(nin: usize, nouts: &[usize], acts: &[Activation]) -> Self {
+        MLP::new(nin, nouts).with_activations(acts)
+    }
+
+    pub fn with_activations(self, acts: &[Activation]) -> Self {
+        assert_eq!(
+            acts.len(),
+            self.layers.len(),
+            "got {} activations for {} layers",
+            acts.len(),
+            self.layers.len()
+        );
+        MLP {
+            layers: self
+                .layers
+                .into_iter()
+                .zip(acts)
+                .map(|(l, &act)| l.with_activation(act))
+                .collect(),
+        }
+    }
+
+    pub fn apply(&self, x: &[Value]) -> Vec<Value> {
+        let mut x = x.to_vec();
+        for layer in &self.layers {
+            x = layer.apply(&x);
+        }
+        x
+    }
+
+    /// `apply` on fresh leaves holding `x`, for when the grads of the inputs aren't needed
+    pub fn apply_scalars(&self, x: &[Scalar]) -> Vec<Value> {
+        self.apply(&Value::from_slice(x))
+    }
+
+    /// Outputs row-aligned with `xs`; every row is checked against the input size before
+    /// any graph is built
+    pub fn apply_batch(&self, xs: &[Vec<Value>]) -> Vec<Vec<Value>> {
+        let mut xs = xs.to_vec();
+        for layer in &self.layers {
+            xs = layer.apply_batch(&xs);
+        }
+        xs
+    }
+
+    /// Inference without a graph: same result as `apply` in eval mode, but no `Value` is
+    /// created
+    pub fn predict(&self, x: &[Scalar]) -> Vec<Scalar> {
+        let mut x = x.to_vec();
+        for layer in &self.layers {
+            x = layer.predict(&x);
+        }
+        x
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Input size followed by the width of every layer
+    pub fn sizes(&self) -> Vec<usize> {
+        let nin = self.layers.first().map_or(0, Layer::nin);
+        std::iter::once(nin)
+            .chain(self.layers.iter().map(|l| l.neurons.len()))
+            .collect()
+    }
+
+    /// Fingerprint of what the parameters mean: the sizes, every layer's init scheme
+    /// (see `Layer::init`), which neurons have a bias and every neuron's activation. Two
+    /// networks with the same hash take each other's weights. Dropout, which only acts
+    /// during training, is not covered.
+    pub fn architecture_hash(&self) -> u64 {
+        let mut h = Fnv1a::default();
+        for s in self.sizes() {
+            h.write(&(s as u64).to_le_bytes());
+        }
+        for layer in &self.layers {
+            let (scheme, a, b) = match layer.init {
+                None => (0, 0.0, 0.0),
+                Some(Init::Uniform { lo, hi }) => (1, lo, hi),
+                Some(Init::Normal { mean, std }) => (2, mean, std),
+                Some(Init::XavierUniform) => (3, 0.0, 0.0),
+                Some(Init::XavierNormal) => (4, 0.0, 0.0),
+                Some(Init::HeUniform) => (5, 0.0, 0.0),
+                Some(Init::HeNormal) => (6, 0.0, 0.0),
+            };
+            h.write(&[scheme]);
+            h.write(&a.to_le_bytes());
+            h.write(&b.to_le_bytes());
+            for n in &layer.neurons {
+                h.write(&[u8::from(n.b.is_some())]);
+                h.write(n.act.name().as_bytes());
+                if let Activation::LeakyReLU(slope) = n.act {
+                    h.write(&slope.to_le_bytes());
+                }
+            }
+        }
+        h.finish()
+    }
+
+    /// Fingerprint of the exact data of every parameter, in `get_parameters` order
+    pub fn weights_hash(&self) -> u64 {
+        let mut h = Fnv1a::default();
+        for p in self.get_parameters() {
+            h.write(&p.data().to_le_bytes());
+        }
+        h.finish()
+    }
+
+    pub fn get_parameters(&self) -> Vec<Value> {
+        self.layers.iter().flat_map(Layer::get_parameters).collect()
+    }
+
+    pub fn num_parameters(&self) -> usize {
+        self.layers.iter().map(Layer::num_parameters).sum()
+    }
+
+    /// One line per layer with its shape, activation and parameter count, then the total
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "{:<6} {:<12} {:<12} {:>8}",
+            "layer", "shape", "activation", "params"
+        )];
+        for (i, l) in self.layers.iter().enumerate() {
+            let mut act = l
+                .neurons
+                .first()
+                .map_or(Activation::default(), Neuron::activation)
+                .name()
+                .to_string();
+            if l.dropout.is_some() {
+                act.push_str("+dropout");
+            }
+            lines.push(format!(
+                "{:<6} {:<12} {:<12} {:>8}",
+                i,
+                format!("{} -> {}", l.nin(), l.neurons.len()),
+                act,
+                l.num_parameters()
+            ));
+        }
+        lines.push(format!("{:<32} {:>8}", "total", self.num_parameters()));
+        lines.join("\n")
+    }
+
+    /// `d output / d x_i` for each input of a network with a single output, e.g. to see
+    /// which features a prediction is most sensitive to. The grads of the parameters are
+    /// left alone, so it can be called in the middle of accumulating a batch.
+    pub fn input_gradients(&self, x: &[Scalar]) -> Vec<Scalar> {
+        let inputs = Value::from_slice(x);
+        let outputs = self.apply(&inputs);
+        assert_eq!(
+            outputs.len(),
+            1,
+            "input gradients need a single output, the network has {}",
+            outputs.len()
+        );
+        // only the data of the derivative graph is needed, not a graph of its own
+        no_grad(|| backward_graph(&outputs[0], &inputs))
+            .iter()
+            .map(Value::data)
+            .collect()
+    }
+
+    /// Min, max and mean |grad| of the parameters of every layer, as left by the last
+    /// backward pass; nothing is modified
+    pub fn grad_stats(&self) -> GradStats {
+        GradStats {
+            layers: self
+                .layers
+                .iter()
+                .map(|l| LayerGradStats::of(&l.get_parameters()))
+                .collect(),
+        }
+    }
+
+    /// Every weight, without the biases, e.g. for weight decay
+    pub fn weights(&self) -> Vec<Value> {
+        self.layers.iter().flat_map(Layer::weights).collect()
+    }
+
+    pub fn biases(&self) -> Vec<Value> {
+        self.layers.iter().flat_map(Layer::biases).collect()
+    }
+
+    pub fn zero_grad(&self) {
+        for l in &self.layers {
+            l.zero_grad();
+        }
+    }
+}
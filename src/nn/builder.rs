@@ -0,0 +1,189 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::{Activation, Dropout, Init, Layer, MLP};
+use crate::Scalar;
+
+/// Settings of one layer added with `MlpBuilder::layer_with`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerConfig {
+    pub activation: Activation,
+    pub init: Init,
+
+    /// whether the neurons get a bias; on by default
+    pub bias: bool,
+
+    /// dropout probability on the layer's outputs
+    pub dropout: Option<Scalar>,
+}
+
+impl Default for LayerConfig {
+    fn default() -> Self {
+        LayerConfig {
+            activation: Activation::default(),
+            init: Init::default(),
+            bias: true,
+            dropout: None,
+        }
+    }
+}
+
+/// Configurations `MlpBuilder::build` rejects
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    /// the network takes no inputs
+    NoInputs,
+
+    NoLayers,
+
+    /// a layer was given no neurons
+    ZeroWidth {
+        layer: usize,
+    },
+
+    /// a dropout probability outside `[0, 1)`
+    DropoutProbability {
+        layer: usize,
+        p: Scalar,
+    },
+
+    /// `dropout` was called before any `layer`
+    DropoutWithoutLayer,
+
+    /// a layer's init has an empty range or a non-positive std
+    InvalidInit {
+        layer: usize,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::NoInputs => write!(f, "a network needs at least one input"),
+            BuildError::NoLayers => write!(f, "a network needs at least one layer"),
+            BuildError::ZeroWidth { layer } => write!(f, "layer {} has no neurons", layer),
+            BuildError::DropoutProbability { layer, p } => write!(
+                f,
+                "dropout probability of layer {} must be in [0, 1), got {}",
+                layer, p
+            ),
+            BuildError::DropoutWithoutLayer => write!(f, "dropout given before any layer"),
+            BuildError::InvalidInit { layer, reason } => write!(f, "layer {}: {}", layer, reason),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// Layer-by-layer construction of an `MLP`:
+///
+/// ```
+/// use micrograd::{Activation, Init, LayerConfig, MlpBuilder};
+///
+/// let mlp = MlpBuilder::new(2)
+///     .layer(16, Activation::ReLU)
+///     .dropout(0.2)
+///     .layer_with(8, LayerConfig { init: Init::XavierUniform, ..Default::default() })
+///     .layer(1, Activation::Linear)
+///     .seed(42)
+///     .build()
+///     .unwrap();
+/// assert_eq!(mlp.sizes(), vec![2, 16, 8, 1]);
+/// ```
+///
+/// Settings are only checked by `build`. Without a seed the parameters are drawn from
+/// the thread rng.
+#[derive(Debug, Clone)]
+pub struct MlpBuilder {
+    nin: usize,
+    layers: Vec<(usize, LayerConfig)>,
+    seed: Option<u64>,
+    dropout_without_layer: bool,
+}
+
+impl MlpBuilder {
+    pub fn new(nin: usize) -> Self {
+        MlpBuilder {
+            nin,
+            layers: vec![],
+            seed: None,
+            dropout_without_layer: false,
+        }
+    }
+
+    /// A layer of `width` neurons using `activation` and the default init
+    pub fn layer(self, width: usize, activation: Activation) -> Self {
+        self.layer_with(
+            width,
+            LayerConfig {
+                activation,
+                ..LayerConfig::default()
+            },
+        )
+    }
+
+    pub fn layer_with(mut self, width: usize, config: LayerConfig) -> Self {
+        self.layers.push((width, config));
+        self
+    }
+
+    /// Dropout with probability `p` on the outputs of the last layer added
+    pub fn dropout(mut self, p: Scalar) -> Self {
+        match self.layers.last_mut() {
+            Some((_, config)) => config.dropout = Some(p),
+            None => self.dropout_without_layer = true,
+        }
+        self
+    }
+
+    /// Draw the parameters, and seed the dropout masks, from `seed`
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn build(&self) -> Result<MLP, BuildError> {
+        if self.nin == 0 {
+            return Err(BuildError::NoInputs);
+        }
+        if self.dropout_without_layer {
+            return Err(BuildError::DropoutWithoutLayer);
+        }
+        if self.layers.is_empty() {
+            return Err(BuildError::NoLayers);
+        }
+        for (layer, (width, config)) in self.layers.iter().enumerate() {
+            if *width == 0 {
+                return Err(BuildError::ZeroWidth { layer });
+            }
+            if let Err(reason) = config.init.validate() {
+                return Err(BuildError::InvalidInit { layer, reason });
+            }
+            if let Some(p) = config.dropout {
+                if !(0.0..1.0).contains(&p) {
+                    return Err(BuildError::DropoutProbability { layer, p });
+                }
+            }
+        }
+
+        let seed = self.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut nin = self.nin;
+        let mut layers = vec![];
+        for (i, &(width, config)) in self.layers.iter().enumerate() {
+            // the bias is drawn even when dropped, so the weights don't depend on it
+            let mut layer = Layer::new_with_init(nin, width, config.init, &mut rng)
+                .with_activation(config.activation);
+            if !config.bias {
+                layer = layer.without_bias();
+            }
+            if let Some(p) = config.dropout {
+                layer = layer.with_dropout(Dropout::new(p, seed.wrapping_add(i as u64)));
+            }
+            layers.push(layer);
+            nin = width;
+        }
+        Ok(MLP::from_layers(layers))
+    }
+}
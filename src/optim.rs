@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+
+use crate::value::consts::PI;
+use crate::{Scalar, Value};
+
+/// Stochastic gradient descent, optionally with momentum and weight decay:
+/// `v = momentum * v + grad; p -= lr * (v + weight_decay * p)`
+#[derive(Debug, Clone)]
+pub struct Sgd {
+    params: Vec<Value>,
+    lr: Scalar,
+    momentum: Scalar,
+
+    /// decoupled weight decay, applied to the parameters directly at `step`
+    weight_decay: Scalar,
+
+    /// velocity per parameter
+    velocity: HashMap<Value, Scalar>,
+}
+
+impl Sgd {
+    pub fn new(params: Vec<Value>, lr: Scalar) -> Self {
+        Sgd::with_momentum(params, lr, 0.0)
+    }
+
+    pub fn with_momentum(params: Vec<Value>, lr: Scalar, momentum: Scalar) -> Self {
+        assert!(
+            (0.0..1.0).contains(&momentum),
+            "momentum must be in [0, 1), got {}",
+            momentum
+        );
+        Sgd {
+            params,
+            lr,
+            momentum,
+            weight_decay: 0.0,
+            velocity: HashMap::new(),
+        }
+    }
+
+    pub fn lr(&self) -> Scalar {
+        self.lr
+    }
+
+    pub fn set_lr(&mut self, lr: Scalar) {
+        self.lr = lr;
+    }
+
+    pub fn momentum(&self) -> Scalar {
+        self.momentum
+    }
+
+    pub fn weight_decay(&self) -> Scalar {
+        self.weight_decay
+    }
+
+    /// Shrink every parameter by `lr * weight_decay * p` on each step, independently of
+    /// the loss. To leave the biases alone, give them their own optimizer, e.g. over
+    /// `MLP::biases`.
+    pub fn set_weight_decay(&mut self, weight_decay: Scalar) {
+        self.weight_decay = weight_decay;
+    }
+
+    pub fn params(&self) -> &[Value] {
+        &self.params
+    }
+
+    /// Momentum buffer of `p`, 0 until its first step
+    #[cfg(feature = "serde")]
+    pub(crate) fn velocity(&self, p: &Value) -> Scalar {
+        self.velocity.get(p).copied().unwrap_or(0.0)
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_velocity(&mut self, p: &Value, v: Scalar) {
+        self.velocity.insert(p.clone(), v);
+    }
+
+    /// Update every parameter from its current grad
+    pub fn step(&mut self) {
+        for p in &self.params {
+            let v = self.velocity.entry(p.clone()).or_insert(0.0);
+            *v = self.momentum * *v + p.grad();
+            let decay = self.weight_decay * p.data();
+            p.add_data(-self.lr * (*v + decay));
+        }
+    }
+
+    /// Step on grads summed over `k` micro-batches with `Value::backward_accumulate`: they
+    /// are divided by `k` first, so the update is that of the mean loss, then zeroed for
+    /// the next round
+    pub fn step_accumulated(&mut self, k: usize) {
+        assert!(k > 0, "cannot average over 0 accumulated passes");
+        for p in &self.params {
+            p.set_grad(p.grad() / k as Scalar);
+        }
+        self.step();
+        self.zero_grad();
+    }
+
+    pub fn zero_grad(&self) {
+        for p in &self.params {
+            p.zero_grad();
+        }
+    }
+}
+
+/// Clamp every grad to `[-max_abs, max_abs]`
+pub fn clip_grad_value(params: &[Value], max_abs: Scalar) {
+    for p in params {
+        p.set_grad(p.grad().clamp(-max_abs, max_abs));
+    }
+}
+
+/// Global L2 norm of the grads of all of `params`, `sqrt(Σ grad²)`
+pub fn grad_norm(params: &[Value]) -> Scalar {
+    params
+        .iter()
+        .map(|p| p.grad() * p.grad())
+        .sum::<Scalar>()
+        .sqrt()
+}
+
+/// Rescale the grads so their global L2 norm, taken over all of `params`, is at most
+/// `max_norm`. Returns the norm before clipping.
+pub fn clip_grad_norm(params: &[Value], max_norm: Scalar) -> Scalar {
+    let norm = grad_norm(params);
+    if norm > max_norm {
+        let scale = max_norm / norm;
+        for p in params {
+            p.set_grad(p.grad() * scale);
+        }
+    }
+    norm
+}
+
+/// Learning rate as a function of the epoch, advanced explicitly with `step_epoch`
+pub trait LrScheduler {
+    /// learning rate for the current epoch
+    fn lr(&self) -> Scalar;
+
+    fn step_epoch(&mut self);
+
+    /// Hand the current learning rate to `optim`
+    fn apply(&self, optim: &mut Sgd) {
+        optim.set_lr(self.lr());
+    }
+}
+
+/// Multiply the learning rate by `gamma` every `step_size` epochs
+#[derive(Debug, Clone)]
+pub struct StepDecay {
+    initial: Scalar,
+    gamma: Scalar,
+    step_size: usize,
+    epoch: usize,
+}
+
+impl StepDecay {
+    pub fn new(initial: Scalar, gamma: Scalar, step_size: usize) -> Self {
+        assert!(step_size > 0, "step size must be at least 1");
+        StepDecay {
+            initial,
+            gamma,
+            step_size,
+            epoch: 0,
+        }
+    }
+}
+
+impl LrScheduler for StepDecay {
+    fn lr(&self) -> Scalar {
+        self.initial * self.gamma.powi((self.epoch / self.step_size) as i32)
+    }
+
+    fn step_epoch(&mut self) {
+        self.epoch += 1;
+    }
+}
+
+/// `initial * gamma^epoch`
+#[derive(Debug, Clone)]
+pub struct ExponentialDecay {
+    initial: Scalar,
+    gamma: Scalar,
+    epoch: usize,
+}
+
+impl ExponentialDecay {
+    pub fn new(initial: Scalar, gamma: Scalar) -> Self {
+        ExponentialDecay {
+            initial,
+            gamma,
+            epoch: 0,
+        }
+    }
+}
+
+impl LrScheduler for ExponentialDecay {
+    fn lr(&self) -> Scalar {
+        self.initial * self.gamma.powi(self.epoch as i32)
+    }
+
+    fn step_epoch(&mut self) {
+        self.epoch += 1;
+    }
+}
+
+/// Half a cosine from `initial` down to `min` over `period` epochs, then stays at `min`
+#[derive(Debug, Clone)]
+pub struct CosineAnnealing {
+    initial: Scalar,
+    min: Scalar,
+    period: usize,
+    epoch: usize,
+}
+
+impl CosineAnnealing {
+    pub fn new(initial: Scalar, min: Scalar, period: usize) -> Self {
+        assert!(period > 0, "period must be at least 1");
+        CosineAnnealing {
+            initial,
+            min,
+            period,
+            epoch: 0,
+        }
+    }
+}
+
+impl LrScheduler for CosineAnnealing {
+    fn lr(&self) -> Scalar {
+        let progress = self.epoch.min(self.period) as Scalar / self.period as Scalar;
+        self.min + (self.initial - self.min) * (1.0 + (PI * progress).cos()) / 2.0
+    }
+
+    fn step_epoch(&mut self) {
+        self.epoch += 1;
+    }
+}
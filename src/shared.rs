@@ -0,0 +1,63 @@
+//! Ownership and interior mutability of graph nodes.
+//!
+//! By default nodes are `Rc` + `RefCell`; with the `sync` feature they are `Arc` + `RwLock`,
+//! so graphs can be sent to and shared between threads.
+//!
+//! Locks only make each read and write atomic, not a whole backward pass. Threads running
+//! `backward` through the same nodes, e.g. the parameters of a shared `MLP`, each reset
+//! and accumulate those nodes' grads, so afterwards the shared grads are meaningless:
+//! give each thread its own copy of the parameters to train, and only trust the grads of
+//! nodes one thread built for itself.
+
+#[cfg(not(feature = "sync"))]
+pub(crate) use std::rc::Rc as Shared;
+#[cfg(feature = "sync")]
+pub(crate) use std::sync::Arc as Shared;
+
+/// Mutable field of a node
+#[cfg(not(feature = "sync"))]
+pub(crate) struct Slot<T>(std::cell::RefCell<T>);
+
+#[cfg(not(feature = "sync"))]
+impl<T> Slot<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Slot(std::cell::RefCell::new(value))
+    }
+
+    pub(crate) fn borrow(&self) -> std::cell::Ref<'_, T> {
+        self.0.borrow()
+    }
+
+    pub(crate) fn borrow_mut(&self) -> std::cell::RefMut<'_, T> {
+        self.0.borrow_mut()
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut T {
+        self.0.get_mut()
+    }
+}
+
+/// Mutable field of a node. Data and grad locks are only held for a single read or write,
+/// so an expression using the same node twice can't deadlock.
+#[cfg(feature = "sync")]
+pub(crate) struct Slot<T>(std::sync::RwLock<T>);
+
+#[cfg(feature = "sync")]
+impl<T> Slot<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Slot(std::sync::RwLock::new(value))
+    }
+
+    // a panic while holding the lock leaves plain data behind, so poisoning is ignored
+    pub(crate) fn borrow(&self) -> std::sync::RwLockReadGuard<'_, T> {
+        self.0.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn borrow_mut(&self) -> std::sync::RwLockWriteGuard<'_, T> {
+        self.0.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut T {
+        self.0.get_mut().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{Scalar, Value};
+
+/// Size limits for a computation graph; `None` leaves a category unconstrained
+#[derive(Debug, Clone, Default)]
+pub struct GraphBudget {
+    pub max_nodes: Option<usize>,
+    /// keyed by `Op::name`, e.g. `("mul", 4)`
+    pub max_per_op: Vec<(&'static str, usize)>,
+    /// counted like `GraphStats::depth`, in edges, so a lone leaf has depth 0
+    pub max_depth: Option<usize>,
+}
+
+/// Check `root` against `budget`, returning a report of every constrained category
+/// when at least one of them is over
+pub fn check_graph_budget(root: &Value, budget: &GraphBudget) -> Result<(), String> {
+    let stats = root.graph_stats();
+
+    let mut lines = vec![];
+    let mut over = false;
+    let mut row = |name: String, actual: usize, allowed: usize| {
+        let marker = if actual > allowed {
+            over = true;
+            "  <-- over budget"
+        } else {
+            ""
+        };
+        lines.push(format!(
+            "  {:<10} actual {:>6}  allowed {:>6}{}",
+            name, actual, allowed, marker
+        ));
+    };
+    if let Some(allowed) = budget.max_nodes {
+        row("nodes".into(), stats.nodes, allowed);
+    }
+    for (name, allowed) in &budget.max_per_op {
+        let actual = stats.ops.get(name).copied().unwrap_or(0);
+        row(format!("op {}", name), actual, *allowed);
+    }
+    if let Some(allowed) = budget.max_depth {
+        row("depth".into(), stats.depth, allowed);
+    }
+
+    if over {
+        Err(format!("graph budget exceeded:\n{}", lines.join("\n")))
+    } else {
+        Ok(())
+    }
+}
+
+/// Panic with a per-category actual vs. allowed report if `root` exceeds `budget`
+pub fn assert_graph_budget(root: &Value, budget: GraphBudget) {
+    if let Err(report) = check_graph_budget(root, &budget) {
+        panic!("{}", report);
+    }
+}
+
+/// An input whose analytic gradient disagrees with the central difference
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradCheckError {
+    /// position of the input in `inputs`
+    pub index: usize,
+    pub analytic: Scalar,
+    pub numeric: Scalar,
+}
+
+impl std::fmt::Display for GradCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "gradient of input {} diverged: analytic {} vs numeric {} (off by {})",
+            self.index,
+            self.analytic,
+            self.numeric,
+            (self.analytic - self.numeric).abs()
+        )
+    }
+}
+
+impl std::error::Error for GradCheckError {}
+
+/// Compare the grads from `backward` against central differences `(f(x+eps) - f(x-eps)) / 2eps`
+/// for every input. `tol` is relative to the larger of the two gradients, or absolute when
+/// both are below 1.
+pub fn check_gradients(
+    build: impl Fn(&[Value]) -> Value,
+    inputs: &[Scalar],
+    eps: Scalar,
+    tol: Scalar,
+) -> Result<(), GradCheckError> {
+    let leaves: Vec<Value> = inputs.iter().map(|&x| Value::new(x)).collect();
+    build(&leaves).backward();
+    let analytic: Vec<Scalar> = leaves.iter().map(Value::grad).collect();
+
+    for (index, leaf) in leaves.iter().enumerate() {
+        // set rather than stepped, so rounding can't leave later inputs off their point
+        let original = leaf.data();
+        leaf.set_data(original + eps);
+        let plus = build(&leaves).data();
+        leaf.set_data(original - eps);
+        let minus = build(&leaves).data();
+        leaf.set_data(original);
+
+        let numeric = (plus - minus) / (2.0 * eps);
+        let scale = analytic[index].abs().max(numeric.abs()).max(1.0);
+        let off = (analytic[index] - numeric).abs();
+        // NaN counts as diverged
+        if off.is_nan() || off > tol * scale {
+            return Err(GradCheckError {
+                index,
+                analytic: analytic[index],
+                numeric,
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,478 @@
+//! A ready-made training loop for [`MLP`], full-batch or on shuffled mini-batches
+
+use std::io;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+
+use crate::data::{BatchIter, LastBatch};
+use crate::losses::{binary_cross_entropy, mse, CompositeLoss};
+use crate::metrics::{
+    auc, best_threshold_by_f1, class_of, per_class_accuracy, MetricsReport, StreamingMetrics,
+};
+use crate::optim::Sgd;
+use crate::shared::{Shared, Slot};
+use crate::{Scalar, Value, MLP};
+
+/// Per-sample loss used by [`MLP::fit`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Loss {
+    /// mean squared error over the outputs
+    #[default]
+    Mse,
+
+    /// binary cross-entropy averaged over the outputs, which must be probabilities,
+    /// e.g. from a sigmoid output layer
+    BinaryCrossEntropy,
+}
+
+impl Loss {
+    fn of(&self, outputs: &[Value], targets: &[Scalar]) -> Value {
+        match self {
+            Loss::Mse => {
+                let targets: Vec<Value> = targets.iter().map(|&t| Value::new(t)).collect();
+                mse(outputs, &targets)
+            }
+            Loss::BinaryCrossEntropy => {
+                assert_eq!(
+                    outputs.len(),
+                    targets.len(),
+                    "got {} predictions but {} targets",
+                    outputs.len(),
+                    targets.len()
+                );
+                let mut total = binary_cross_entropy(&outputs[0], targets[0]);
+                for (y, &t) in outputs.iter().zip(targets).skip(1) {
+                    total += binary_cross_entropy(y, t);
+                }
+                total / outputs.len() as Scalar
+            }
+        }
+    }
+}
+
+/// When [`MLP::fit`] stops
+#[derive(Debug, Clone)]
+pub struct EarlyStopping {
+    pub max_epochs: usize,
+    /// stop as soon as the epoch loss drops below this
+    pub target_loss: Option<Scalar>,
+    /// stop after this many epochs in a row without the best loss improving by more
+    /// than `min_delta`
+    pub patience: Option<usize>,
+    pub min_delta: Scalar,
+    /// when patience runs out, put back the parameters of the best epoch
+    pub restore_best: bool,
+}
+
+impl Default for EarlyStopping {
+    fn default() -> Self {
+        EarlyStopping {
+            max_epochs: 100,
+            target_loss: None,
+            patience: None,
+            min_delta: 0.0,
+            restore_best: false,
+        }
+    }
+}
+
+/// Held-out samples scored with the parameters training ends with, in one streaming pass,
+/// see [`FitReport`]
+#[derive(Debug, Clone, Default)]
+pub struct Validation {
+    pub xs: Vec<Vec<Scalar>>,
+    /// targets in the same form as the training ones
+    pub ys: Vec<Vec<Scalar>>,
+    /// score it as classification over this many classes: the class of an output or
+    /// target is its argmax, or for a single output whether it is `>= 0.5`
+    pub num_classes: Option<usize>,
+}
+
+/// Scores of a single-output classifier and their 0/1 labels, as `metrics::auc` takes them
+type BinaryScores = (Vec<Scalar>, Vec<u8>);
+
+impl Validation {
+    /// One pass through `StreamingMetrics`, also keeping the scores and 0/1 labels of a
+    /// single-output classifier when both classes are present, for `auc`
+    fn evaluate(&self, model: &MLP) -> (MetricsReport, Option<BinaryScores>) {
+        assert_eq!(
+            self.xs.len(),
+            self.ys.len(),
+            "got {} validation inputs but {} targets",
+            self.xs.len(),
+            self.ys.len()
+        );
+        let mut metrics = match self.num_classes {
+            Some(n) => StreamingMetrics::classification(n),
+            None => StreamingMetrics::new(),
+        };
+        let binary = self.num_classes.is_some() && model.sizes().last() == Some(&1);
+        if binary {
+            assert_eq!(self.num_classes, Some(2), "a single output has 2 classes");
+        }
+        let (mut scores, mut labels) = (vec![], vec![]);
+        for (x, y) in self.xs.iter().zip(&self.ys) {
+            let pred = model.predict(x);
+            metrics.update(&pred, y);
+            if binary {
+                scores.push(pred[0]);
+                labels.push(class_of(y) as u8);
+            }
+        }
+        let both = labels.contains(&0) && labels.contains(&1);
+        (metrics.finalize(), both.then_some((scores, labels)))
+    }
+}
+
+/// Builds the objective from the model, its outputs on the samples being scored and
+/// their targets, see [`FitConfig::with_composite_loss`]
+pub type CompositeLossFn = dyn Fn(&MLP, &[Vec<Value>], &[Vec<Scalar>]) -> CompositeLoss;
+
+pub struct FitConfig {
+    pub lr: Scalar,
+    /// see [`Sgd::with_momentum`]
+    pub momentum: Scalar,
+    pub loss: Loss,
+    /// replaces `loss` when set: training runs backward from its `total()`, and its
+    /// `terms()` are recorded every epoch in [`FitReport::term_history`]
+    pub composite_loss: Option<Box<CompositeLossFn>>,
+    pub stopping: EarlyStopping,
+    /// step on shuffled mini-batches of this size, see [`BatchIter`], instead of once
+    /// per epoch on all samples
+    pub batch_size: Option<usize>,
+    pub last_batch: LastBatch,
+    /// seeds the mini-batch shuffling
+    pub seed: u64,
+    /// called once per epoch, in order
+    pub callbacks: Vec<Box<dyn Callback>>,
+    pub validation: Option<Validation>,
+}
+
+impl std::fmt::Debug for FitConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FitConfig")
+            .field("lr", &self.lr)
+            .field("momentum", &self.momentum)
+            .field("loss", &self.loss)
+            .field("composite_loss", &self.composite_loss.is_some())
+            .field("stopping", &self.stopping)
+            .field("batch_size", &self.batch_size)
+            .field("last_batch", &self.last_batch)
+            .field("seed", &self.seed)
+            .field("callbacks", &self.callbacks.len())
+            .field("validation", &self.validation)
+            .finish()
+    }
+}
+
+impl Default for FitConfig {
+    fn default() -> Self {
+        FitConfig::new(0.1, 100)
+    }
+}
+
+impl FitConfig {
+    /// Plain gradient descent on the mean squared error for `max_epochs` epochs
+    pub fn new(lr: Scalar, max_epochs: usize) -> Self {
+        FitConfig {
+            lr,
+            momentum: 0.0,
+            loss: Loss::Mse,
+            composite_loss: None,
+            stopping: EarlyStopping {
+                max_epochs,
+                ..EarlyStopping::default()
+            },
+            batch_size: None,
+            last_batch: LastBatch::Keep,
+            seed: 0,
+            callbacks: vec![],
+            validation: None,
+        }
+    }
+
+    pub fn with_callback(mut self, callback: impl Callback + 'static) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Train on a sum of named terms instead of `loss`, e.g. the task loss plus an
+    /// `l2_penalty` of the parameters
+    pub fn with_composite_loss(
+        mut self,
+        loss: impl Fn(&MLP, &[Vec<Value>], &[Vec<Scalar>]) -> CompositeLoss + 'static,
+    ) -> Self {
+        self.composite_loss = Some(Box::new(loss));
+        self
+    }
+}
+
+/// Hook into [`MLP::fit`]
+pub trait Callback {
+    /// Called every epoch with the loss over all samples, while `model` still holds the
+    /// parameters that loss was computed with. `Break` stops training.
+    fn on_epoch_end(&mut self, epoch: usize, loss: Scalar, model: &MLP) -> ControlFlow<()>;
+}
+
+/// Records the loss of every epoch. Clones share the record, so keep one to read it
+/// after handing the other to `FitConfig::with_callback`.
+#[derive(Clone)]
+pub struct LossLogger {
+    history: Shared<Slot<Vec<Scalar>>>,
+}
+
+impl Default for LossLogger {
+    fn default() -> Self {
+        LossLogger {
+            history: Shared::new(Slot::new(vec![])),
+        }
+    }
+}
+
+impl LossLogger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn history(&self) -> Vec<Scalar> {
+        self.history.borrow().clone()
+    }
+}
+
+impl Callback for LossLogger {
+    fn on_epoch_end(&mut self, _epoch: usize, loss: Scalar, _model: &MLP) -> ControlFlow<()> {
+        self.history.borrow_mut().push(loss);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Saves the weights with [`MLP::save_weights`] every time the loss reaches a new low.
+/// A failed save stops training; the error can be taken from any clone afterwards.
+#[derive(Clone)]
+pub struct CheckpointSaver {
+    path: PathBuf,
+    state: Shared<Slot<SaverState>>,
+}
+
+struct SaverState {
+    best: Scalar,
+    saved: Vec<usize>,
+    error: Option<io::Error>,
+}
+
+impl CheckpointSaver {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        CheckpointSaver {
+            path: path.into(),
+            state: Shared::new(Slot::new(SaverState {
+                best: Scalar::INFINITY,
+                saved: vec![],
+                error: None,
+            })),
+        }
+    }
+
+    /// Epochs whose weights were written, the last being the ones on disk
+    pub fn saved_epochs(&self) -> Vec<usize> {
+        self.state.borrow().saved.clone()
+    }
+
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.state.borrow_mut().error.take()
+    }
+}
+
+impl Callback for CheckpointSaver {
+    fn on_epoch_end(&mut self, epoch: usize, loss: Scalar, model: &MLP) -> ControlFlow<()> {
+        let mut state = self.state.borrow_mut();
+        if loss >= state.best || loss.is_nan() {
+            return ControlFlow::Continue(());
+        }
+        match model.save_weights(&self.path) {
+            Ok(()) => {
+                state.best = loss;
+                state.saved.push(epoch);
+                ControlFlow::Continue(())
+            }
+            Err(e) => {
+                state.error = Some(e);
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// the loss dropped below `target_loss`
+    Converged,
+    PatienceExhausted,
+    MaxEpochs,
+    /// a callback returned `Break`
+    Callback,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FitReport {
+    /// the loss over all samples at the start of every epoch that ran
+    pub history: Vec<Scalar>,
+    /// `CompositeLoss::terms` alongside every `history` entry, empty without
+    /// `FitConfig::composite_loss`
+    pub term_history: Vec<Vec<(String, Scalar)>>,
+    /// loss of the parameters training ended with, after any restore
+    pub final_loss: Scalar,
+    pub stop_reason: StopReason,
+    /// best loss seen, counting only improvements by more than `min_delta`, and its epoch
+    pub best_loss: Scalar,
+    pub best_epoch: usize,
+    /// accuracy on the validation samples of each class, `None` for a class without
+    /// any; only with a classification `FitConfig::validation`
+    pub class_accuracy: Option<Vec<Option<Scalar>>>,
+    /// `metrics::auc` of the validation scores, for a single-output classifier whose
+    /// validation samples have both classes
+    pub auc: Option<Scalar>,
+    /// `metrics::best_threshold_by_f1` as (threshold, f1), when `auc` is reported
+    pub best_threshold: Option<(Scalar, Scalar)>,
+    /// the `StreamingMetrics` of `FitConfig::validation`, if any
+    pub validation: Option<MetricsReport>,
+}
+
+impl MLP {
+    /// Gradient descent on the mean of the per-sample losses, or on
+    /// `config.composite_loss`, until `config.stopping` says stop. Every epoch takes one step on all of `xs` and `ys`, or one per
+    /// mini-batch with `config.batch_size`; either way the loss recorded, and seen by
+    /// the stopping rules and callbacks, is the one over all samples.
+    pub fn fit(&self, xs: &[Vec<Scalar>], ys: &[Vec<Scalar>], mut config: FitConfig) -> FitReport {
+        assert_eq!(
+            xs.len(),
+            ys.len(),
+            "got {} inputs but {} targets",
+            xs.len(),
+            ys.len()
+        );
+        assert!(!xs.is_empty(), "cannot fit on no samples");
+        let inputs: Vec<Vec<Value>> = xs
+            .iter()
+            .map(|x| x.iter().map(|&xi| Value::new(xi)).collect())
+            .collect();
+        // the loss to step on, and the composite terms to record, if any
+        let loss_of = |samples: &[usize]| {
+            if let Some(composite) = &config.composite_loss {
+                let outputs: Vec<Vec<Value>> =
+                    samples.iter().map(|&i| self.apply(&inputs[i])).collect();
+                let targets: Vec<Vec<Scalar>> = samples.iter().map(|&i| ys[i].clone()).collect();
+                let loss = composite(self, &outputs, &targets);
+                return (loss.total(), loss.terms());
+            }
+            let mut total = config
+                .loss
+                .of(&self.apply(&inputs[samples[0]]), &ys[samples[0]]);
+            for &i in &samples[1..] {
+                total += config.loss.of(&self.apply(&inputs[i]), &ys[i]);
+            }
+            (total / samples.len() as Scalar, vec![])
+        };
+        let all: Vec<usize> = (0..xs.len()).collect();
+        let loss = || loss_of(&all);
+        let mut batches = config.batch_size.map(|size| {
+            let batches =
+                BatchIter::new(xs, ys, size, config.seed).with_last_batch(config.last_batch);
+            // otherwise every epoch would run out the patience without a single step
+            assert!(
+                batches.num_batches() > 0,
+                "batch size {} with LastBatch::Drop leaves no batch of {} samples",
+                size,
+                xs.len()
+            );
+            batches
+        });
+
+        let stopping = &config.stopping;
+        let params = self.get_parameters();
+        let snapshot = || params.iter().map(Value::data).collect::<Vec<_>>();
+        let mut optim = Sgd::with_momentum(params.clone(), config.lr, config.momentum);
+        let mut history = Vec::with_capacity(stopping.max_epochs);
+        let mut term_history = vec![];
+        let (mut best_loss, mut best_epoch) = (Scalar::INFINITY, 0);
+        let mut best_params = None;
+        let mut stale = 0;
+        let mut stop_reason = StopReason::MaxEpochs;
+        for epoch in 0..stopping.max_epochs {
+            let (l, terms) = loss();
+            history.push(l.data());
+            if config.composite_loss.is_some() {
+                term_history.push(terms);
+            }
+            // NaN never counts as an improvement
+            if l.data() < best_loss - stopping.min_delta {
+                best_loss = l.data();
+                best_epoch = epoch;
+                stale = 0;
+                if stopping.restore_best {
+                    best_params = Some(snapshot());
+                }
+            } else {
+                stale += 1;
+            }
+            let mut interrupted = false;
+            for callback in &mut config.callbacks {
+                interrupted |= callback.on_epoch_end(epoch, l.data(), self).is_break();
+            }
+            if interrupted {
+                stop_reason = StopReason::Callback;
+                break;
+            }
+            if stopping.target_loss.is_some_and(|target| l.data() < target) {
+                stop_reason = StopReason::Converged;
+                break;
+            }
+            if stopping.patience.is_some_and(|patience| stale >= patience) {
+                stop_reason = StopReason::PatienceExhausted;
+                if let Some(best) = &best_params {
+                    for (p, &d) in params.iter().zip(best) {
+                        p.set_data(d);
+                    }
+                }
+                break;
+            }
+            match &mut batches {
+                None => {
+                    optim.zero_grad();
+                    l.backward();
+                    optim.step();
+                }
+                Some(batches) => {
+                    for batch in batches.epoch_indices() {
+                        optim.zero_grad();
+                        loss_of(&batch).0.backward();
+                        optim.step();
+                    }
+                }
+            }
+        }
+        let (validation, binary) = match &config.validation {
+            Some(v) => {
+                let (metrics, binary) = v.evaluate(self);
+                (Some(metrics), binary)
+            }
+            None => (None, None),
+        };
+        FitReport {
+            history,
+            term_history,
+            final_loss: loss().0.data(),
+            stop_reason,
+            best_loss,
+            best_epoch,
+            class_accuracy: validation
+                .as_ref()
+                .and_then(|m| m.confusion.as_deref())
+                .map(per_class_accuracy),
+            auc: binary.as_ref().map(|(scores, labels)| auc(scores, labels)),
+            best_threshold: binary
+                .as_ref()
+                .map(|(scores, labels)| best_threshold_by_f1(scores, labels)),
+            validation,
+        }
+    }
+}
@@ -0,0 +1,1628 @@
+#[cfg(feature = "serde")]
+mod json;
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Formatter;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::shared::{Shared, Slot};
+
+#[cfg(feature = "serde")]
+pub use json::{graph_from_json, ParseError};
+
+/// Type of data and grads: `f32`, or `f64` with the `f64` feature. A whole graph always
+/// uses one precision.
+#[cfg(not(feature = "f64"))]
+pub type Scalar = f32;
+#[cfg(feature = "f64")]
+pub type Scalar = f64;
+/// Mathematical constants at `Scalar` precision
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+/// Mathematical constants at `Scalar` precision
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
+
+/// Math operation that produced a value, together with its operands
+#[derive(Debug, Clone)]
+pub enum Op {
+    None,
+    Plus(Value, Value),
+    Sub(Value, Value),
+    Mul(Value, Value),
+    /// `a * b + c` in a single node
+    MulAdd(Value, Value, Value),
+    Div(Value, Value),
+    /// the larger operand; ties go to the first
+    Max(Value, Value),
+    /// raised to a constant exponent
+    Pow(Value, Scalar),
+    /// base raised to an exponent that is itself a node
+    PowV(Value, Value),
+    Tanh(Value),
+    Exp(Value),
+    Ln(Value),
+    ReLU(Value),
+    /// ReLU with a constant slope for negative inputs
+    LeakyReLU(Value, Scalar),
+    Sigmoid(Value),
+    Neg(Value),
+    /// binary cross-entropy of a probability against a constant target
+    Bce(Value, Scalar),
+    /// binary cross-entropy of a logit against a constant target, sigmoid fused in
+    BceWithLogits(Value, Scalar),
+    /// sum of any number of operands in a single node
+    Sum(Vec<Value>),
+    /// mean of one or more operands in a single node
+    Mean(Vec<Value>),
+    Abs(Value),
+    /// bounded to a constant `[lo, hi]`
+    Clamp(Value, Scalar, Scalar),
+    Sin(Value),
+    Cos(Value),
+    /// GELU in its tanh approximation
+    Gelu(Value),
+}
+
+/// Static description of an operation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct OpMeta {
+    /// rendered by Display and the graph visualization; empty for leaves
+    pub symbol: &'static str,
+
+    /// stable identifier of the variant
+    pub name: &'static str,
+
+    /// number of values the operation consumes; for `Sum` that of the instance
+    pub arity: usize,
+}
+
+/// The only exhaustive match over Op outside of the forward/backward math,
+/// every consumer of the graph structure goes through it or `for_each_child`
+pub(crate) fn op_metadata(op: &Op) -> OpMeta {
+    let (symbol, name, arity) = match op {
+        Op::None => ("", "none", 0),
+        Op::Plus(..) => ("+", "plus", 2),
+        Op::Sub(..) => ("-", "sub", 2),
+        Op::Mul(..) => ("*", "mul", 2),
+        Op::MulAdd(..) => ("mul_add", "mul_add", 3),
+        Op::Div(..) => ("/", "div", 2),
+        Op::Max(..) => ("max", "max", 2),
+        Op::Pow(..) => ("^", "pow", 1),
+        Op::PowV(..) => ("^", "powv", 2),
+        Op::Tanh(..) => ("tanh", "tanh", 1),
+        Op::Exp(..) => ("exp", "exp", 1),
+        Op::Ln(..) => ("ln", "ln", 1),
+        Op::ReLU(..) => ("relu", "relu", 1),
+        Op::LeakyReLU(..) => ("leaky_relu", "leaky_relu", 1),
+        Op::Sigmoid(..) => ("sigmoid", "sigmoid", 1),
+        Op::Neg(..) => ("-", "neg", 1),
+        Op::Bce(..) => ("bce", "bce", 1),
+        Op::BceWithLogits(..) => ("bce_logits", "bce_with_logits", 1),
+        Op::Sum(vs) => ("sum", "sum", vs.len()),
+        Op::Mean(vs) => ("mean", "mean", vs.len()),
+        Op::Abs(..) => ("abs", "abs", 1),
+        Op::Clamp(..) => ("clamp", "clamp", 1),
+        Op::Sin(..) => ("sin", "sin", 1),
+        Op::Cos(..) => ("cos", "cos", 1),
+        Op::Gelu(..) => ("gelu", "gelu", 1),
+    };
+    OpMeta {
+        symbol,
+        name,
+        arity,
+    }
+}
+
+/// Visit the operands of `op`, in order
+pub(crate) fn for_each_child<'a>(op: &'a Op, mut f: impl FnMut(&'a Value)) {
+    match op {
+        Op::None => {}
+        Op::MulAdd(a, b, c) => {
+            f(a);
+            f(b);
+            f(c);
+        }
+        Op::Plus(a, b)
+        | Op::Sub(a, b)
+        | Op::Mul(a, b)
+        | Op::Div(a, b)
+        | Op::Max(a, b)
+        | Op::PowV(a, b) => {
+            f(a);
+            f(b);
+        }
+        Op::Pow(a, _)
+        | Op::LeakyReLU(a, _)
+        | Op::Clamp(a, _, _)
+        | Op::Bce(a, _)
+        | Op::BceWithLogits(a, _)
+        | Op::Tanh(a)
+        | Op::Exp(a)
+        | Op::Ln(a)
+        | Op::ReLU(a)
+        | Op::Sigmoid(a)
+        | Op::Neg(a)
+        | Op::Abs(a)
+        | Op::Sin(a)
+        | Op::Cos(a)
+        | Op::Gelu(a) => f(a),
+        Op::Sum(vs) | Op::Mean(vs) => vs.iter().for_each(f),
+    }
+}
+
+impl Op {
+    /// Stable lowercase name of the variant, e.g. "mul"
+    pub fn name(&self) -> &'static str {
+        op_metadata(self).name
+    }
+}
+
+impl std::fmt::Display for Op {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", op_metadata(self).symbol)
+    }
+}
+
+/// A node given to `Value::grad_wrt` that the root does not depend on
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnreachableLeaf {
+    /// position of the node in `leaves`
+    pub index: usize,
+
+    /// `Value::id` of the node
+    pub id: usize,
+}
+
+impl std::fmt::Display for UnreachableLeaf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "leaf {} (node {}) is not reachable from the root",
+            self.index, self.id
+        )
+    }
+}
+
+impl std::error::Error for UnreachableLeaf {}
+
+/// Source of node ids, unique for the lifetime of the process
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Holds the math data, derivative, operation, as well as some metadata, such as the label
+struct Value_ {
+    /// current data
+    data: Slot<Scalar>,
+
+    /// derivative of the value `backward` was last run from with respect to this one
+    grad: Slot<Scalar>,
+
+    /// math operation that produces the data
+    op: Slot<Op>,
+
+    /// set by `free_graph` on the nodes whose op it cleared
+    freed: AtomicBool,
+
+    /// uid
+    id: usize,
+
+    /// human readable name
+    label: Slot<String>,
+
+    /// construction site, for attributing graph size to code
+    tag: Option<String>,
+}
+
+/// Dropping a node would otherwise drop its operands recursively, once per level, and
+/// overflow the stack on deep graphs. Operands that are about to be freed are instead
+/// detached onto an explicit stack; shared ones are left to their other owners.
+impl Drop for Value_ {
+    fn drop(&mut self) {
+        let mut stack = take_operands(self.op.get_mut());
+        while let Some(v) = stack.pop() {
+            if let Ok(mut inner) = Shared::try_unwrap(v.0) {
+                stack.extend(take_operands(inner.op.get_mut()));
+            }
+        }
+    }
+}
+
+/// Replace `op` with `Op::None`, returning the operands it held
+fn take_operands(op: &mut Op) -> Vec<Value> {
+    let mut operands = vec![];
+    for_each_child(op, |c| operands.push(c.clone()));
+    *op = Op::None;
+    operands
+}
+
+/// A node in the computation graph.
+///
+/// Cloning is cheap and yields another handle to the same node.
+#[derive(Clone)]
+pub struct Value(Shared<Value_>);
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Value(id={}, data={}, grad={}, op={})",
+            self.id(),
+            self.data(),
+            self.grad(),
+            self.op().name()
+        )
+    }
+}
+
+/// Only the ids of the operands are printed, so deep graphs don't flood the output
+impl std::fmt::Debug for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let children: Vec<usize> = self.children().iter().map(Value::id).collect();
+        f.debug_struct("Value")
+            .field("id", &self.id())
+            .field("label", &*self.0.label.borrow())
+            .field("data", &self.data())
+            .field("grad", &self.grad())
+            .field("op", &self.op().name())
+            .field("children", &children)
+            .finish()
+    }
+}
+
+/// Equality is identity: two handles are equal when they point to the same node, not when
+/// their data is the same, so `Value::new(1.0) != Value::new(1.0)`. This makes `Value`
+/// usable as a key for per-node state such as optimizer buffers or visited sets.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr_eq(other)
+    }
+}
+
+impl Eq for Value {}
+
+/// Consistent with `PartialEq`: ids are unique per node
+impl std::hash::Hash for Value {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
+
+/// A fresh leaf, the same as `Value::new`
+impl From<Scalar> for Value {
+    fn from(data: Scalar) -> Self {
+        Value::new(data)
+    }
+}
+
+/// A `Vec` of fresh leaves, like `vec!`: `values![1.0, -1.0]`
+#[macro_export]
+macro_rules! values {
+    ($($x:expr),* $(,)?) => {
+        ::std::vec::Vec::<$crate::Value>::from([$($crate::Value::new($x)),*])
+    };
+}
+
+/// Runs its closure when dropped, so the scopes below restore their thread-local setting
+/// even when `f` panics and the panic is caught further up
+struct OnDrop<F: FnOnce()>(Option<F>);
+
+impl<F: FnOnce()> Drop for OnDrop<F> {
+    fn drop(&mut self) {
+        if let Some(f) = self.0.take() {
+            f();
+        }
+    }
+}
+
+thread_local! {
+    /// tag given to every value constructed inside `with_tag`
+    static CONSTRUCTION_TAG: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+thread_local! {
+    /// set inside `no_grad`
+    static NO_GRAD: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` without recording the graph: every op result is a leaf holding just its data.
+/// Meant for inference, where the graph would only cost memory.
+pub fn no_grad<R>(f: impl FnOnce() -> R) -> R {
+    let outer = NO_GRAD.with(|g| g.replace(true));
+    let _restore = OnDrop(Some(move || NO_GRAD.with(|g| g.set(outer))));
+    f()
+}
+
+thread_local! {
+    /// set inside `detect_anomaly`
+    static DETECT_ANOMALY: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` checking every number the engine computes: an op result with non-finite data,
+/// or a backward pass writing a non-finite grad, panics right there with the node's id,
+/// op, label and operands, instead of letting NaN spread through the rest of training.
+/// Outside of it nothing is checked.
+pub fn detect_anomaly<R>(f: impl FnOnce() -> R) -> R {
+    let outer = DETECT_ANOMALY.with(|d| d.replace(true));
+    let _restore = OnDrop(Some(move || DETECT_ANOMALY.with(|d| d.set(outer))));
+    f()
+}
+
+/// The thread-local settings of `no_grad`, `with_tag` and `detect_anomaly`, captured to
+/// build values on worker threads exactly as the calling thread would
+#[cfg(feature = "rayon")]
+#[derive(Debug, Clone)]
+pub(crate) struct BuildContext {
+    no_grad: bool,
+    tag: Option<String>,
+    detect_anomaly: bool,
+}
+
+#[cfg(feature = "rayon")]
+impl BuildContext {
+    pub(crate) fn current() -> Self {
+        BuildContext {
+            no_grad: NO_GRAD.with(Cell::get),
+            tag: CONSTRUCTION_TAG.with(|t| t.borrow().clone()),
+            detect_anomaly: DETECT_ANOMALY.with(Cell::get),
+        }
+    }
+
+    /// Run `f` on this thread under the captured settings
+    pub(crate) fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+        let outer = BuildContext::current();
+        self.set();
+        let _restore = OnDrop(Some(move || outer.set()));
+        f()
+    }
+
+    fn set(&self) {
+        NO_GRAD.with(|g| g.set(self.no_grad));
+        CONSTRUCTION_TAG.with(|t| *t.borrow_mut() = self.tag.clone());
+        DETECT_ANOMALY.with(|d| d.set(self.detect_anomaly));
+    }
+}
+
+/// `name=data` for each operand of `op`, as reported by `detect_anomaly`
+fn describe_operands(op: &Op) -> String {
+    let mut operands = vec![];
+    for_each_child(op, |c| {
+        operands.push(format!("{}={}", c.display_name(), c.data()))
+    });
+    operands.join(", ")
+}
+
+/// ` "label"` for a labeled node, nothing otherwise
+fn describe_label(v: &Value) -> String {
+    let label = v.label();
+    if label.is_empty() {
+        label
+    } else {
+        format!(" {:?}", label)
+    }
+}
+
+/// Tag every value constructed while running `f`, including op results.
+/// Scopes nest; the innermost tag wins. Inside a scope, `Neuron` and `Layer` tag the
+/// nodes they build by site: "neuron.dot", "neuron.activation" and "layer.dropout".
+pub fn with_tag<R>(tag: &str, f: impl FnOnce() -> R) -> R {
+    let outer = CONSTRUCTION_TAG.with(|t| t.replace(Some(tag.into())));
+    let _restore = OnDrop(Some(move || {
+        CONSTRUCTION_TAG.with(|t| *t.borrow_mut() = outer)
+    }));
+    f()
+}
+
+/// `with_tag` for a construction site inside the library, only while some `with_tag`
+/// scope is open, so untracked graphs don't pay for a tag per node
+pub(crate) fn with_site_tag<R>(tag: &str, f: impl FnOnce() -> R) -> R {
+    if CONSTRUCTION_TAG.with(|t| t.borrow().is_some()) {
+        with_tag(tag, f)
+    } else {
+        f()
+    }
+}
+
+/// Leaf data precision used by `Value::structural_hash`
+const STRUCTURAL_HASH_DECIMALS: i32 = 6;
+
+/// FNV-1a, used instead of `DefaultHasher` so fingerprints are stable across Rust releases
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf29ce484222325)
+    }
+}
+
+impl Fnv1a {
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Value {
+    pub fn new(data: Scalar) -> Self {
+        Value::from_op(data, Op::None)
+    }
+
+    /// One fresh leaf per element, e.g. to feed a row of inputs to `MLP::apply`
+    pub fn from_slice(data: &[Scalar]) -> Vec<Value> {
+        data.iter().map(|&d| Value::new(d)).collect()
+    }
+
+    pub fn new_tagged(data: Scalar, tag: impl Into<String>) -> Self {
+        Value::build(data, Op::None, Some(tag.into()))
+    }
+
+    pub(crate) fn from_op(data: Scalar, op: Op) -> Self {
+        // described before `no_grad` drops the operands
+        let anomaly =
+            (!data.is_finite() && !matches!(op, Op::None) && DETECT_ANOMALY.with(Cell::get))
+                .then(|| (op.name(), describe_operands(&op)));
+        let tag = CONSTRUCTION_TAG.with(|t| t.borrow().clone());
+        let op = if NO_GRAD.with(Cell::get) {
+            Op::None
+        } else {
+            op
+        };
+        let v = Value::build(data, op, tag);
+        if let Some((name, operands)) = anomaly {
+            panic!(
+                "anomaly: node {} computed by {} has data {}; operands [{}]",
+                v.id(),
+                name,
+                data,
+                operands
+            );
+        }
+        v
+    }
+
+    /// Number of handles to this node, including the ones held by nodes computed from it
+    pub fn ref_count(&self) -> usize {
+        Shared::strong_count(&self.0)
+    }
+
+    /// Cut the graph below this node once its grads are no longer needed: every non-leaf
+    /// node reachable from here forgets its operands, so intermediates are freed right away
+    /// instead of whenever the last handle to the root goes. Data and grads are kept;
+    /// running `backward` through a released node panics.
+    pub fn free_graph(&self) {
+        for v in topological_order(self) {
+            if !v.is_leaf() {
+                *v.0.op.borrow_mut() = Op::None;
+                v.0.freed.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// A new leaf with a snapshot of this node's data; no grad flows back through it
+    pub fn detach(&self) -> Value {
+        Value::new(self.data())
+    }
+
+    fn build(data: Scalar, op: Op, tag: Option<String>) -> Self {
+        Value(Shared::new(Value_ {
+            data: Slot::new(data),
+            grad: Slot::new(0.0),
+            op: Slot::new(op),
+            freed: AtomicBool::new(false),
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            label: Slot::new(String::new()),
+            tag,
+        }))
+    }
+
+    pub fn data(&self) -> Scalar {
+        *self.0.data.borrow()
+    }
+
+    pub fn grad(&self) -> Scalar {
+        *self.0.grad.borrow()
+    }
+
+    pub(crate) fn add_grad(&self, delta: Scalar) {
+        *self.0.grad.borrow_mut() += delta;
+    }
+
+    /// Overwrite the data of a leaf, e.g. a parameter. Panics on a computed node, whose
+    /// data is derived from its operands; see `set_data_unchecked`.
+    pub fn set_data(&self, data: Scalar) {
+        self.check_settable("set_data");
+        self.set_data_unchecked(data);
+    }
+
+    /// `set_data(data() + delta)`, with the same leaf check
+    pub fn add_data(&self, delta: Scalar) {
+        self.check_settable("add_data");
+        *self.0.data.borrow_mut() += delta;
+    }
+
+    /// Overwrite the data of any node, leaving it out of step with its operands until
+    /// the next `forward`
+    pub fn set_data_unchecked(&self, data: Scalar) {
+        *self.0.data.borrow_mut() = data;
+    }
+
+    fn check_settable(&self, method: &str) {
+        assert!(
+            self.is_leaf(),
+            "{} on node {} computed by {}: only leaves can be set",
+            method,
+            self.id(),
+            self.op().name()
+        );
+    }
+
+    /// Back-propagate from this node: every node it depends on gets its grad reset, then
+    /// set to `d self / d node`. Running it again, or on another graph sharing leaves,
+    /// gives the same grads instead of adding onto old ones.
+    ///
+    /// ```
+    /// use micrograd::Value;
+    ///
+    /// let (a, b) = (Value::new(2.0), Value::new(3.0));
+    /// let c = &a * &b;
+    /// c.backward();
+    /// assert_eq!((a.grad(), b.grad()), (3.0, 2.0));
+    /// ```
+    pub fn backward(&self) {
+        for v in topological_order(self) {
+            v.zero_grad();
+        }
+        calculate_grad(self);
+    }
+
+    /// Recompute the data of every non-leaf node below this one from its operands, e.g.
+    /// after an optimizer step changed the parameters, so a graph built once can be
+    /// evaluated again instead of rebuilt. The result is exactly what a fresh build over
+    /// the same leaf data gives. Grads are left alone, and nodes released by `free_graph`
+    /// keep their last data.
+    pub fn forward(&self) {
+        for v in topological_order(self) {
+            if !v.is_leaf() {
+                let data = evaluate(&v.op());
+                v.set_data_unchecked(data);
+            }
+        }
+    }
+
+    /// Like `backward`, but adds (`+=`) onto the grads of the leaves instead of resetting
+    /// them. Use it to sum the grads of several losses sharing parameters, e.g.
+    /// micro-batches before `Sgd::step_accumulated`. Computed nodes are reset first, so
+    /// one shared by an earlier loss only passes on this loss's grad.
+    pub fn backward_accumulate(&self) {
+        for v in topological_order(self) {
+            if !v.is_leaf() {
+                v.zero_grad();
+            }
+        }
+        calculate_grad(self);
+    }
+
+    /// `backward` from here, then the grad of each of `leaves`, in order: how this value
+    /// changes with each of them, e.g. a loss with each input feature. A node this one
+    /// doesn't depend on is an error rather than a zero, and is found before any grad is
+    /// touched.
+    ///
+    /// ```
+    /// use micrograd::Value;
+    ///
+    /// let (a, b) = (Value::new(2.0), Value::new(3.0));
+    /// let f = &a * &b + &a;
+    /// assert_eq!(f.grad_wrt(&[a, b]), Ok(vec![4.0, 2.0]));
+    /// ```
+    pub fn grad_wrt(&self, leaves: &[Value]) -> Result<Vec<Scalar>, UnreachableLeaf> {
+        let order = topological_order(self);
+        let reachable: HashSet<usize> = order.iter().map(Value::id).collect();
+        if let Some((index, leaf)) = leaves
+            .iter()
+            .enumerate()
+            .find(|(_, l)| !reachable.contains(&l.id()))
+        {
+            return Err(UnreachableLeaf {
+                index,
+                id: leaf.id(),
+            });
+        }
+        for v in &order {
+            v.zero_grad();
+        }
+        calculate_grad(self);
+        Ok(leaves.iter().map(Value::grad).collect())
+    }
+
+    pub fn zero_grad(&self) {
+        self.set_grad(0.0);
+    }
+
+    pub(crate) fn set_grad(&self, grad: Scalar) {
+        *self.0.grad.borrow_mut() = grad;
+    }
+
+    pub fn id(&self) -> usize {
+        self.0.id
+    }
+
+    /// Whether both handles are the same node; the same as `==`
+    pub fn ptr_eq(&self, other: &Value) -> bool {
+        Shared::ptr_eq(&self.0, &other.0)
+    }
+
+    pub fn op(&self) -> impl Deref<Target = Op> + '_ {
+        self.0.op.borrow()
+    }
+
+    pub fn label(&self) -> String {
+        self.0.label.borrow().clone()
+    }
+
+    pub fn set_label(&self, label: impl Into<String>) {
+        *self.0.label.borrow_mut() = label.into();
+    }
+
+    /// `set_label` for use while building an expression
+    pub fn with_label(self, label: impl Into<String>) -> Value {
+        self.set_label(label);
+        self
+    }
+
+    /// The label, or the id for unlabeled nodes
+    pub fn display_name(&self) -> String {
+        let label = self.0.label.borrow();
+        if label.is_empty() {
+            format!("#{}", self.0.id)
+        } else {
+            label.clone()
+        }
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        self.0.tag.as_deref()
+    }
+
+    pub fn is_leaf(&self) -> bool {
+        op_metadata(&self.0.op.borrow()).arity == 0
+    }
+
+    /// `self^exponent`. Like `powf`, a negative base with a fractional exponent
+    /// gives NaN, and so does its grad.
+    pub fn pow(&self, exponent: Scalar) -> Value {
+        Value::from_op(self.data().powf(exponent), Op::Pow(self.clone(), exponent))
+    }
+
+    /// `self^exponent` with the exponent a node too, so it can be learned. The
+    /// exponent's grad is `self^exponent * ln(self)`; where `ln` is undefined, at a base
+    /// `<= 0`, it is taken to be 0.
+    pub fn powv(&self, exponent: &Value) -> Value {
+        Value::from_op(
+            self.data().powf(exponent.data()),
+            Op::PowV(self.clone(), exponent.clone()),
+        )
+    }
+
+    pub fn tanh(&self) -> Value {
+        Value::from_op(self.data().tanh(), Op::Tanh(self.clone()))
+    }
+
+    pub fn exp(&self) -> Value {
+        Value::from_op(self.data().exp(), Op::Exp(self.clone()))
+    }
+
+    /// Natural log; like `Scalar::ln`, zero gives -inf and negative inputs give NaN
+    pub fn ln(&self) -> Value {
+        Value::from_op(self.data().ln(), Op::Ln(self.clone()))
+    }
+
+    /// `max(0, x)`
+    pub fn relu(&self) -> Value {
+        Value::from_op(self.data().max(0.0), Op::ReLU(self.clone()))
+    }
+
+    /// `x` for positive inputs, `slope * x` otherwise; the grad is 1 or `slope`
+    pub fn leaky_relu(&self, slope: Scalar) -> Value {
+        let x = self.data();
+        let y = if x > 0.0 { x } else { slope * x };
+        Value::from_op(y, Op::LeakyReLU(self.clone(), slope))
+    }
+
+    /// `self * b + c` as one node instead of a product and a sum, with the same data
+    /// as the unfused `&(self * b) + c`: it is not rounded once like `Scalar::mul_add`
+    pub fn mul_add(&self, b: &Value, c: &Value) -> Value {
+        Value::from_op(
+            self.data() * b.data() + c.data(),
+            Op::MulAdd(self.clone(), b.clone(), c.clone()),
+        )
+    }
+
+    /// The larger of the two. The grad goes entirely to the larger operand; on a tie it
+    /// goes to `self`. Against a constant, e.g. `x.max(&Value::new(0.0))`, it clamps from
+    /// below.
+    pub fn max(&self, other: &Value) -> Value {
+        Value::from_op(
+            self.data().max(other.data()),
+            Op::Max(self.clone(), other.clone()),
+        )
+    }
+
+    /// `1 / (1 + e^-x)`, evaluated so it saturates to 0 or 1 instead of overflowing
+    pub fn sigmoid(&self) -> Value {
+        Value::from_op(stable_sigmoid(self.data()), Op::Sigmoid(self.clone()))
+    }
+
+    /// `|x|`. The grad is `sign(x)`, taken to be 0 at exactly 0 like the ReLU subgradient
+    pub fn abs(&self) -> Value {
+        Value::from_op(self.data().abs(), Op::Abs(self.clone()))
+    }
+
+    /// `x` bounded to `[lo, hi]`. The grad passes through only strictly inside the
+    /// interval; at or beyond a bound it is 0.
+    pub fn clamp(&self, lo: Scalar, hi: Scalar) -> Value {
+        assert!(lo <= hi, "clamp bounds out of order: lo {} > hi {}", lo, hi);
+        Value::from_op(self.data().clamp(lo, hi), Op::Clamp(self.clone(), lo, hi))
+    }
+
+    pub fn sin(&self) -> Value {
+        Value::from_op(self.data().sin(), Op::Sin(self.clone()))
+    }
+
+    pub fn cos(&self) -> Value {
+        Value::from_op(self.data().cos(), Op::Cos(self.clone()))
+    }
+
+    /// `0.5 x (1 + tanh(sqrt(2/π) (x + 0.044715 x³)))`, the tanh approximation of
+    /// `x Φ(x)`. The grad tends to 0 for large negative `x` and to 1 for large positive.
+    pub fn gelu(&self) -> Value {
+        Value::from_op(gelu(self.data()), Op::Gelu(self.clone()))
+    }
+
+    /// A single node adding up all of `values`; unlike a chain of `+` its depth doesn't
+    /// grow with the number of terms. An empty slice gives a zero leaf.
+    pub fn sum(values: &[Value]) -> Value {
+        if values.is_empty() {
+            return Value::new(0.0);
+        }
+        let data = values.iter().fold(0.0, |acc, v| acc + v.data());
+        Value::from_op(data, Op::Sum(values.to_vec()))
+    }
+
+    /// A single node averaging `values`, each of which gets `1/n` of its grad. Panics on
+    /// an empty slice, which has no mean.
+    pub fn mean(values: &[Value]) -> Value {
+        assert!(!values.is_empty(), "cannot take the mean of no values");
+        Value::from_op(mean_of(values), Op::Mean(values.to_vec()))
+    }
+
+    /// Number of distinct nodes in the graph rooted here, this one included
+    pub fn node_count(&self) -> usize {
+        let mut count = 0;
+        self.visit(&mut (), |_, _, _| count += 1);
+        count
+    }
+
+    /// Every node reachable from this one, children before parents, each once. The
+    /// same order `backward` walks in reverse.
+    pub fn iter_graph(&self) -> impl Iterator<Item = Value> {
+        topological_order(self).into_iter()
+    }
+
+    /// The operands of this node's op, in order; empty for leaves
+    pub fn children(&self) -> Vec<Value> {
+        let mut children = vec![];
+        for_each_child(&self.0.op.borrow(), |c| children.push(c.clone()));
+        children
+    }
+
+    /// Call `f` once per node with its operands, children before parents.
+    /// A node reachable along several paths is visited once.
+    pub fn visit<S>(&self, state: &mut S, mut f: impl FnMut(&mut S, &Value, &[Value])) {
+        let mut visited = HashSet::new();
+        // (node, whether its children have already been pushed)
+        let mut stack = vec![(self.clone(), false)];
+        while let Some((value, expanded)) = stack.pop() {
+            if expanded {
+                f(state, &value, &value.children());
+                continue;
+            }
+            if !visited.insert(value.clone()) {
+                continue;
+            }
+            let children = value.children();
+            stack.push((value, true));
+            // reversed so the first operand is visited first
+            stack.extend(
+                children
+                    .into_iter()
+                    .rev()
+                    .filter(|c| !visited.contains(c))
+                    .map(|c| (c, false)),
+            );
+        }
+    }
+
+    /// Like `visit`, but parents come before their children
+    pub fn visit_reverse<S>(&self, state: &mut S, mut f: impl FnMut(&mut S, &Value, &[Value])) {
+        for value in reverse_topological_order(self) {
+            f(state, &value, &value.children());
+        }
+    }
+
+    /// Size and shape of the graph rooted here
+    pub fn graph_stats(&self) -> GraphStats {
+        let mut stats = GraphStats::default();
+        // longest path from each node down to a leaf
+        let mut depths: HashMap<usize, usize> = HashMap::new();
+        self.visit(&mut stats, |stats, v, children| {
+            stats.nodes += 1;
+            stats.edges += children.len();
+            *stats.ops.entry(v.op().name()).or_default() += 1;
+            let depth = children
+                .iter()
+                .map(|c| depths[&c.id()] + 1)
+                .max()
+                .unwrap_or(0);
+            depths.insert(v.id(), depth);
+        });
+        stats.depth = depths[&self.id()];
+        stats
+    }
+
+    /// Node count per construction tag over the reachable graph, largest first.
+    /// Untagged nodes are counted under "untagged".
+    pub fn bloat_report(&self) -> Vec<(String, usize)> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        self.visit(&mut counts, |counts, v, _| {
+            let tag = v.tag().unwrap_or("untagged");
+            *counts.entry(tag.into()).or_default() += 1;
+        });
+        let mut report: Vec<_> = counts.into_iter().collect();
+        report.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        report
+    }
+
+    /// The reachable graph in graphviz DOT format, root first. Nodes are named by id, so
+    /// the output only depends on the graph and is stable across runs of the same code.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph computation {\n    rankdir=LR;\n");
+        for v in reverse_topological_order(self) {
+            let mut text = v.display_name();
+            if !v.is_leaf() {
+                text.push_str(&format!("\n{}", *v.op()));
+            }
+            text.push_str(&format!("\ndata={} grad={}", v.data(), v.grad()));
+            dot.push_str(&format!(
+                "    n{} [label=\"{}\"];\n",
+                v.id(),
+                escape_dot(&text)
+            ));
+            for c in v.children() {
+                dot.push_str(&format!("    n{} -> n{};\n", c.id(), v.id()));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The expression as an indented tree, one node per line, e.g.
+    /// `+ out #7 (data=4.0 grad=1.0)` with its operands two spaces further in. A node
+    /// reached again once printed is shown as `(see #id)` instead of being expanded
+    /// twice. With `max_depth`, operands deeper than that are replaced by
+    /// a single `…` line; the root is at depth 0.
+    pub fn print_tree(&self, max_depth: Option<usize>) -> String {
+        let mut lines = vec![];
+        let mut expanded = HashSet::new();
+        let mut stack = vec![(self.clone(), 0)];
+        while let Some((v, depth)) = stack.pop() {
+            let indent = "  ".repeat(depth);
+            let mut head = String::new();
+            if !v.is_leaf() {
+                head.push_str(&format!("{} ", *v.op()));
+            }
+            let label = v.label();
+            if !label.is_empty() {
+                head.push_str(&format!("{} ", label));
+            }
+            if expanded.contains(&v) {
+                lines.push(format!("{}{}(see #{})", indent, head, v.id()));
+                continue;
+            }
+            lines.push(format!(
+                "{}{}#{} (data={:?} grad={:?})",
+                indent,
+                head,
+                v.id(),
+                v.data(),
+                v.grad()
+            ));
+            let children = v.children();
+            if !children.is_empty() && max_depth.is_some_and(|max| depth >= max) {
+                lines.push(format!("{}  …", indent));
+                continue;
+            }
+            expanded.insert(v.clone());
+            stack.extend(children.into_iter().rev().map(|c| (c, depth + 1)));
+        }
+        lines.join("\n")
+    }
+
+    /// Fingerprint of the graph's structure: op kinds, arity, operand order, and op
+    /// constants and leaf data, both rounded to `STRUCTURAL_HASH_DECIMALS` places. Ids, labels
+    /// and tags are ignored, so two separately built copies of the same expression hash
+    /// identically.
+    pub fn structural_hash(&self) -> u64 {
+        self.structural_hash_with_precision(STRUCTURAL_HASH_DECIMALS)
+    }
+
+    pub fn structural_hash_with_precision(&self, decimals: i32) -> u64 {
+        let scale = 10f64.powi(decimals);
+        // a no-op cast with the f64 feature
+        #[allow(clippy::unnecessary_cast)]
+        let rounded = |x: Scalar| ((x as f64 * scale).round() as i64).to_le_bytes();
+        let mut hashes: HashMap<usize, u64> = HashMap::new();
+        self.visit(&mut hashes, |hashes, v, children| {
+            let meta = op_metadata(&v.op());
+            let mut h = Fnv1a::default();
+            h.write(meta.symbol.as_bytes());
+            // widened so 32 and 64-bit targets agree
+            h.write(&(meta.arity as u64).to_le_bytes());
+            if v.is_leaf() {
+                h.write(&rounded(v.data()));
+            }
+            if let Op::Pow(_, c) | Op::LeakyReLU(_, c) | Op::Bce(_, c) | Op::BceWithLogits(_, c) =
+                &*v.op()
+            {
+                h.write(&rounded(*c));
+            }
+            if let Op::Clamp(_, lo, hi) = &*v.op() {
+                h.write(&rounded(*lo));
+                h.write(&rounded(*hi));
+            }
+            for child in children {
+                h.write(&hashes[&child.id()].to_le_bytes());
+            }
+            hashes.insert(v.id(), h.finish());
+        });
+        hashes[&self.id()]
+    }
+}
+
+/// Summary of a graph, from `Value::graph_stats`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphStats {
+    /// distinct nodes, the root and leaves included
+    pub nodes: usize,
+
+    /// one per operand of every node, so `x * x` has two
+    pub edges: usize,
+
+    /// edges on the longest path from the root to a leaf
+    pub depth: usize,
+
+    /// nodes per `Op::name`, leaves under "none"
+    pub ops: BTreeMap<&'static str, usize>,
+}
+
+impl std::fmt::Display for GraphStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} nodes, {} edges, depth {}",
+            self.nodes, self.edges, self.depth
+        )?;
+        for (i, (name, count)) in self.ops.iter().enumerate() {
+            write!(f, "{}{}: {}", if i == 0 { " (" } else { ", " }, name, count)?;
+        }
+        if !self.ops.is_empty() {
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+/// Quote a DOT string literal: backslashes and quotes are escaped, newlines become `\n`
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Every node reachable from `root`, children before parents
+pub(crate) fn topological_order(root: &Value) -> Vec<Value> {
+    let mut order = vec![];
+    root.visit(&mut order, |order, v, _| order.push(v.clone()));
+    order
+}
+
+/// Every node reachable from `root`, parents before children
+pub(crate) fn reverse_topological_order(root: &Value) -> Vec<Value> {
+    let mut order = topological_order(root);
+    order.reverse();
+    order
+}
+
+pub(crate) fn stable_sigmoid(x: Scalar) -> Scalar {
+    if x >= 0.0 {
+        1.0 / (1.0 + (-x).exp())
+    } else {
+        let e = x.exp();
+        e / (1.0 + e)
+    }
+}
+
+/// `sqrt(2 / π)`, the scale inside the tanh of `gelu`
+const GELU_SCALE: Scalar = consts::FRAC_2_SQRT_PI * consts::FRAC_1_SQRT_2;
+
+/// Weight of the cubic term inside the tanh of `gelu`
+const GELU_CUBIC: Scalar = 0.044715;
+
+/// The tanh of `gelu` at `x`, with the derivative of its argument
+fn gelu_tanh(x: Scalar) -> (Scalar, Scalar) {
+    let t = (GELU_SCALE * (x + GELU_CUBIC * x * x * x)).tanh();
+    (t, GELU_SCALE * (1.0 + 3.0 * GELU_CUBIC * x * x))
+}
+
+pub(crate) fn gelu(x: Scalar) -> Scalar {
+    0.5 * x * (1.0 + gelu_tanh(x).0)
+}
+
+/// `d gelu / dx`; once the tanh saturates its term is dropped, as `x` times the
+/// growing inner derivative would be `0 * inf` for huge `x`
+pub(crate) fn gelu_grad(x: Scalar) -> Scalar {
+    let (t, inner) = gelu_tanh(x);
+    let sech2 = 1.0 - t * t;
+    let outer = 0.5 * (1.0 + t);
+    if sech2 == 0.0 {
+        outer
+    } else {
+        outer + 0.5 * x * sech2 * inner
+    }
+}
+
+/// Probabilities are kept this far from 0 and 1 by `Op::Bce`, so its log and grad stay finite
+const BCE_EPS: Scalar = 1e-7;
+
+pub(crate) fn clamp_probability(p: Scalar) -> Scalar {
+    p.clamp(BCE_EPS, 1.0 - BCE_EPS)
+}
+
+/// `-(t ln p + (1 - t) ln(1 - p))` with `p` clamped away from 0 and 1
+pub(crate) fn bce_loss(p: Scalar, t: Scalar) -> Scalar {
+    let p = clamp_probability(p);
+    -(t * p.ln() + (1.0 - t) * (1.0 - p).ln())
+}
+
+/// `bce_loss(sigmoid(x), t)` as `max(x, 0) - x t + ln(1 + e^-|x|)`
+pub(crate) fn bce_with_logits_loss(x: Scalar, t: Scalar) -> Scalar {
+    x.max(0.0) - x * t + (-x.abs()).exp().ln_1p()
+}
+
+/// Sum of the data divided by the count, the order `Op::Sum` adds in
+fn mean_of(values: &[Value]) -> Scalar {
+    values.iter().fold(0.0, |acc, v| acc + v.data()) / values.len() as Scalar
+}
+
+/// The data of a node computed by `op` from the current data of its operands, exactly
+/// as the op's constructor computes it
+fn evaluate(op: &Op) -> Scalar {
+    match op {
+        Op::None => unreachable!("leaves have no op to evaluate"),
+        Op::Plus(a, b) => a.data() + b.data(),
+        Op::Sub(a, b) => a.data() - b.data(),
+        Op::Mul(a, b) => a.data() * b.data(),
+        Op::MulAdd(a, b, c) => a.data() * b.data() + c.data(),
+        Op::Div(a, b) => a.data() / b.data(),
+        Op::Max(a, b) => a.data().max(b.data()),
+        Op::Pow(a, e) => a.data().powf(*e),
+        Op::PowV(a, b) => a.data().powf(b.data()),
+        Op::Tanh(a) => a.data().tanh(),
+        Op::Exp(a) => a.data().exp(),
+        Op::Ln(a) => a.data().ln(),
+        Op::ReLU(a) => a.data().max(0.0),
+        Op::LeakyReLU(a, slope) => {
+            let x = a.data();
+            if x > 0.0 {
+                x
+            } else {
+                slope * x
+            }
+        }
+        Op::Sigmoid(a) => stable_sigmoid(a.data()),
+        Op::Neg(a) => -a.data(),
+        Op::Bce(a, t) => bce_loss(a.data(), *t),
+        Op::BceWithLogits(a, t) => bce_with_logits_loss(a.data(), *t),
+        Op::Sum(vs) => vs.iter().fold(0.0, |acc, v| acc + v.data()),
+        Op::Mean(vs) => mean_of(vs),
+        Op::Abs(a) => a.data().abs(),
+        Op::Clamp(a, lo, hi) => a.data().clamp(*lo, *hi),
+        Op::Sin(a) => a.data().sin(),
+        Op::Cos(a) => a.data().cos(),
+        Op::Gelu(a) => gelu(a.data()),
+    }
+}
+
+/// Back-propagate from `root`: seed its grad with 1.0 and accumulate (`+=`) into the grad
+/// of every node it depends on
+fn calculate_grad(root: &Value) {
+    let detect_anomaly = DETECT_ANOMALY.with(Cell::get);
+    root.set_grad(1.0);
+    for v in reverse_topological_order(root) {
+        assert!(
+            !v.0.freed.load(Ordering::Relaxed),
+            "backward through node {} after its graph was released by free_graph",
+            v.id()
+        );
+        let grad = v.grad();
+        match &*v.op() {
+            Op::None => {}
+            Op::Plus(a, b) => {
+                a.add_grad(grad);
+                b.add_grad(grad);
+            }
+            Op::Sub(a, b) => {
+                a.add_grad(grad);
+                b.add_grad(-grad);
+            }
+            Op::Mul(a, b) => {
+                a.add_grad(grad * b.data());
+                b.add_grad(grad * a.data());
+            }
+            Op::MulAdd(a, b, c) => {
+                a.add_grad(grad * b.data());
+                b.add_grad(grad * a.data());
+                c.add_grad(grad);
+            }
+            Op::Div(a, b) => {
+                let d = b.data();
+                a.add_grad(grad / d);
+                b.add_grad(-grad * a.data() / (d * d));
+            }
+            Op::Max(a, b) => {
+                if a.data() >= b.data() {
+                    a.add_grad(grad);
+                } else {
+                    b.add_grad(grad);
+                }
+            }
+            Op::Pow(a, e) => {
+                a.add_grad(grad * e * a.data().powf(e - 1.0));
+            }
+            // ln is undefined for a base <= 0, where the exponent gets no grad
+            Op::PowV(a, b) => {
+                let (x, e) = (a.data(), b.data());
+                a.add_grad(grad * e * x.powf(e - 1.0));
+                if x > 0.0 {
+                    b.add_grad(grad * v.data() * x.ln());
+                }
+            }
+            Op::Tanh(a) => {
+                let t = v.data();
+                a.add_grad(grad * (1.0 - t * t));
+            }
+            // d/dx e^x is the output itself
+            Op::Exp(a) => a.add_grad(grad * v.data()),
+            Op::Ln(a) => a.add_grad(grad / a.data()),
+            // the subgradient at exactly 0 is taken to be 0
+            Op::ReLU(a) => {
+                if a.data() > 0.0 {
+                    a.add_grad(grad);
+                }
+            }
+            // like ReLU, exactly 0 counts as negative
+            Op::LeakyReLU(a, slope) => {
+                let d = if a.data() > 0.0 { 1.0 } else { *slope };
+                a.add_grad(grad * d);
+            }
+            Op::Sigmoid(a) => {
+                let s = v.data();
+                a.add_grad(grad * s * (1.0 - s));
+            }
+            Op::Neg(a) => a.add_grad(-grad),
+            Op::Bce(a, t) => {
+                let p = clamp_probability(a.data());
+                a.add_grad(grad * (p - t) / (p * (1.0 - p)));
+            }
+            // the sigmoid's derivative cancels the log's, leaving `sigmoid(x) - t`
+            Op::BceWithLogits(a, t) => {
+                a.add_grad(grad * (stable_sigmoid(a.data()) - t));
+            }
+            Op::Sum(vs) => {
+                for a in vs {
+                    a.add_grad(grad);
+                }
+            }
+            Op::Mean(vs) => {
+                let share = grad / vs.len() as Scalar;
+                for a in vs {
+                    a.add_grad(share);
+                }
+            }
+            // sign(x), with 0 at exactly 0 rather than the NaN of x / |x|
+            Op::Abs(a) => {
+                let x = a.data();
+                if x != 0.0 {
+                    a.add_grad(grad * x.signum());
+                }
+            }
+            Op::Clamp(a, lo, hi) => {
+                if *lo < a.data() && a.data() < *hi {
+                    a.add_grad(grad);
+                }
+            }
+            Op::Sin(a) => a.add_grad(grad * a.data().cos()),
+            Op::Cos(a) => a.add_grad(-grad * a.data().sin()),
+            Op::Gelu(a) => a.add_grad(grad * gelu_grad(a.data())),
+        }
+        if detect_anomaly {
+            let op = v.op();
+            for_each_child(&op, |c| {
+                if !c.grad().is_finite() {
+                    panic!(
+                        "anomaly: backward of node {}{} computed by {} with grad {} wrote \
+                         grad {} into operand {}; operands [{}]",
+                        v.id(),
+                        describe_label(&v),
+                        op.name(),
+                        grad,
+                        c.grad(),
+                        c.id(),
+                        describe_operands(&op)
+                    );
+                }
+            });
+        }
+    }
+}
+
+/// Gradients of `root` with respect to each of `wrt`, built as graph nodes rather than
+/// written into the grad cells. The results can be differentiated again, e.g. with
+/// `backward` or another `backward_graph`, for second derivatives and Hessian-vector
+/// products. Inputs `root` doesn't depend on get a zero leaf. Leaves every grad alone.
+///
+/// ```
+/// use micrograd::{backward_graph, Value};
+///
+/// let x = Value::new(3.0);
+/// let y = &x * &x * &x;
+/// let dy = backward_graph(&y, &[x.clone()]).remove(0);
+/// let d2y = backward_graph(&dy, &[x.clone()]).remove(0);
+/// assert_eq!((dy.data(), d2y.data()), (27.0, 18.0));
+/// ```
+pub fn backward_graph(root: &Value, wrt: &[Value]) -> Vec<Value> {
+    let mut grads: HashMap<usize, Value> = HashMap::new();
+    grads.insert(root.id(), Value::new(1.0));
+    for v in reverse_topological_order(root) {
+        assert!(
+            !v.0.freed.load(Ordering::Relaxed),
+            "backward through node {} after its graph was released by free_graph",
+            v.id()
+        );
+        // nodes no path from the root reaches through a differentiable op
+        let g = match grads.get(&v.id()) {
+            Some(g) => g.clone(),
+            None => continue,
+        };
+        match &*v.op() {
+            Op::None => {}
+            Op::Plus(a, b) => {
+                accumulate(&mut grads, a, g.clone());
+                accumulate(&mut grads, b, g);
+            }
+            Op::Sub(a, b) => {
+                accumulate(&mut grads, a, g.clone());
+                accumulate(&mut grads, b, -g);
+            }
+            Op::Mul(a, b) => {
+                accumulate(&mut grads, a, &g * b);
+                accumulate(&mut grads, b, g * a);
+            }
+            Op::MulAdd(a, b, c) => {
+                accumulate(&mut grads, a, &g * b);
+                accumulate(&mut grads, b, &g * a);
+                accumulate(&mut grads, c, g);
+            }
+            Op::Div(a, b) => {
+                accumulate(&mut grads, a, &g / b);
+                accumulate(&mut grads, b, -g * a / (b * b));
+            }
+            Op::Max(a, b) => {
+                if a.data() >= b.data() {
+                    accumulate(&mut grads, a, g);
+                } else {
+                    accumulate(&mut grads, b, g);
+                }
+            }
+            Op::Pow(a, e) => accumulate(&mut grads, a, g * *e * a.pow(e - 1.0)),
+            Op::PowV(a, b) => {
+                accumulate(&mut grads, a, &g * b * a.powv(&(b - 1.0)));
+                if a.data() > 0.0 {
+                    accumulate(&mut grads, b, g * &v * a.ln());
+                }
+            }
+            Op::Tanh(a) => accumulate(&mut grads, a, g * (1.0 - &v * &v)),
+            Op::Exp(a) => accumulate(&mut grads, a, g * &v),
+            Op::Ln(a) => accumulate(&mut grads, a, g / a),
+            Op::ReLU(a) => {
+                if a.data() > 0.0 {
+                    accumulate(&mut grads, a, g);
+                }
+            }
+            Op::LeakyReLU(a, slope) => {
+                let d = if a.data() > 0.0 { 1.0 } else { *slope };
+                accumulate(&mut grads, a, g * d);
+            }
+            Op::Sigmoid(a) => accumulate(&mut grads, a, g * &v * (1.0 - &v)),
+            Op::Neg(a) => accumulate(&mut grads, a, -g),
+            Op::Bce(a, t) => {
+                let p = a.clamp(BCE_EPS, 1.0 - BCE_EPS);
+                accumulate(&mut grads, a, g * (&p - *t) / (&p * (1.0 - &p)));
+            }
+            Op::BceWithLogits(a, t) => accumulate(&mut grads, a, g * (a.sigmoid() - *t)),
+            Op::Sum(vs) => {
+                for a in vs {
+                    accumulate(&mut grads, a, g.clone());
+                }
+            }
+            Op::Mean(vs) => {
+                let share = g / vs.len() as Scalar;
+                for a in vs {
+                    accumulate(&mut grads, a, share.clone());
+                }
+            }
+            Op::Abs(a) => {
+                let x = a.data();
+                if x != 0.0 {
+                    accumulate(&mut grads, a, g * x.signum());
+                }
+            }
+            Op::Clamp(a, lo, hi) => {
+                if *lo < a.data() && a.data() < *hi {
+                    accumulate(&mut grads, a, g);
+                }
+            }
+            Op::Sin(a) => accumulate(&mut grads, a, g * a.cos()),
+            Op::Cos(a) => accumulate(&mut grads, a, -g * a.sin()),
+            Op::Gelu(a) => {
+                let t = ((a + GELU_CUBIC * a.pow(3.0)) * GELU_SCALE).tanh();
+                let inner = (GELU_CUBIC * 3.0) * a.pow(2.0) * GELU_SCALE + GELU_SCALE;
+                let d = 0.5 * (1.0 + &t) + 0.5 * a * (1.0 - &t * &t) * inner;
+                accumulate(&mut grads, a, g * d);
+            }
+        }
+    }
+    wrt.iter()
+        .map(|x| {
+            grads
+                .get(&x.id())
+                .cloned()
+                .unwrap_or_else(|| Value::new(0.0))
+        })
+        .collect()
+}
+
+/// `grads[v] += g`, for `backward_graph`
+fn accumulate(grads: &mut HashMap<usize, Value>, v: &Value, g: Value) {
+    let total = match grads.remove(&v.id()) {
+        Some(acc) => acc + g,
+        None => g,
+    };
+    grads.insert(v.id(), total);
+}
+
+macro_rules! value_op_impl {
+    ($bound:ident, $func:ident, $variant:ident, $op:tt) => {
+        impl std::ops::$bound<&Value> for &Value {
+            type Output = Value;
+            fn $func(self, rhs: &Value) -> Self::Output {
+                let d = self.data() $op rhs.data();
+                Value::from_op(d, Op::$variant(self.clone(), rhs.clone()))
+            }
+        }
+
+        impl std::ops::$bound for Value {
+            type Output = Value;
+            fn $func(self, rhs: Self) -> Self::Output {
+                &self $op &rhs
+            }
+        }
+
+        impl std::ops::$bound<&Value> for Value {
+            type Output = Value;
+            fn $func(self, rhs: &Value) -> Self::Output {
+                &self $op rhs
+            }
+        }
+
+        impl std::ops::$bound<Value> for &Value {
+            type Output = Value;
+            fn $func(self, rhs: Value) -> Self::Output {
+                self $op &rhs
+            }
+        }
+
+        // scalars become constant leaves, which take part in backward like any other node
+        impl std::ops::$bound<Scalar> for &Value {
+            type Output = Value;
+            fn $func(self, rhs: Scalar) -> Self::Output {
+                self $op &Value::new(rhs)
+            }
+        }
+
+        impl std::ops::$bound<&Value> for Scalar {
+            type Output = Value;
+            fn $func(self, rhs: &Value) -> Self::Output {
+                &Value::new(self) $op rhs
+            }
+        }
+
+        impl std::ops::$bound<Scalar> for Value {
+            type Output = Value;
+            fn $func(self, rhs: Scalar) -> Self::Output {
+                &self $op rhs
+            }
+        }
+
+        impl std::ops::$bound<Value> for Scalar {
+            type Output = Value;
+            fn $func(self, rhs: Value) -> Self::Output {
+                self $op &rhs
+            }
+        }
+    };
+}
+
+value_op_impl!(Add, add, Plus, +);
+value_op_impl!(Sub, sub, Sub, -);
+value_op_impl!(Mul, mul, Mul, *);
+// IEEE semantics: dividing by a zero-valued node gives inf or NaN, in the forward pass
+// and in the grads, rather than a panic
+value_op_impl!(Div, div, Div, /);
+
+/// Compound assignment rebinds the handle to a new node wrapping the old one, it doesn't
+/// mutate the node in place: other clones of the old value keep their meaning and the
+/// graph records every step.
+macro_rules! value_assign_impl {
+    ($bound:ident, $func:ident, $op:tt) => {
+        impl std::ops::$bound<&Value> for Value {
+            fn $func(&mut self, rhs: &Value) {
+                *self = &*self $op rhs;
+            }
+        }
+
+        impl std::ops::$bound<Value> for Value {
+            fn $func(&mut self, rhs: Value) {
+                *self = &*self $op &rhs;
+            }
+        }
+    };
+}
+
+value_assign_impl!(AddAssign, add_assign, +);
+value_assign_impl!(SubAssign, sub_assign, -);
+value_assign_impl!(MulAssign, mul_assign, *);
+
+impl std::ops::Neg for &Value {
+    type Output = Value;
+    fn neg(self) -> Self::Output {
+        Value::from_op(-self.data(), Op::Neg(self.clone()))
+    }
+}
+
+impl std::ops::Neg for Value {
+    type Output = Value;
+    fn neg(self) -> Self::Output {
+        -&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{evaluate, for_each_child, op_metadata, Op, Value};
+
+    /// one instance of every Op variant; the match fails to compile when a variant is added
+    fn instance(op: &Op) -> Value {
+        let (a, b) = (Value::new(1.0), Value::new(2.0));
+        match op {
+            Op::None => a,
+            Op::Plus(..) => &a + &b,
+            Op::Sub(..) => &a - &b,
+            Op::Mul(..) => &a * &b,
+            Op::MulAdd(..) => a.mul_add(&b, &b),
+            Op::Div(..) => &a / &b,
+            Op::Max(..) => a.max(&b),
+            Op::Pow(..) => a.pow(2.0),
+            Op::PowV(..) => a.powv(&b),
+            Op::Tanh(..) => a.tanh(),
+            Op::Exp(..) => a.exp(),
+            Op::Ln(..) => a.ln(),
+            Op::ReLU(..) => a.relu(),
+            Op::LeakyReLU(..) => a.leaky_relu(0.1),
+            Op::Sigmoid(..) => a.sigmoid(),
+            Op::Neg(..) => -&a,
+            Op::Bce(..) => crate::losses::binary_cross_entropy(&a.sigmoid(), 1.0),
+            Op::BceWithLogits(..) => crate::losses::bce_with_logits(&a, 1.0),
+            Op::Sum(..) => Value::sum(&[a, b]),
+            Op::Mean(..) => Value::mean(&[a, b]),
+            Op::Abs(..) => a.abs(),
+            Op::Clamp(..) => a.clamp(0.0, 1.0),
+            Op::Sin(..) => a.sin(),
+            Op::Cos(..) => a.cos(),
+            Op::Gelu(..) => a.gelu(),
+        }
+    }
+
+    /// every Op variant, over placeholder operands; add new ones here as well as to
+    /// `instance`
+    fn variants() -> Vec<Op> {
+        let x = Value::new(0.0);
+        vec![
+            Op::None,
+            Op::Plus(x.clone(), x.clone()),
+            Op::Sub(x.clone(), x.clone()),
+            Op::Mul(x.clone(), x.clone()),
+            Op::MulAdd(x.clone(), x.clone(), x.clone()),
+            Op::Div(x.clone(), x.clone()),
+            Op::Max(x.clone(), x.clone()),
+            Op::Pow(x.clone(), 2.0),
+            Op::PowV(x.clone(), x.clone()),
+            Op::Tanh(x.clone()),
+            Op::Exp(x.clone()),
+            Op::Ln(x.clone()),
+            Op::ReLU(x.clone()),
+            Op::LeakyReLU(x.clone(), 0.1),
+            Op::Sigmoid(x.clone()),
+            Op::Neg(x.clone()),
+            Op::Bce(x.clone(), 1.0),
+            Op::BceWithLogits(x.clone(), 1.0),
+            Op::Sum(vec![x.clone(), x.clone()]),
+            Op::Mean(vec![x.clone(), x.clone()]),
+            Op::Abs(x.clone()),
+            Op::Clamp(x.clone(), 0.0, 1.0),
+            Op::Sin(x.clone()),
+            Op::Cos(x.clone()),
+            Op::Gelu(x),
+        ]
+    }
+
+    #[test]
+    fn op_metadata_is_consistent() {
+        for op in &variants() {
+            let v = instance(op);
+            assert_eq!(std::mem::discriminant(&*v.op()), std::mem::discriminant(op));
+            let meta = op_metadata(op);
+            let mut children = 0;
+            for_each_child(&v.op(), |_| children += 1);
+            assert_eq!(children, meta.arity, "{:?}", op);
+            assert_eq!(v.is_leaf(), meta.arity == 0);
+            if !v.is_leaf() {
+                assert!(!meta.symbol.is_empty(), "{:?} has no symbol", op);
+            }
+            assert_eq!(format!("{}", op), meta.symbol);
+            assert_eq!(op.name(), meta.name);
+            if !v.is_leaf() {
+                assert_eq!(evaluate(&v.op()).to_bits(), v.data().to_bits(), "{:?}", op);
+            }
+        }
+    }
+
+    /// the parser keeps its own name table, so a new op must be added there too
+    #[cfg(feature = "serde")]
+    #[test]
+    fn every_op_survives_a_json_round_trip() {
+        for op in &variants() {
+            let v = instance(op);
+            let parsed = super::graph_from_json(&v.to_json())
+                .unwrap_or_else(|e| panic!("{:?} does not parse back: {}", op, e));
+            assert_eq!(parsed.op().name(), op.name());
+            assert_eq!(parsed.structural_hash(), v.structural_hash(), "{:?}", op);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//! JSON dump of a computation graph, one record per node
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{op_metadata, topological_order, Op, Value};
+use crate::Scalar;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct NodeRecord {
+    /// position in the dump
+    id: usize,
+    data: Scalar,
+    grad: Scalar,
+
+    /// `Op::name`
+    op: String,
+
+    /// constants of the op: the exponent of "pow", the slope of "leaky_relu", the target
+    /// of "bce" and "bce_with_logits", the bounds of "clamp"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    params: Vec<Scalar>,
+
+    children: Vec<usize>,
+
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    label: String,
+}
+
+/// Reasons `graph_from_json` rejects its input
+#[derive(Debug)]
+pub enum ParseError {
+    /// not a JSON array of node records
+    Json(serde_json::Error),
+
+    /// no nodes, so no root
+    Empty,
+
+    /// ids must count up from 0 in order
+    UnexpectedId {
+        expected: usize,
+        got: usize,
+    },
+
+    UnknownOp {
+        id: usize,
+        op: String,
+    },
+
+    /// a child id that is not an earlier node
+    DanglingChild {
+        id: usize,
+        child: usize,
+    },
+
+    /// the op takes a different number of children
+    Arity {
+        id: usize,
+        expected: usize,
+        got: usize,
+    },
+
+    /// the op takes a different number of constants
+    Params {
+        id: usize,
+        expected: usize,
+        got: usize,
+    },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Json(e) => write!(f, "invalid graph: {}", e),
+            ParseError::Empty => write!(f, "graph has no nodes"),
+            ParseError::UnexpectedId { expected, got } => {
+                write!(f, "expected node id {}, got {}", expected, got)
+            }
+            ParseError::UnknownOp { id, op } => write!(f, "node {} has unknown op {:?}", id, op),
+            ParseError::DanglingChild { id, child } => write!(
+                f,
+                "node {} refers to child {}, which is not an earlier node",
+                id, child
+            ),
+            ParseError::Arity { id, expected, got } => write!(
+                f,
+                "node {} has {} children, its op takes {}",
+                id, got, expected
+            ),
+            ParseError::Params { id, expected, got } => write!(
+                f,
+                "node {} has {} op constants, its op takes {}",
+                id, got, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(e: serde_json::Error) -> Self {
+        ParseError::Json(e)
+    }
+}
+
+fn params_of(op: &Op) -> Vec<Scalar> {
+    match op {
+        Op::Pow(_, c) | Op::LeakyReLU(_, c) | Op::Bce(_, c) | Op::BceWithLogits(_, c) => vec![*c],
+        Op::Clamp(_, lo, hi) => vec![*lo, *hi],
+        _ => vec![],
+    }
+}
+
+/// The op named `name` over `children`, checking the counts against the node's record.
+/// Missing operands and constants are filled in to build the op, so the counts it takes
+/// come from `op_metadata` and `params_of` rather than a second table here.
+fn op_from_record(record: &NodeRecord, children: Vec<Value>) -> Result<Op, ParseError> {
+    let id = record.id;
+    let got = children.len();
+    let mut c = children.into_iter();
+    let mut next = || c.next().unwrap_or_else(|| Value::new(0.0));
+    let p = |i: usize| record.params.get(i).copied().unwrap_or(0.0);
+    let op = match record.op.as_str() {
+        "none" => Op::None,
+        "plus" => Op::Plus(next(), next()),
+        "sub" => Op::Sub(next(), next()),
+        "mul" => Op::Mul(next(), next()),
+        "mul_add" => Op::MulAdd(next(), next(), next()),
+        "div" => Op::Div(next(), next()),
+        "max" => Op::Max(next(), next()),
+        "pow" => Op::Pow(next(), p(0)),
+        "powv" => Op::PowV(next(), next()),
+        "leaky_relu" => Op::LeakyReLU(next(), p(0)),
+        "bce" => Op::Bce(next(), p(0)),
+        "bce_with_logits" => Op::BceWithLogits(next(), p(0)),
+        "clamp" => Op::Clamp(next(), p(0), p(1)),
+        "tanh" => Op::Tanh(next()),
+        "exp" => Op::Exp(next()),
+        "ln" => Op::Ln(next()),
+        "relu" => Op::ReLU(next()),
+        "sigmoid" => Op::Sigmoid(next()),
+        "neg" => Op::Neg(next()),
+        "abs" => Op::Abs(next()),
+        "sin" => Op::Sin(next()),
+        "cos" => Op::Cos(next()),
+        "gelu" => Op::Gelu(next()),
+        "sum" => Op::Sum((0..got).map(|_| next()).collect()),
+        // takes at least one operand
+        "mean" => Op::Mean((0..got.max(1)).map(|_| next()).collect()),
+        op => return Err(ParseError::UnknownOp { id, op: op.into() }),
+    };
+    let expected = op_metadata(&op).arity;
+    if got != expected {
+        return Err(ParseError::Arity { id, expected, got });
+    }
+    let params = params_of(&op).len();
+    if record.params.len() != params {
+        return Err(ParseError::Params {
+            id,
+            expected: params,
+            got: record.params.len(),
+        });
+    }
+    Ok(op)
+}
+
+impl Value {
+    /// The reachable graph as a JSON array of
+    /// `{"id", "data", "grad", "op", "params", "children", "label"}` records, children
+    /// before parents and the root last. Ids are positions in the array rather than
+    /// `Value::id`, so the output only depends on the graph; `op` is `Op::name`, and
+    /// `params` and `label` are left out when empty.
+    pub fn to_json(&self) -> String {
+        let order = topological_order(self);
+        let index: HashMap<usize, usize> =
+            order.iter().enumerate().map(|(i, v)| (v.id(), i)).collect();
+        let records: Vec<NodeRecord> = order
+            .iter()
+            .enumerate()
+            .map(|(i, v)| NodeRecord {
+                id: i,
+                data: v.data(),
+                grad: v.grad(),
+                op: v.op().name().into(),
+                params: params_of(&v.op()),
+                children: v.children().iter().map(|c| index[&c.id()]).collect(),
+                label: v.label(),
+            })
+            .collect();
+        serde_json::to_string(&records).expect("graph records always serialize")
+    }
+}
+
+/// Rebuild a graph written by `Value::to_json` and return its root, the last record.
+/// Nodes get fresh ids; data, grads and labels are taken as stored, not recomputed.
+pub fn graph_from_json(json: &str) -> Result<Value, ParseError> {
+    let records: Vec<NodeRecord> = serde_json::from_str(json)?;
+    let mut nodes: Vec<Value> = Vec::with_capacity(records.len());
+    for (expected, record) in records.iter().enumerate() {
+        if record.id != expected {
+            return Err(ParseError::UnexpectedId {
+                expected,
+                got: record.id,
+            });
+        }
+        let children = record
+            .children
+            .iter()
+            .map(|&child| {
+                nodes.get(child).cloned().ok_or(ParseError::DanglingChild {
+                    id: record.id,
+                    child,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let op = op_from_record(record, children)?;
+        let v = Value::from_op(record.data, op).with_label(record.label.clone());
+        v.set_grad(record.grad);
+        nodes.push(v);
+    }
+    nodes.pop().ok_or(ParseError::Empty)
+}
@@ -0,0 +1,155 @@
+//! Graphviz rendering of a computation graph.
+//!
+//! [`Value::to_dot_with`] writes the graph rooted at a value as a DOT string, to be piped
+//! into `dot -Tsvg` or any Graphviz binding, with the layout of [`VizOptions`]. Unlike
+//! the plain [`Value::to_dot`], nodes are numbered by position, children before parents,
+//! so the output only depends on the graph and not on `Value::id`.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::value::reverse_topological_order;
+use crate::{Scalar, Value};
+
+/// Direction the graph is laid out in, leaves first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RankDir {
+    /// left to right
+    #[default]
+    LR,
+
+    /// top to bottom
+    TB,
+}
+
+/// Settings of `Value::to_dot_with`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VizOptions {
+    /// draw each op as its own small node between its operands and its result, instead
+    /// of labeling the edges with it
+    pub split_op_nodes: bool,
+
+    pub rankdir: RankDir,
+
+    /// fill every value node by its |grad|, from white for 0 to red for the largest in
+    /// the graph
+    pub color_by_grad: bool,
+
+    /// values to draw at most, the root always among them; the rest collapse into a
+    /// single ellipsis node
+    pub max_nodes: Option<usize>,
+}
+
+impl Default for VizOptions {
+    fn default() -> Self {
+        VizOptions {
+            split_op_nodes: true,
+            rankdir: RankDir::LR,
+            color_by_grad: false,
+            max_nodes: None,
+        }
+    }
+}
+
+/// `s` inside a quoted record label, where braces, bars and angle brackets are syntax
+fn escape_record(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '"' | '\\' | '{' | '}' | '|' | '<' | '>') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// `#rrggbb` from white at `t = 0` to red at `t = 1`
+fn heat(t: Scalar) -> String {
+    let c = (255.0 * (1.0 - t.clamp(0.0, 1.0))).round() as u8;
+    format!("#ff{:02x}{:02x}", c, c)
+}
+
+impl Value {
+    /// The graph rooted here in Graphviz DOT, one record per value showing its label,
+    /// data and grad. Values kept out by `max_nodes` are the ones furthest from the root:
+    /// every drawn value's consumers are drawn too.
+    pub fn to_dot_with(&self, options: &VizOptions) -> String {
+        // root first, every node after all of its consumers
+        let mut order = reverse_topological_order(self);
+        let hidden = match options.max_nodes {
+            Some(max) if order.len() > max.max(1) => order.split_off(max.max(1)).len(),
+            _ => 0,
+        };
+        order.reverse();
+        let index: HashMap<usize, usize> =
+            order.iter().enumerate().map(|(i, v)| (v.id(), i)).collect();
+        let max_grad = order.iter().map(|v| v.grad().abs()).fold(0.0, Scalar::max);
+
+        let rankdir = match options.rankdir {
+            RankDir::LR => "LR",
+            RankDir::TB => "TB",
+        };
+        let mut dot = format!(
+            "digraph {{\n  rankdir={};\n  node [shape=record];\n",
+            rankdir
+        );
+        if hidden > 0 {
+            writeln!(
+                dot,
+                "  more [label=\"... {} more\", shape=plaintext];",
+                hidden
+            )
+            .unwrap();
+        }
+        for (i, v) in order.iter().enumerate() {
+            let mut attrs = format!(
+                "label=\"{{ {} | data {:.4} | grad {:.4} }}\"",
+                escape_record(&v.label()),
+                v.data(),
+                v.grad()
+            );
+            if options.color_by_grad {
+                let t = if max_grad > 0.0 {
+                    v.grad().abs() / max_grad
+                } else {
+                    0.0
+                };
+                write!(attrs, ", style=filled, fillcolor=\"{}\"", heat(t)).unwrap();
+            }
+            writeln!(dot, "  n{} [{}];", i, attrs).unwrap();
+
+            let children = v.children();
+            if children.is_empty() {
+                continue;
+            }
+            let op = format!("{}", *v.op())
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"");
+            let target = if options.split_op_nodes {
+                writeln!(dot, "  n{}_op [label=\"{}\", shape=circle];", i, op).unwrap();
+                writeln!(dot, "  n{}_op -> n{};", i, i).unwrap();
+                format!("n{}_op", i)
+            } else {
+                format!("n{}", i)
+            };
+            let edge_label = if options.split_op_nodes {
+                String::new()
+            } else {
+                format!(" [label=\"{}\"]", op)
+            };
+            let mut linked_more = false;
+            for c in &children {
+                match index.get(&c.id()) {
+                    Some(j) => writeln!(dot, "  n{} -> {}{};", j, target, edge_label).unwrap(),
+                    None if !linked_more => {
+                        linked_more = true;
+                        writeln!(dot, "  more -> {}{};", target, edge_label).unwrap();
+                    }
+                    None => {}
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
@@ -0,0 +1,217 @@
+use std::time::Instant;
+
+use micrograd::arena::{Graph, Var};
+use micrograd::losses::{bce_with_logits, binary_cross_entropy};
+use micrograd::optim::Sgd;
+use micrograd::train::FitConfig;
+use micrograd::{Activation, LayerConfig, MlpBuilder, Scalar, Value, MLP};
+
+fn batch() -> (Vec<Vec<Scalar>>, Vec<Scalar>) {
+    let xs: Vec<Vec<Scalar>> = (0..16)
+        .map(|i| {
+            let t = i as Scalar / 8.0 - 1.0;
+            vec![t, t * t]
+        })
+        .collect();
+    let ys = xs.iter().map(|x| (x[0] * 3.0).sin()).collect();
+    (xs, ys)
+}
+
+/// Mean squared error over the batch, in the arena, with the same association as `mse`
+fn arena_loss<'g>(
+    g: &'g Graph,
+    mlp: &MLP,
+    params: &[Var<'g>],
+    xs: &[Vec<Scalar>],
+    ys: &[Scalar],
+) -> Var<'g> {
+    let mut total = g.leaf(0.0);
+    for (x, y) in xs.iter().zip(ys) {
+        let x: Vec<Var> = x.iter().map(|&xi| g.leaf(xi)).collect();
+        let d = g.apply_mlp(mlp, params, &x)[0] - *y;
+        total = total + d * d;
+    }
+    total / xs.len() as Scalar
+}
+
+#[test]
+fn ops_match_the_value_engine() {
+    let g = Graph::new();
+    let (a, b) = (g.leaf(0.8), g.leaf(-1.7));
+    let y = ((a * b).tanh() + (a / b).exp() - b.pow(2.0)).sigmoid() * (a.ln() + -b.relu())
+        + b.leaky_relu(0.2) * 3.0;
+    y.backward();
+
+    let (va, vb) = (Value::new(0.8), Value::new(-1.7));
+    let vy = ((&va * &vb).tanh() + (&va / &vb).exp() - vb.pow(2.0)).sigmoid()
+        * (va.ln() + -vb.relu())
+        + vb.leaky_relu(0.2) * 3.0;
+    vy.backward();
+
+    assert_eq!(y.data(), vy.data());
+    assert_eq!(a.grad(), va.grad());
+    assert_eq!(b.grad(), vb.grad());
+}
+
+#[test]
+fn remaining_ops_match_the_value_engine() {
+    let g = Graph::new();
+    let (a, b, c) = (g.leaf(0.8), g.leaf(-1.7), g.leaf(2.3));
+    let y = a.mul_add(b, c).abs() + a.max(b).sin() + c.powv(a)
+        - b.clamp(-1.0, 1.0).cos() * a.clamp(0.0, 1.0)
+        + a.sigmoid().binary_cross_entropy(0.3)
+        + b.bce_with_logits(1.0);
+    y.backward();
+
+    let (va, vb, vc) = (Value::new(0.8), Value::new(-1.7), Value::new(2.3));
+    let vy = va.mul_add(&vb, &vc).abs() + va.max(&vb).sin() + vc.powv(&va)
+        - vb.clamp(-1.0, 1.0).cos() * va.clamp(0.0, 1.0)
+        + binary_cross_entropy(&va.sigmoid(), 0.3)
+        + bce_with_logits(&vb, 1.0);
+    vy.backward();
+
+    assert_eq!(y.data(), vy.data());
+    assert_eq!(
+        [a.grad(), b.grad(), c.grad()],
+        [va.grad(), vb.grad(), vc.grad()]
+    );
+}
+
+#[test]
+fn shared_operands_accumulate_and_later_nodes_are_ignored() {
+    let g = Graph::with_capacity(8);
+    let x = g.leaf(3.0);
+    let y = x * x * x;
+    let z = y + x;
+    y.backward();
+    assert_eq!(x.grad(), 27.0);
+    assert_eq!(z.grad(), 0.0);
+
+    // a second pass resets instead of adding
+    z.backward();
+    assert_eq!(x.grad(), 28.0);
+    assert_eq!(g.len(), 4);
+}
+
+#[test]
+#[should_panic(expected = "operands belong to different graphs")]
+fn mixing_graphs_panics() {
+    let (g, h) = (Graph::new(), Graph::new());
+    let _ = g.leaf(1.0) + h.leaf(2.0);
+}
+
+#[test]
+fn apply_mlp_skips_missing_biases() {
+    let mlp = MlpBuilder::new(2)
+        .layer_with(
+            3,
+            LayerConfig {
+                bias: false,
+                ..Default::default()
+            },
+        )
+        .layer(1, Activation::Linear)
+        .seed(8)
+        .build()
+        .unwrap();
+    let g = Graph::new();
+    let params = g.load_parameters(&mlp);
+    assert_eq!(params.len(), 6 + 4);
+    let x = [0.3, -0.6];
+    let out = g.apply_mlp(&mlp, &params, &x.map(|xi| g.leaf(xi)))[0];
+    assert_eq!(out.data(), mlp.predict(&x)[0]);
+}
+
+#[test]
+fn apply_mlp_matches_apply_bit_for_bit() {
+    let mlp = MLP::new_with_seed(3, &[5, 4, 2], 9);
+    let x = [0.7, -1.3, 0.25];
+    let g = Graph::new();
+    let params = g.load_parameters(&mlp);
+    let out = g.apply_mlp(&mlp, &params, &x.map(|xi| g.leaf(xi)));
+    let expected = mlp.apply_scalars(&x);
+    for (a, b) in out.iter().zip(&expected) {
+        assert_eq!(a.data().to_bits(), b.data().to_bits());
+    }
+}
+
+#[test]
+fn training_matches_the_value_engine() {
+    let (xs, ys) = batch();
+    let sizes = [8, 8, 1];
+    let acts = [
+        Activation::Gelu,
+        Activation::LeakyReLU(0.1),
+        Activation::Linear,
+    ];
+    let reference = MLP::new_with_seed(2, &sizes, 3).with_activations(&acts);
+    let mlp = MLP::new_with_seed(2, &sizes, 3).with_activations(&acts);
+
+    let mut sgd = Sgd::new(reference.get_parameters(), 0.05);
+    for _ in 0..20 {
+        let preds: Vec<Value> = xs
+            .iter()
+            .map(|x| {
+                reference.apply(&x.iter().map(|&xi| Value::new(xi)).collect::<Vec<_>>())[0].clone()
+            })
+            .collect();
+        let targets: Vec<Value> = ys.iter().map(|&y| Value::new(y)).collect();
+        micrograd::losses::mse(&preds, &targets).backward();
+        sgd.step();
+    }
+
+    let g = Graph::new();
+    let params = g.load_parameters(&mlp);
+    let mut last = 0.0;
+    for _ in 0..20 {
+        g.truncate(params.len());
+        let loss = arena_loss(&g, &mlp, &params, &xs, &ys);
+        loss.backward();
+        last = loss.data();
+        for p in &params {
+            p.set_data(p.data() - 0.05 * p.grad());
+        }
+    }
+    assert!(last.is_finite());
+    g.store_parameters(&mlp, &params);
+
+    for (a, b) in mlp.get_parameters().iter().zip(reference.get_parameters()) {
+        assert!(
+            (a.data() - b.data()).abs() < 1e-5,
+            "{} vs {}",
+            a.data(),
+            b.data()
+        );
+    }
+}
+
+/// Per-epoch time of `MLP::fit` against the same full-batch loop in the arena; run with
+/// `cargo test --release --test arena -- --ignored --nocapture`
+#[test]
+#[ignore]
+fn bench_against_the_value_engine() {
+    let (xs, ys) = batch();
+    let targets: Vec<Vec<Scalar>> = ys.iter().map(|&y| vec![y]).collect();
+    let epochs = 200;
+
+    let mlp = MLP::new_with_seed(2, &[32, 32, 1], 1);
+    let start = Instant::now();
+    mlp.fit(&xs, &targets, FitConfig::new(0.05, epochs));
+    let rc = start.elapsed() / epochs as u32;
+
+    let mlp = MLP::new_with_seed(2, &[32, 32, 1], 1);
+    let g = Graph::new();
+    let params = g.load_parameters(&mlp);
+    let start = Instant::now();
+    for _ in 0..epochs {
+        g.truncate(params.len());
+        arena_loss(&g, &mlp, &params, &xs, &ys).backward();
+        for p in &params {
+            p.set_data(p.data() - 0.05 * p.grad());
+        }
+    }
+    let arena = start.elapsed() / epochs as u32;
+
+    println!("per epoch: Rc {:?}, arena {:?}", rc, arena);
+    assert!(arena < rc);
+}
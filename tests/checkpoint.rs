@@ -0,0 +1,655 @@
+use std::path::PathBuf;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use micrograd::checkpoint::LoadError;
+use micrograd::losses::mse;
+use micrograd::optim::Sgd;
+use micrograd::{Activation, Init, LayerConfig, MlpBuilder, Scalar, Value, MLP};
+
+fn outputs(mlp: &MLP) -> Vec<Scalar> {
+    let xs = [[0.5, -1.0, 0.3], [0.1, 0.2, -0.9], [-0.7, 0.9, 0.0]];
+    xs.iter()
+        .flat_map(|x| {
+            let x: Vec<Value> = x.iter().map(|&xi| Value::new(xi)).collect();
+            mlp.apply(&x)
+        })
+        .map(|v| v.data())
+        .collect()
+}
+
+#[cfg(feature = "serde")]
+mod json {
+    use micrograd::checkpoint::Checkpoint;
+    use micrograd::data::BatchIter;
+    use micrograd::losses::mse_batch;
+    use micrograd::{Activation, LayerConfig, MlpBuilder};
+
+    use super::*;
+
+    #[test]
+    fn json_round_trip_is_bit_identical() {
+        let mlp = MLP::new_with_seed(3, &[4, 4, 2], 9).with_activations(&[
+            Activation::ReLU,
+            Activation::Tanh,
+            Activation::Linear,
+        ]);
+        let loaded = MLP::from_json(&mlp.to_json()).unwrap();
+        assert_eq!(outputs(&mlp), outputs(&loaded));
+
+        let labels =
+            |m: &MLP| -> Vec<String> { m.get_parameters().iter().map(Value::label).collect() };
+        assert_eq!(labels(&mlp), labels(&loaded));
+        let original_ids: Vec<usize> = mlp.get_parameters().iter().map(Value::id).collect();
+        assert!(loaded
+            .get_parameters()
+            .iter()
+            .all(|p| p.is_leaf() && p.grad() == 0.0 && !original_ids.contains(&p.id())));
+        assert_eq!(
+            loaded.layers()[2].neurons()[0].activation(),
+            Activation::Linear
+        );
+        // init schemes come back too, so the loaded copy takes the same binary weights
+        assert_eq!(loaded.layers()[0].init(), Some(Init::default()));
+        assert_eq!(loaded.architecture_hash(), mlp.architecture_hash());
+    }
+
+    #[test]
+    fn json_round_trip_keeps_missing_biases() {
+        let mlp = MlpBuilder::new(3)
+            .layer_with(
+                3,
+                LayerConfig {
+                    bias: false,
+                    ..Default::default()
+                },
+            )
+            .layer(1, Activation::Linear)
+            .seed(4)
+            .build()
+            .unwrap();
+        let json = mlp.to_json();
+        let loaded = MLP::from_json(&json).unwrap();
+        assert_eq!(loaded.num_parameters(), mlp.num_parameters());
+        assert!(loaded.layers()[0].biases().is_empty());
+        assert_eq!(outputs(&mlp), outputs(&loaded));
+        assert_eq!(loaded.to_json(), json);
+    }
+
+    #[test]
+    fn json_stores_data_not_grads() {
+        let mlp = MLP::new_with_seed(2, &[1], 3);
+        let before = mlp.to_json();
+        mlp.apply(&[Value::new(1.0), Value::new(2.0)])[0].backward();
+        assert_eq!(mlp.to_json(), before);
+    }
+
+    #[test]
+    fn json_shape_mismatches_are_errors() {
+        let json = MLP::new_with_seed(2, &[2, 1], 3).to_json();
+        let mut state: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        let mut wrong_inputs = state.clone();
+        wrong_inputs["layers"][1]["neurons"][0]["w"]
+            .as_array_mut()
+            .unwrap()
+            .pop();
+        let err = MLP::from_json(&wrong_inputs.to_string()).unwrap_err();
+        assert!(matches!(
+            err,
+            LoadError::NeuronInputs {
+                layer: 1,
+                neuron: 0,
+                expected: 2,
+                got: 1
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "neuron 0 of layer 1 should have 2 weights, got 1"
+        );
+
+        let mut wrong_width = state.clone();
+        wrong_width["sizes"][1] = 3.into();
+        assert!(matches!(
+            MLP::from_json(&wrong_width.to_string()),
+            Err(LoadError::LayerWidth {
+                layer: 0,
+                expected: 3,
+                got: 2
+            })
+        ));
+
+        state["sizes"].as_array_mut().unwrap().pop();
+        assert!(matches!(
+            MLP::from_json(&state.to_string()),
+            Err(LoadError::LayerCount { .. })
+        ));
+
+        assert!(matches!(
+            MLP::from_json("{\"sizes\": 3}"),
+            Err(LoadError::Json(_))
+        ));
+    }
+
+    const STATE_DICT: &str = r#"{
+        "layers.0.weight": [[0.5, -0.25], [1.0, 0.0], [-0.5, 0.75]],
+        "layers.0.bias": [0.1, -0.2, 0.0],
+        "layers.1.weight": [[1.0, -1.0, 0.5]],
+        "layers.1.bias": [0.25]
+    }"#;
+
+    #[test]
+    fn state_dict_forward_matches_hand_computation() {
+        let mut mlp = MLP::new(2, &[3, 1]);
+        mlp.load_state_dict_json(STATE_DICT).unwrap();
+
+        let x: [Scalar; 2] = [0.8, -0.4];
+        let h = [
+            (0.5 * x[0] - 0.25 * x[1] + 0.1).tanh(),
+            (1.0 * x[0] + 0.0 * x[1] - 0.2).tanh(),
+            (-0.5 * x[0] + 0.75 * x[1] + 0.0).tanh(),
+        ];
+        let expected = (h[0] - h[1] + 0.5 * h[2] + 0.25).tanh();
+        let out = mlp.apply(&x.map(Value::new))[0].data();
+        assert!((out - expected).abs() < 1e-6, "{} vs {}", out, expected);
+
+        // row j of a weight matrix is neuron j
+        let n2 = &mlp.layers()[0].neurons()[2];
+        let w: Vec<Scalar> = n2.weights().iter().map(Value::data).collect();
+        assert_eq!(w, vec![-0.5, 0.75]);
+        assert_eq!(n2.bias().unwrap().data(), 0.0);
+    }
+
+    #[test]
+    fn state_dict_skips_missing_biases() {
+        let mut mlp = MlpBuilder::new(2)
+            .layer_with(
+                3,
+                LayerConfig {
+                    bias: false,
+                    ..Default::default()
+                },
+            )
+            .layer(1, Activation::Tanh)
+            .build()
+            .unwrap();
+        let err = mlp.load_state_dict_json(STATE_DICT).unwrap_err();
+        assert!(matches!(&err, LoadError::UnexpectedKey(k) if k == "layers.0.bias"));
+
+        let no_bias = STATE_DICT.replace(r#""layers.0.bias": [0.1, -0.2, 0.0],"#, "");
+        mlp.load_state_dict_json(&no_bias).unwrap();
+        let x: [Scalar; 2] = [0.8, -0.4];
+        let h = [
+            (0.5 * x[0] - 0.25 * x[1]).tanh(),
+            (1.0 * x[0] + 0.0 * x[1]).tanh(),
+            (-0.5 * x[0] + 0.75 * x[1]).tanh(),
+        ];
+        let expected = (h[0] - h[1] + 0.5 * h[2] + 0.25).tanh();
+        let out = mlp.apply(&x.map(Value::new))[0].data();
+        assert!((out - expected).abs() < 1e-6, "{} vs {}", out, expected);
+    }
+
+    #[test]
+    fn state_dict_errors_are_distinct() {
+        let mut mlp = MLP::new_with_seed(2, &[3, 1], 1);
+        let before = mlp
+            .get_parameters()
+            .iter()
+            .map(Value::data)
+            .collect::<Vec<_>>();
+
+        let missing = STATE_DICT.replace(r#""layers.1.bias": [0.25]"#, r#""other": [0.25]"#);
+        let err = mlp.load_state_dict_json(&missing).unwrap_err();
+        assert!(matches!(&err, LoadError::MissingKey(k) if k == "layers.1.bias"));
+        assert_eq!(err.to_string(), r#"state dict has no "layers.1.bias""#);
+
+        let extra = STATE_DICT.replace('}', r#", "layers.2.weight": [[1.0]]}"#);
+        let err = mlp.load_state_dict_json(&extra).unwrap_err();
+        assert!(matches!(&err, LoadError::UnexpectedKey(k) if k == "layers.2.weight"));
+
+        let transposed = STATE_DICT.replace("[[1.0, -1.0, 0.5]]", "[[1.0], [-1.0], [0.5]]");
+        let err = mlp.load_state_dict_json(&transposed).unwrap_err();
+        assert!(matches!(
+            &err,
+            LoadError::TensorShape { key, expected, got }
+                if key == "layers.1.weight" && *expected == [1, 3] && *got == [3, 1]
+        ));
+
+        let ragged = STATE_DICT.replace("[1.0, 0.0]", "[1.0]");
+        let err = mlp.load_state_dict_json(&ragged).unwrap_err();
+        assert!(matches!(
+            &err,
+            LoadError::TensorShape { expected, got, .. } if *expected == [3, 2] && *got == [3, 1]
+        ));
+
+        let short_bias = STATE_DICT.replace("[0.1, -0.2, 0.0]", "[0.1, -0.2]");
+        let err = mlp.load_state_dict_json(&short_bias).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"state dict entry "layers.0.bias" has shape [2], expected [3]"#
+        );
+
+        assert!(matches!(
+            mlp.load_state_dict_json("[1, 2]").unwrap_err(),
+            LoadError::Json(_)
+        ));
+
+        // nothing was written by the failed loads
+        let after = mlp
+            .get_parameters()
+            .iter()
+            .map(Value::data)
+            .collect::<Vec<_>>();
+        assert_eq!(before, after);
+    }
+
+    fn xor() -> (Vec<Vec<Scalar>>, Vec<Vec<Scalar>>) {
+        let xs = vec![
+            vec![0.0, 0.0],
+            vec![0.0, 1.0],
+            vec![1.0, 0.0],
+            vec![1.0, 1.0],
+            vec![0.5, 0.5],
+        ];
+        let ys = vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0], vec![0.5]];
+        (xs, ys)
+    }
+
+    /// Mini-batch SGD for epochs `from..to`, returning the full-batch loss after each
+    fn train(mlp: &MLP, optim: &mut Sgd, seed: u64, from: usize, to: usize) -> Vec<Scalar> {
+        let (xs, ys) = xor();
+        let mut batches = BatchIter::new(&xs, &ys, 2, seed);
+        batches.skip_epochs(from);
+        let rows = |rows: &[Vec<Scalar>]| -> Vec<Vec<Value>> {
+            rows.iter().map(|r| Value::from_slice(r)).collect()
+        };
+        let mut losses = vec![];
+        for _ in from..to {
+            for (bx, by) in batches.epoch() {
+                optim.zero_grad();
+                mse_batch(&mlp.apply_batch(&rows(&bx)), &rows(&by)).backward();
+                optim.step();
+            }
+            losses.push(mse_batch(&mlp.apply_batch(&rows(&xs)), &rows(&ys)).data());
+        }
+        losses
+    }
+
+    #[test]
+    fn checkpoint_resumes_bit_identically() {
+        let fresh = || MLP::new_with_seed(2, &[4, 1], 3);
+        let momentum = |mlp: &MLP| {
+            let mut sgd = Sgd::with_momentum(mlp.get_parameters(), 0.3, 0.9);
+            sgd.set_weight_decay(1e-3);
+            sgd
+        };
+
+        let straight = fresh();
+        let mut optim = momentum(&straight);
+        let expected = train(&straight, &mut optim, 11, 0, 10);
+
+        let first = fresh();
+        let mut optim = momentum(&first);
+        let mut losses = train(&first, &mut optim, 11, 0, 5);
+        let path = temp_path("training_checkpoint");
+        Checkpoint::new(first, optim, 5, 11).save(&path).unwrap();
+
+        let checkpoint = Checkpoint::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let seed = checkpoint.seed;
+        let (mlp, mut optim, epoch) = checkpoint.into_parts();
+        assert_eq!(epoch, 5);
+        losses.extend(train(&mlp, &mut optim, seed, epoch, 10));
+
+        let bits = |v: &[Scalar]| v.iter().map(|x| x.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(&losses), bits(&expected));
+        let params = |m: &MLP| {
+            m.get_parameters()
+                .iter()
+                .map(Value::data)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(bits(&params(&mlp)), bits(&params(&straight)));
+    }
+
+    #[test]
+    fn checkpoint_keeps_optimizer_subsets() {
+        let mlp = MLP::new_with_seed(2, &[3, 1], 1);
+        let optim = Sgd::with_momentum(mlp.biases(), 0.1, 0.5);
+        let path = temp_path("bias_checkpoint");
+        Checkpoint::new(mlp.clone(), optim, 0, 0)
+            .save(&path)
+            .unwrap();
+        let loaded = Checkpoint::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let labels = |ps: &[Value]| ps.iter().map(Value::label).collect::<Vec<_>>();
+        assert_eq!(labels(loaded.optimizer.params()), labels(&mlp.biases()));
+        assert_eq!(loaded.optimizer.momentum(), 0.5);
+
+        // a value outside the model can't be recorded by position
+        let stray = Sgd::new(vec![Value::new(1.0)], 0.1);
+        let err = Checkpoint::new(mlp, stray, 0, 0).save(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn checkpoint_rejects_other_versions_and_bad_indices() {
+        let mlp = MLP::new_with_seed(2, &[1], 1);
+        let path = temp_path("versioned_checkpoint");
+        let optim = Sgd::new(mlp.get_parameters(), 0.1);
+        Checkpoint::new(mlp, optim, 2, 0).save(&path).unwrap();
+        let json = std::fs::read_to_string(&path).unwrap();
+
+        std::fs::write(&path, json.replace(r#""version":1"#, r#""version":2"#)).unwrap();
+        assert!(matches!(
+            Checkpoint::load(&path),
+            Err(LoadError::UnsupportedVersion(2))
+        ));
+        // a future layout is rejected by its version, not as malformed
+        std::fs::write(&path, r#"{"version":3,"weights":[]}"#).unwrap();
+        assert!(matches!(
+            Checkpoint::load(&path),
+            Err(LoadError::UnsupportedVersion(3))
+        ));
+
+        std::fs::write(&path, json.replace(r#""index":2"#, r#""index":7"#)).unwrap();
+        let err = Checkpoint::load(&path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "optimizer refers to parameter 7, network has 3"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("micrograd_{}_{}.bin", name, std::process::id()))
+}
+
+fn train_briefly(mlp: &MLP) {
+    let x = [Value::new(0.5), Value::new(-1.0), Value::new(0.3)];
+    let targets = [Value::new(0.2), Value::new(-0.4)];
+    let mut optim = Sgd::new(mlp.get_parameters(), 0.1);
+    for _ in 0..5 {
+        let loss = mse(&mlp.apply(&x), &targets);
+        optim.zero_grad();
+        loss.backward();
+        optim.step();
+    }
+}
+
+#[test]
+fn load_weights_restores_saved_outputs() {
+    let path = temp_path("restore");
+    let mut mlp = MLP::new_with_seed(3, &[4, 2], 5);
+    train_briefly(&mlp);
+    let saved = outputs(&mlp);
+    mlp.save_weights(&path).unwrap();
+
+    let ids: Vec<usize> = mlp.get_parameters().iter().map(Value::id).collect();
+    train_briefly(&mlp);
+    assert_ne!(outputs(&mlp), saved);
+
+    mlp.load_weights(&path).unwrap();
+    assert_eq!(outputs(&mlp), saved);
+    let reloaded_ids: Vec<usize> = mlp.get_parameters().iter().map(Value::id).collect();
+    assert_eq!(ids, reloaded_ids);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn load_weights_reports_bad_files() {
+    let path = temp_path("bad");
+    let mut mlp = MLP::new_with_seed(3, &[4, 2], 5);
+    mlp.save_weights(&path).unwrap();
+    let good = std::fs::read(&path).unwrap();
+    let before = outputs(&mlp);
+
+    let mut load = |bytes: &[u8]| {
+        std::fs::write(&path, bytes).unwrap();
+        mlp.load_weights(&path).unwrap_err()
+    };
+    assert!(matches!(load(b"nope"), LoadError::BadMagic));
+    assert!(matches!(load(&good[..2]), LoadError::BadMagic));
+
+    let mut bumped = good.clone();
+    bumped[4] = 9;
+    assert!(matches!(load(&bumped), LoadError::UnsupportedVersion(9)));
+
+    let mut width = good.clone();
+    width[8] = 2;
+    assert!(matches!(
+        load(&width),
+        LoadError::ScalarWidth { got: 2, .. }
+    ));
+
+    assert!(matches!(
+        load(&good[..good.len() - 1]),
+        LoadError::Truncated
+    ));
+    let mut corrupt_count = good.clone();
+    corrupt_count[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(matches!(load(&corrupt_count), LoadError::Truncated));
+
+    let mut longer = good.clone();
+    longer.push(0);
+    assert!(matches!(load(&longer), LoadError::TrailingBytes(1)));
+
+    assert!(matches!(load(&[]), LoadError::BadMagic));
+
+    // nothing was applied by the failed loads
+    assert_eq!(outputs(&mlp), before);
+
+    MLP::new_with_seed(3, &[5, 2], 5)
+        .save_weights(&path)
+        .unwrap();
+    match mlp.load_weights(&path).unwrap_err() {
+        LoadError::Architecture { expected, got } => {
+            assert_eq!(expected, vec![3, 4, 2]);
+            assert_eq!(got, vec![3, 5, 2]);
+        }
+        e => panic!("unexpected error {}", e),
+    }
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(mlp.load_weights(&path), Err(LoadError::Io(_))));
+}
+
+/// 3 -> 2 -> 2 tanh network with the bias of one layer dropped; either choice gives 14
+/// parameters
+fn without_bias_in(layer: usize) -> MLP {
+    let config = |i: usize| LayerConfig {
+        activation: Activation::Tanh,
+        bias: i != layer,
+        ..Default::default()
+    };
+    MlpBuilder::new(3)
+        .layer_with(2, config(0))
+        .layer_with(2, config(1))
+        .seed(4)
+        .build()
+        .unwrap()
+}
+
+/// Shift every parameter, so a load that succeeds is visible in the outputs
+fn perturb(mlp: &MLP) {
+    for p in mlp.get_parameters() {
+        p.add_data(0.5);
+    }
+}
+
+#[test]
+fn load_weights_checks_the_bias_layout() {
+    let path = temp_path("bias_layout");
+    let saved = without_bias_in(0);
+    saved.save_weights(&path).unwrap();
+
+    let mut other = without_bias_in(1);
+    assert_eq!(other.num_parameters(), saved.num_parameters());
+    let before = outputs(&other);
+    let err = other.load_weights(&path).unwrap_err();
+    assert!(matches!(
+        err,
+        LoadError::Bias {
+            layer: 0,
+            neuron: 0,
+            expected: true
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "neuron 0 of layer 0 has a bias, the weights have none"
+    );
+    assert_eq!(outputs(&other), before);
+
+    let mut same = without_bias_in(0);
+    perturb(&same);
+    same.load_weights(&path).unwrap();
+    assert_eq!(outputs(&same), outputs(&saved));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn version_1_weights_load_as_all_biased() {
+    let path = temp_path("version_1");
+    let mlp = MLP::new_with_seed(3, &[2, 2], 5);
+    mlp.save_weights(&path).unwrap();
+    let v3 = std::fs::read(&path).unwrap();
+
+    // version 1 is version 3 without what follows the sizes: a bias flag per neuron and
+    // the architecture hash
+    let header = 16 + 4 * 3;
+    let mut v1 = v3[..header].to_vec();
+    v1[4..8].copy_from_slice(&1u32.to_le_bytes());
+    v1.extend_from_slice(&v3[header + 4 + 8..]);
+    std::fs::write(&path, &v1).unwrap();
+
+    let mut loaded = MLP::new_with_seed(3, &[2, 2], 6);
+    loaded.load_weights(&path).unwrap();
+    assert_eq!(outputs(&loaded), outputs(&mlp));
+
+    let err = without_bias_in(1).load_weights(&path).unwrap_err();
+    assert!(matches!(
+        err,
+        LoadError::Bias {
+            layer: 1,
+            neuron: 0,
+            expected: false
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "neuron 0 of layer 1 has no bias, the weights have one"
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn version_2_weights_load_without_a_hash() {
+    let path = temp_path("version_2");
+    let saved = without_bias_in(0);
+    saved.save_weights(&path).unwrap();
+    let v3 = std::fs::read(&path).unwrap();
+
+    // version 2 is version 3 without the architecture hash after the bias flags
+    let flags_end = 16 + 4 * 3 + 4;
+    let mut v2 = v3[..flags_end].to_vec();
+    v2[4..8].copy_from_slice(&2u32.to_le_bytes());
+    v2.extend_from_slice(&v3[flags_end + 8..]);
+    std::fs::write(&path, &v2).unwrap();
+
+    let mut loaded = without_bias_in(0);
+    perturb(&loaded);
+    loaded.load_weights(&path).unwrap();
+    assert_eq!(outputs(&loaded), outputs(&saved));
+
+    // the bias flags are still checked
+    let err = without_bias_in(1).load_weights(&path).unwrap_err();
+    assert!(matches!(err, LoadError::Bias { layer: 0, .. }));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn architecture_hash_ignores_weights_and_weights_hash_ignores_nothing() {
+    let a = MLP::new_with_seed(3, &[4, 2], 1);
+    let b = MLP::new_with_seed(3, &[4, 2], 2);
+    assert_eq!(a.architecture_hash(), b.architecture_hash());
+    assert_ne!(a.weights_hash(), b.weights_hash());
+    assert_eq!(
+        a.weights_hash(),
+        MLP::new_with_seed(3, &[4, 2], 1).weights_hash()
+    );
+
+    let others = [
+        MLP::new_with_seed(3, &[4, 3], 1),
+        MLP::new_with_seed(2, &[4, 2], 1),
+        MLP::new_with_seed(3, &[4, 2], 1).into_regressor(),
+        MLP::new_with_seed(3, &[4, 2], 1)
+            .with_activations(&[Activation::LeakyReLU(0.1), Activation::Tanh]),
+        MLP::new_with_seed(3, &[4, 2], 1)
+            .with_activations(&[Activation::LeakyReLU(0.2), Activation::Tanh]),
+    ];
+    for (i, other) in others.iter().enumerate() {
+        assert_ne!(other.architecture_hash(), a.architecture_hash(), "{}", i);
+    }
+    assert_ne!(
+        without_bias_in(0).architecture_hash(),
+        without_bias_in(1).architecture_hash()
+    );
+    let with_init = |init| MLP::new_with_init(3, &[4, 2], init, &mut StdRng::seed_from_u64(1));
+    let inits = [
+        Init::default(),
+        Init::Uniform { lo: -0.5, hi: 0.5 },
+        Init::Normal {
+            mean: 0.0,
+            std: 1.0,
+        },
+        Init::XavierUniform,
+        Init::HeNormal,
+    ];
+    assert_eq!(
+        with_init(inits[0]).architecture_hash(),
+        a.architecture_hash()
+    );
+    for (i, &x) in inits.iter().enumerate() {
+        for &y in &inits[i + 1..] {
+            assert_ne!(
+                with_init(x).architecture_hash(),
+                with_init(y).architecture_hash()
+            );
+        }
+    }
+
+    a.get_parameters()[3].add_data(1e-3);
+    assert_ne!(
+        a.weights_hash(),
+        MLP::new_with_seed(3, &[4, 2], 1).weights_hash()
+    );
+}
+
+#[test]
+fn load_weights_rejects_another_architecture_hash() {
+    let path = temp_path("architecture_hash");
+    let saved = MLP::new_with_seed(3, &[4, 2], 5);
+    saved.save_weights(&path).unwrap();
+
+    // same sizes and biases, so only the hash tells the activations apart
+    let mut relu =
+        MLP::new_with_seed(3, &[4, 2], 6).with_activations(&[Activation::ReLU, Activation::Tanh]);
+    let before = relu.weights_hash();
+    match relu.load_weights(&path).unwrap_err() {
+        LoadError::ArchitectureHash { expected, got } => {
+            assert_eq!(expected, relu.architecture_hash());
+            assert_eq!(got, saved.architecture_hash());
+        }
+        e => panic!("unexpected error {}", e),
+    }
+    assert_eq!(relu.weights_hash(), before);
+
+    let mut same = MLP::new_with_seed(3, &[4, 2], 6);
+    same.load_weights(&path).unwrap();
+    assert_eq!(same.weights_hash(), saved.weights_hash());
+    std::fs::remove_file(&path).unwrap();
+}
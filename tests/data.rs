@@ -0,0 +1,278 @@
+use micrograd::data::{
+    argmax, argmax_scalars, labels_to_targets, one_hot, one_hot_scalars, sliding_windows,
+    stratified_split, BatchIter, LastBatch, SequenceBatch, SequenceLoader, SplitError,
+};
+use micrograd::losses::mse_batch;
+use micrograd::metrics::accuracy;
+use micrograd::optim::Sgd;
+use micrograd::{Scalar, Value, MLP};
+
+fn count(targets: &[usize], class: usize) -> usize {
+    targets.iter().filter(|&&t| t == class).count()
+}
+
+#[test]
+fn preserves_class_proportions() {
+    // 18 samples of class 0, 2 of class 1
+    let targets: Vec<usize> = (0..20).map(|i| if i % 10 == 0 { 1 } else { 0 }).collect();
+    let inputs: Vec<Vec<Scalar>> = (0..20).map(|i| vec![i as Scalar]).collect();
+    let split = stratified_split(&inputs, &targets, 0.25, 3).unwrap();
+
+    assert_eq!(split.train_inputs.len() + split.test_inputs.len(), 20);
+    for class in [0, 1] {
+        let total = count(&targets, class) as Scalar;
+        let expected_test = total * 0.25;
+        let got_test = count(&split.test_targets, class) as Scalar;
+        let got_train = count(&split.train_targets, class) as Scalar;
+        assert!((got_test - expected_test).abs() <= 1.0, "class {}", class);
+        assert!(
+            (got_train - (total - expected_test)).abs() <= 1.0,
+            "class {}",
+            class
+        );
+    }
+    // the minority class shows up on both sides
+    assert_eq!(count(&split.test_targets, 1), 1);
+    assert_eq!(count(&split.train_targets, 1), 1);
+
+    // inputs travel with their targets
+    for (x, &t) in split.test_inputs.iter().zip(&split.test_targets) {
+        assert_eq!(targets[x[0] as usize], t);
+    }
+}
+
+#[test]
+fn is_reproducible_under_a_seed() {
+    let targets: Vec<usize> = (0..30).map(|i| i % 3).collect();
+    let inputs: Vec<Vec<Scalar>> = (0..30).map(|i| vec![i as Scalar]).collect();
+    let a = stratified_split(&inputs, &targets, 0.3, 11).unwrap();
+    let b = stratified_split(&inputs, &targets, 0.3, 11).unwrap();
+    assert_eq!(a.test_inputs, b.test_inputs);
+    assert_eq!(a.train_targets, b.train_targets);
+}
+
+#[test]
+fn rejects_singleton_class() {
+    let targets = vec![0, 0, 0, 1];
+    let inputs = vec![vec![0.0]; 4];
+    assert_eq!(
+        stratified_split(&inputs, &targets, 0.5, 0).unwrap_err(),
+        SplitError::TooFewSamples { class: 1, count: 1 }
+    );
+    assert!(matches!(
+        stratified_split(&inputs, &targets[..3], 0.5, 0),
+        Err(SplitError::LengthMismatch { .. })
+    ));
+    assert!(matches!(
+        stratified_split(&inputs, &targets, 1.0, 0),
+        Err(SplitError::InvalidFraction(_))
+    ));
+}
+
+#[test]
+fn sliding_windows_boundaries() {
+    let series: Vec<Scalar> = (0..10).map(|i| i as Scalar).collect();
+    let (inputs, targets) = sliding_windows(&series, 3, 3, 2);
+    assert_eq!(
+        inputs,
+        vec![
+            vec![0.0, 1.0, 2.0],
+            vec![2.0, 3.0, 4.0],
+            vec![4.0, 5.0, 6.0]
+        ]
+    );
+    assert_eq!(
+        targets,
+        vec![
+            vec![3.0, 4.0, 5.0],
+            vec![5.0, 6.0, 7.0],
+            vec![7.0, 8.0, 9.0]
+        ]
+    );
+
+    // the window starting at 6 would need values up to index 11, so it is dropped
+    let (inputs, _) = sliding_windows(&series, 4, 3, 2);
+    assert_eq!(inputs.len(), 2);
+    assert_eq!(inputs.last().unwrap(), &vec![2.0, 3.0, 4.0, 5.0]);
+
+    // a window that exactly reaches the end is kept
+    let (inputs, targets) = sliding_windows(&series, 7, 3, 5);
+    assert_eq!(inputs.len(), 1);
+    assert_eq!(targets[0], vec![7.0, 8.0, 9.0]);
+
+    assert!(sliding_windows(&series, 8, 3, 1).0.is_empty());
+}
+
+#[test]
+fn sequence_loader_pads_and_masks() {
+    let loader = SequenceLoader::new(vec![vec![1.0, 2.0, 3.0], vec![4.0], vec![5.0, 6.0]], 2);
+    let batches: Vec<SequenceBatch> = loader.collect();
+    assert_eq!(batches.len(), 2);
+    assert_eq!(
+        batches[0],
+        SequenceBatch {
+            data: vec![vec![1.0, 2.0, 3.0], vec![4.0, 0.0, 0.0]],
+            mask: vec![vec![1.0, 1.0, 1.0], vec![1.0, 0.0, 0.0]],
+            lengths: vec![3, 1],
+        }
+    );
+    assert_eq!(
+        batches[1],
+        SequenceBatch {
+            data: vec![vec![5.0, 6.0]],
+            mask: vec![vec![1.0, 1.0]],
+            lengths: vec![2],
+        }
+    );
+}
+
+/// Ten samples whose input and target both encode their index
+fn indexed() -> (Vec<Vec<Scalar>>, Vec<Vec<Scalar>>) {
+    let xs = (0..10).map(|i| vec![i as Scalar, 0.0]).collect();
+    let ys = (0..10).map(|i| vec![-(i as Scalar)]).collect();
+    (xs, ys)
+}
+
+#[test]
+fn batches_cover_every_sample_once_per_epoch() {
+    let (xs, ys) = indexed();
+    let mut batches = BatchIter::new(&xs, &ys, 3, 5);
+    assert_eq!(batches.num_batches(), 4);
+    for _ in 0..3 {
+        let epoch = batches.epoch();
+        let sizes: Vec<usize> = epoch.iter().map(|(bx, _)| bx.len()).collect();
+        assert_eq!(sizes, vec![3, 3, 3, 1]);
+        let mut seen: Vec<Scalar> = vec![];
+        for (bx, by) in &epoch {
+            // rows stay paired
+            for (x, y) in bx.iter().zip(by) {
+                assert_eq!(x[0], -y[0]);
+                seen.push(x[0]);
+            }
+        }
+        seen.sort_by(Scalar::total_cmp);
+        assert_eq!(seen, (0..10).map(|i| i as Scalar).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn batches_reshuffle_reproducibly() {
+    let (xs, ys) = indexed();
+    let mut a = BatchIter::new(&xs, &ys, 4, 9);
+    let mut b = BatchIter::new(&xs, &ys, 4, 9);
+    let first = a.epoch_indices();
+    let second = a.epoch_indices();
+    assert_ne!(first, second);
+    assert_eq!(b.epoch_indices(), first);
+    assert_eq!(b.epoch_indices(), second);
+    assert_ne!(BatchIter::new(&xs, &ys, 4, 10).epoch_indices(), first);
+}
+
+#[test]
+fn skipped_epochs_continue_the_sequence() {
+    let (xs, ys) = indexed();
+    let mut a = BatchIter::new(&xs, &ys, 4, 9);
+    a.skip_epochs(3);
+    let mut b = BatchIter::new(&xs, &ys, 4, 9);
+    for _ in 0..3 {
+        b.epoch_indices();
+    }
+    assert_eq!(a.epoch_indices(), b.epoch_indices());
+}
+
+#[test]
+fn last_batch_can_be_dropped() {
+    let (xs, ys) = indexed();
+    let mut batches = BatchIter::new(&xs, &ys, 4, 1).with_last_batch(LastBatch::Drop);
+    assert_eq!(batches.num_batches(), 2);
+    let epoch = batches.epoch_indices();
+    assert_eq!(epoch.len(), 2);
+    assert!(epoch.iter().all(|b| b.len() == 4));
+
+    let mut whole = BatchIter::new(&xs, &ys, 5, 1).with_last_batch(LastBatch::Drop);
+    assert_eq!(whole.epoch_indices().len(), 2);
+    let mut too_big = BatchIter::new(&xs, &ys, 11, 1).with_last_batch(LastBatch::Drop);
+    assert!(too_big.epoch().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "got 10 inputs but 9 targets")]
+fn batches_reject_mismatched_lengths() {
+    let (xs, mut ys) = indexed();
+    ys.pop();
+    BatchIter::new(&xs, &ys, 2, 0);
+}
+
+#[test]
+#[should_panic(expected = "input row 4 has 1 values, expected 2")]
+fn batches_reject_ragged_rows() {
+    let (mut xs, ys) = indexed();
+    xs[4].pop();
+    BatchIter::new(&xs, &ys, 2, 0);
+}
+
+#[test]
+fn one_hot_marks_the_label() {
+    assert_eq!(one_hot_scalars(2, 4), vec![0.0, 0.0, 1.0, 0.0]);
+    let encoded: Vec<Scalar> = one_hot(0, 3).iter().map(Value::data).collect();
+    assert_eq!(encoded, vec![1.0, 0.0, 0.0]);
+    let targets = labels_to_targets(&[1, 0], 2);
+    assert_eq!(targets.len(), 2);
+    assert_eq!(targets[0][1].data(), 1.0);
+    assert_eq!(targets[1][0].data(), 1.0);
+}
+
+#[test]
+#[should_panic(expected = "label 3 out of range for 3 classes")]
+fn one_hot_rejects_a_label_past_the_classes() {
+    one_hot(3, 3);
+}
+
+#[test]
+fn argmax_breaks_ties_towards_the_lowest_index() {
+    assert_eq!(argmax_scalars(&[0.1, 0.7, 0.7, 0.2]), 1);
+    assert_eq!(argmax_scalars(&[0.5, 0.5]), 0);
+    assert_eq!(argmax_scalars(&[Scalar::NAN, -1.0]), 1);
+    let values: Vec<Value> = [-2.0, 3.0, 3.0].iter().map(|&x| Value::new(x)).collect();
+    assert_eq!(argmax(&values), 1);
+    for label in 0..4 {
+        assert_eq!(argmax(&one_hot(label, 4)), label);
+    }
+}
+
+#[test]
+#[should_panic(expected = "argmax of no values")]
+fn argmax_of_nothing_panics() {
+    argmax(&[]);
+}
+
+#[test]
+fn three_class_training_with_one_hot_targets() {
+    let centers = [[1.0, 0.0], [-0.5, 0.9], [-0.5, -0.9]];
+    let mut xs = vec![];
+    let mut labels = vec![];
+    for (label, c) in centers.iter().enumerate() {
+        for d in [[0.1, 0.0], [-0.1, 0.1], [0.0, -0.1]] {
+            xs.push(vec![c[0] + d[0], c[1] + d[1]]);
+            labels.push(label);
+        }
+    }
+    let targets = labels_to_targets(&labels, 3);
+
+    let mlp = MLP::new_with_seed(2, &[8, 3], 5).into_regressor();
+    let mut sgd = Sgd::new(mlp.get_parameters(), 0.1);
+    for _ in 0..200 {
+        let inputs: Vec<Vec<Value>> = xs
+            .iter()
+            .map(|x| x.iter().map(|&xi| Value::new(xi)).collect())
+            .collect();
+        sgd.zero_grad();
+        mse_batch(&mlp.apply_batch(&inputs), &targets).backward();
+        sgd.step();
+    }
+
+    for (x, &label) in xs.iter().zip(&labels) {
+        assert_eq!(argmax_scalars(&mlp.predict(x)), label);
+    }
+    assert_eq!(accuracy(&mlp, &xs, &labels), 1.0);
+}
@@ -0,0 +1,68 @@
+use micrograd::functional::{dot, mean, sum};
+use micrograd::{Op, Scalar, Value};
+
+#[test]
+fn dot_grad_is_the_other_operand() {
+    let xs: [Scalar; 4] = [0.5, -1.0, 2.0, 0.25];
+    let x: Vec<Value> = xs.iter().map(|&v| Value::new(v)).collect();
+    let w: Vec<Value> = [1.5, 0.5, -0.5, 4.0]
+        .iter()
+        .map(|&v| Value::new(v))
+        .collect();
+    let d = dot(&x, &w);
+    assert_eq!(d.data(), 0.75 - 0.5 - 1.0 + 1.0);
+    assert!(matches!(*d.op(), Op::Sum(..)));
+
+    d.backward();
+    for ((wi, xi), &expected) in w.iter().zip(&x).zip(&xs) {
+        assert_eq!(wi.grad(), expected);
+        assert_eq!(xi.grad(), wi.data());
+    }
+}
+
+#[test]
+fn dot_of_empty_slices_is_a_zero_leaf() {
+    let d = dot(&[], &[]);
+    assert!(d.is_leaf());
+    assert_eq!(d.data(), 0.0);
+}
+
+#[test]
+#[should_panic(expected = "dot product of slices of length 2 and 1")]
+fn dot_rejects_mismatched_lengths() {
+    dot(&[Value::new(1.0), Value::new(2.0)], &[Value::new(1.0)]);
+}
+
+#[test]
+fn mean_gives_each_element_an_equal_share() {
+    let xs = Value::from_slice(&[1.0, -2.0, 3.5, 0.0, 7.5]);
+    let m = mean(&xs);
+    assert_eq!(m.data(), 2.0);
+    assert!(matches!(*m.op(), Op::Mean(..)));
+
+    m.backward();
+    for x in &xs {
+        assert_eq!(x.grad(), 0.2);
+    }
+}
+
+#[test]
+#[should_panic(expected = "cannot take the mean of no values")]
+fn mean_of_nothing_panics() {
+    mean(&[]);
+}
+
+#[test]
+#[should_panic(expected = "cannot sum no values")]
+fn sum_of_nothing_panics() {
+    sum(&[]);
+}
+
+#[test]
+fn sum_matches_value_sum() {
+    let xs = Value::from_slice(&[1.0, 2.0, 3.0]);
+    let s = sum(&xs);
+    assert_eq!(s.data(), 6.0);
+    s.backward();
+    assert!(xs.iter().all(|x| x.grad() == 1.0));
+}
@@ -0,0 +1,58 @@
+use micrograd::linear::fit_linear_least_squares;
+use micrograd::Scalar;
+
+#[test]
+fn recovers_known_coefficients() {
+    // y = 2 x0 - 3 x1 + 0.5
+    let inputs = vec![
+        vec![0.0, 0.0],
+        vec![1.0, 0.0],
+        vec![0.0, 1.0],
+        vec![1.0, 1.0],
+        vec![2.0, -1.0],
+    ];
+    let targets: Vec<Scalar> = inputs
+        .iter()
+        .map(|x| 2.0 * x[0] - 3.0 * x[1] + 0.5)
+        .collect();
+    let model = fit_linear_least_squares(&inputs, &targets);
+    assert!(model.ridge_lambda.is_none());
+    assert!((model.weights[0] - 2.0).abs() < 1e-4);
+    assert!((model.weights[1] + 3.0).abs() < 1e-4);
+    assert!((model.bias - 0.5).abs() < 1e-4);
+    for (x, t) in inputs.iter().zip(&targets) {
+        assert!((model.predict(x) - t).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn converted_mlp_predicts_the_same() {
+    let inputs = vec![
+        vec![0.3, -1.2, 2.0],
+        vec![1.5, 0.7, -0.4],
+        vec![-0.8, 0.1, 0.9],
+        vec![2.2, -0.3, 0.0],
+        vec![0.0, 1.1, -1.7],
+    ];
+    let targets: Vec<Scalar> = vec![0.4, -1.3, 2.1, 0.05, -0.6];
+    let model = fit_linear_least_squares(&inputs, &targets);
+    let mlp = model.to_mlp();
+    assert_eq!(mlp.sizes(), vec![3, 1]);
+    assert_eq!(mlp.num_parameters(), 4);
+    for x in &inputs {
+        assert_eq!(mlp.predict(x), vec![model.predict(x)]);
+        assert_eq!(mlp.apply_scalars(x)[0].data(), model.predict(x));
+    }
+}
+
+#[test]
+fn singular_system_falls_back_to_ridge() {
+    // the second feature is a copy of the first, so AᵀA is singular
+    let inputs = vec![vec![1.0, 1.0], vec![2.0, 2.0], vec![3.0, 3.0]];
+    let targets = vec![2.0, 4.0, 6.0];
+    let model = fit_linear_least_squares(&inputs, &targets);
+    assert!(model.ridge_lambda.is_some());
+    for (x, t) in inputs.iter().zip(&targets) {
+        assert!((model.predict(x) - t).abs() < 1e-3);
+    }
+}
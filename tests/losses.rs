@@ -0,0 +1,505 @@
+use micrograd::losses::{
+    bce_with_logits, binary_cross_entropy, cross_entropy, hinge, huber, l2_penalty, mae, mse,
+    mse_batch, softmax, sum_squared_error, CompositeLoss,
+};
+use micrograd::optim::Sgd;
+use micrograd::testing::check_gradients;
+use micrograd::{Scalar, Value, MLP};
+
+#[test]
+fn total_is_weighted_sum_of_terms() {
+    let w = Value::new(3.0);
+    let task = w.clone() * Value::new(2.0);
+    let l2 = w.clone() * w;
+
+    let mut loss = CompositeLoss::new();
+    loss.add_term("task", task, 1.0);
+    loss.add_term("l2", l2, 0.5);
+
+    assert_eq!(
+        loss.terms(),
+        vec![("task".to_string(), 6.0), ("l2".to_string(), 9.0)]
+    );
+    assert_eq!(loss.total().data(), 6.0 + 0.5 * 9.0);
+}
+
+#[test]
+fn total_grads_are_the_weighted_sum_of_term_grads() {
+    let params = [Value::new(0.7), Value::new(-0.4)];
+    let terms = |p: &[Value]| {
+        let task = (&p[0] * 2.0 + &p[1] - 1.0).pow(2.0);
+        let l2 = l2_penalty(p, 1.0);
+        let saturation = (&p[0] * &p[1]).tanh().pow(2.0);
+        [
+            ("task", task, 1.0),
+            ("l2", l2, 0.1),
+            ("saturation", saturation, 0.5),
+        ]
+    };
+
+    let mut expected = [0.0; 2];
+    for (_, term, weight) in terms(&params) {
+        params.iter().for_each(Value::zero_grad);
+        term.backward();
+        for (e, p) in expected.iter_mut().zip(&params) {
+            *e += weight * p.grad();
+        }
+    }
+
+    let mut loss = CompositeLoss::new();
+    for (name, term, weight) in terms(&params) {
+        loss.add_term(name, term, weight);
+    }
+    params.iter().for_each(Value::zero_grad);
+    loss.total().backward();
+    for (e, p) in expected.iter().zip(&params) {
+        assert!((p.grad() - e).abs() < 1e-5, "{} vs {}", p.grad(), e);
+    }
+}
+
+#[test]
+#[should_panic(expected = "no terms")]
+fn empty_total_panics() {
+    CompositeLoss::new().total();
+}
+
+#[test]
+fn mse_grad_per_prediction() {
+    let predictions: Vec<Value> = [1.0, -2.0, 0.5].iter().map(|&y| Value::new(y)).collect();
+    let targets: Vec<Value> = [0.0, 1.0, 0.5].iter().map(|&t| Value::new(t)).collect();
+    let loss = mse(&predictions, &targets);
+    assert!((loss.data() - 10.0 / 3.0).abs() < 1e-6);
+    loss.backward();
+    for (y, t) in predictions.iter().zip(&targets) {
+        let expected = 2.0 * (y.data() - t.data()) / 3.0;
+        assert!((y.grad() - expected).abs() < 1e-6);
+    }
+
+    let sum = sum_squared_error(&predictions, &targets);
+    assert_eq!(sum.data(), 10.0);
+}
+
+#[test]
+fn mse_single_prediction() {
+    let y = Value::new(0.3);
+    let loss = mse(std::slice::from_ref(&y), &[Value::new(0.8)]);
+    loss.backward();
+    assert!((loss.data() - 0.25).abs() < 1e-6);
+    assert!((y.grad() + 1.0).abs() < 1e-6);
+}
+
+#[test]
+#[should_panic(expected = "got 2 predictions but 1 targets")]
+fn mse_length_mismatch() {
+    mse(&[Value::new(0.0), Value::new(1.0)], &[Value::new(0.0)]);
+}
+
+#[test]
+fn abs_grad_is_the_sign() {
+    check_gradients(|x| x[0].abs() * &x[1], &[1.5, -0.5], 1e-3, 1e-2).unwrap();
+    check_gradients(|x| x[0].abs() * &x[1], &[-2.0, 0.7], 1e-3, 1e-2).unwrap();
+
+    // exactly zero: the subgradient 0 is used, never NaN
+    let x = Value::new(0.0);
+    let y = x.abs();
+    y.backward();
+    assert_eq!((y.data(), x.grad()), (0.0, 0.0));
+}
+
+#[test]
+fn mae_grad_per_prediction() {
+    let preds = [Value::new(1.0), Value::new(-1.0), Value::new(0.5)];
+    let targets = [Value::new(0.0), Value::new(0.0), Value::new(0.5)];
+    let loss = mae(&preds, &targets);
+    assert!((loss.data() - 2.0 / 3.0).abs() < 1e-6);
+    loss.backward();
+    let grads: Vec<Scalar> = preds.iter().map(Value::grad).collect();
+    assert!((grads[0] - 1.0 / 3.0).abs() < 1e-6);
+    assert!((grads[1] + 1.0 / 3.0).abs() < 1e-6);
+    assert_eq!(grads[2], 0.0);
+}
+
+/// Slope learned by a single linear neuron on points of `y = 2x` with one outlier
+fn slope_with_outlier(loss: fn(&[Value], &[Value]) -> Value) -> Scalar {
+    let mlp = MLP::new_with_seed(1, &[1], 4).into_regressor();
+    let xs: [Scalar; 6] = [-1.0, -0.6, -0.2, 0.2, 0.6, 1.0];
+    let mut ys = xs.map(|x| 2.0 * x);
+    ys[4] = 10.0;
+    let inputs: Vec<Vec<Value>> = xs.iter().map(|&x| vec![Value::new(x)]).collect();
+    let targets: Vec<Value> = ys.iter().map(|&y| Value::new(y)).collect();
+    let mut optim = Sgd::new(mlp.get_parameters(), 0.05);
+    for _ in 0..1000 {
+        let preds: Vec<Value> = inputs.iter().map(|x| mlp.apply(x).pop().unwrap()).collect();
+        loss(&preds, &targets).backward();
+        optim.step();
+    }
+    mlp.layers()[0].neurons()[0].weights()[0].data()
+}
+
+#[test]
+fn mae_is_less_perturbed_by_an_outlier() {
+    let with_mae = slope_with_outlier(mae);
+    let with_mse = slope_with_outlier(mse);
+    assert!(
+        (with_mae - 2.0).abs() < (with_mse - 2.0).abs() / 2.0,
+        "mae slope {} mse slope {}",
+        with_mae,
+        with_mse
+    );
+}
+
+#[test]
+fn mse_batch_averages_every_element() {
+    let mlp = MLP::new_with_seed(2, &[3, 2], 4);
+    let xor = [[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]];
+    let xs: Vec<Vec<Value>> = xor
+        .iter()
+        .map(|x| x.iter().map(|&xi| Value::new(xi)).collect())
+        .collect();
+    let ys: Vec<Vec<Value>> = [0.0, 1.0, 1.0, 0.0]
+        .iter()
+        .map(|&y| vec![Value::new(y), Value::new(1.0 - y)])
+        .collect();
+    let loss = mse_batch(&mlp.apply_batch(&xs), &ys);
+
+    let per_row: Scalar = xs
+        .iter()
+        .zip(&ys)
+        .map(|(x, y)| mse(&mlp.apply(x), y).data())
+        .sum::<Scalar>()
+        / 4.0;
+    assert!((loss.data() - per_row).abs() < 1e-6);
+
+    // one backward call reaches every parameter
+    loss.backward();
+    assert!(mlp.get_parameters().iter().all(|p| p.grad() != 0.0));
+}
+
+#[test]
+fn mse_batch_grads_match_a_sum_over_the_count() {
+    let mlp = MLP::new_with_seed(2, &[3, 2], 4);
+    let xs: Vec<Vec<Value>> = [[0.0, 1.0], [1.0, 0.5], [-1.0, 2.0]]
+        .iter()
+        .map(|x| Value::from_slice(x))
+        .collect();
+    let ys: Vec<Vec<Value>> = [[1.0, 0.0], [0.0, 1.0], [0.5, 0.5]]
+        .iter()
+        .map(|y| Value::from_slice(y))
+        .collect();
+    let grads = |loss: Value| {
+        mlp.zero_grad();
+        loss.backward();
+        mlp.get_parameters()
+            .iter()
+            .map(Value::grad)
+            .collect::<Vec<_>>()
+    };
+
+    let predictions = mlp.apply_batch(&xs);
+    let batched = grads(mse_batch(&predictions, &ys));
+    let mut total = sum_squared_error(&predictions[0], &ys[0]);
+    for (y, t) in predictions.iter().zip(&ys).skip(1) {
+        total += sum_squared_error(y, t);
+    }
+    let manual = grads(total / 6.0);
+    for (b, m) in batched.iter().zip(&manual) {
+        assert!((b - m).abs() < 1e-6, "{} vs {}", b, m);
+    }
+}
+
+#[test]
+fn softmax_sums_to_one() {
+    let logits: Vec<Value> = [1.0, 2.0, 3.0].iter().map(|&x| Value::new(x)).collect();
+    let probs = softmax(&logits);
+    let total: Scalar = probs.iter().map(Value::data).sum();
+    assert!((total - 1.0).abs() < 1e-6);
+    assert!(probs[2].data() > probs[1].data() && probs[1].data() > probs[0].data());
+}
+
+/// Backward from every output in turn and compare against `s_i (δ_ij - s_j)`
+fn assert_softmax_jacobian(x: &[Scalar]) {
+    let logits: Vec<Value> = x.iter().map(|&x| Value::new(x)).collect();
+    let probs = softmax(&logits);
+    let s: Vec<Scalar> = probs.iter().map(Value::data).collect();
+    for (i, p) in probs.iter().enumerate() {
+        p.backward();
+        for (j, logit) in logits.iter().enumerate() {
+            let delta = if i == j { 1.0 } else { 0.0 };
+            let expected = s[i] * (delta - s[j]);
+            assert!(
+                (logit.grad() - expected).abs() < 1e-6,
+                "d s{} / d x{}: {} vs {}",
+                i,
+                j,
+                logit.grad(),
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn softmax_jacobian() {
+    assert_softmax_jacobian(&[0.2, -1.0, 0.7]);
+    assert_softmax_jacobian(&[1.5, 0.0, -0.3, 2.2, -2.0]);
+    // a dominating logit: probabilities saturate but nothing overflows
+    assert_softmax_jacobian(&[500.0, 1.0, -2.0, 0.5, 3.0]);
+}
+
+#[test]
+fn softmax_grad_matches_finite_differences() {
+    // a weighted sum of the outputs exercises every row of the Jacobian at once
+    let weights: [Scalar; 5] = [0.3, -1.2, 0.8, 2.0, -0.5];
+    let build = |x: &[Value]| {
+        let probs = softmax(x);
+        let terms: Vec<Value> = probs.iter().zip(weights).map(|(p, w)| p * w).collect();
+        Value::sum(&terms)
+    };
+    check_gradients(build, &[0.2, -1.0, 0.7], 1e-2, 1e-2).unwrap();
+    check_gradients(build, &[1.5, 0.0, -0.3, 2.2, -2.0], 1e-2, 1e-2).unwrap();
+}
+
+#[test]
+fn cross_entropy_grad_matches_finite_differences() {
+    let x0: [Scalar; 3] = [0.2, -1.0, 0.7];
+    let logits: Vec<Value> = x0.iter().map(|&x| Value::new(x)).collect();
+    cross_entropy(&logits, 1).backward();
+
+    let eval = |x: [Scalar; 3]| {
+        let logits: Vec<Value> = x.iter().map(|&x| Value::new(x)).collect();
+        cross_entropy(&logits, 1).data()
+    };
+    let h = 1e-3;
+    for i in 0..3 {
+        let (mut plus, mut minus) = (x0, x0);
+        plus[i] += h;
+        minus[i] -= h;
+        let numeric = (eval(plus) - eval(minus)) / (2.0 * h);
+        assert!(
+            (logits[i].grad() - numeric).abs() < 1e-2,
+            "logit {}: {} vs {}",
+            i,
+            logits[i].grad(),
+            numeric
+        );
+    }
+}
+
+#[test]
+fn cross_entropy_is_stable_for_large_logits() {
+    let logits: Vec<Value> = [1000.0, 0.0, -1000.0]
+        .iter()
+        .map(|&x| Value::new(x))
+        .collect();
+    for probs in softmax(&logits) {
+        assert!(probs.data().is_finite());
+    }
+    let loss = cross_entropy(&logits, 1);
+    assert_eq!(loss.data(), 1000.0);
+    loss.backward();
+    let grads: Vec<Scalar> = logits.iter().map(Value::grad).collect();
+    assert_eq!(grads, vec![1.0, -1.0, 0.0]);
+}
+
+#[test]
+fn cross_entropy_trains_a_classifier() {
+    // class 0 on the left of the y axis, class 1 on the right
+    let points = [
+        ([-1.0, 0.5], 0),
+        ([-0.8, -0.6], 0),
+        ([-0.5, 0.1], 0),
+        ([0.6, 0.4], 1),
+        ([0.9, -0.3], 1),
+        ([0.4, -0.8], 1),
+    ];
+    let mlp = MLP::new_with_seed(2, &[4, 2], 1);
+    let mut optim = Sgd::new(mlp.get_parameters(), 0.1);
+    for _ in 0..200 {
+        for (x, class) in &points {
+            let logits = mlp.apply(&[Value::new(x[0]), Value::new(x[1])]);
+            optim.zero_grad();
+            cross_entropy(&logits, *class).backward();
+            optim.step();
+        }
+    }
+    for (x, class) in &points {
+        let probs = softmax(&mlp.apply(&[Value::new(x[0]), Value::new(x[1])]));
+        assert!(probs[*class].data() > 0.5, "{:?} misclassified", x);
+    }
+}
+
+#[test]
+fn bce_with_logits_grad_matches_finite_differences() {
+    for t in [0.0, 1.0] {
+        for x0 in [-20.0, 0.0, 20.0] {
+            let x = Value::new(x0);
+            let loss = bce_with_logits(&x, t);
+            assert!(loss.data().is_finite());
+            loss.backward();
+
+            let h = 1e-2;
+            let eval = |x: Scalar| bce_with_logits(&Value::new(x), t).data();
+            let numeric = (eval(x0 + h) - eval(x0 - h)) / (2.0 * h);
+            assert!(
+                (x.grad() - numeric).abs() < 1e-2,
+                "logit {} target {}: {} vs {}",
+                x0,
+                t,
+                x.grad(),
+                numeric
+            );
+        }
+    }
+    // exact for a large logit where ln(sigmoid(..)) would round to 0
+    assert!((bce_with_logits(&Value::new(-20.0), 1.0).data() - 20.0).abs() < 1e-6);
+}
+
+#[test]
+fn binary_cross_entropy_of_a_probability() {
+    let p = Value::new(0.25);
+    let loss = binary_cross_entropy(&p, 1.0);
+    assert!((loss.data() - Scalar::ln(4.0)).abs() < 1e-6);
+    loss.backward();
+    assert!((p.grad() + 4.0).abs() < 1e-4);
+
+    // saturated predictions are clamped instead of producing inf
+    let loss = binary_cross_entropy(&Value::new(0.0), 1.0);
+    assert!(loss.data().is_finite());
+}
+
+#[test]
+#[should_panic(expected = "binary target must be in [0, 1], got 2")]
+fn binary_cross_entropy_rejects_bad_targets() {
+    bce_with_logits(&Value::new(0.0), 2.0);
+}
+
+#[test]
+fn bce_fits_a_binary_label() {
+    let mlp = MLP::new_with_seed(2, &[3, 1], 1);
+    let x = [Value::new(0.5), Value::new(-1.0)];
+    let mut optim = Sgd::new(mlp.get_parameters(), 0.5);
+    let loss = |mlp: &MLP| bce_with_logits(&mlp.apply(&x)[0], 1.0);
+    let initial = loss(&mlp).data();
+    for _ in 0..50 {
+        optim.zero_grad();
+        loss(&mlp).backward();
+        optim.step();
+    }
+    // the tanh output caps the logit at 1, i.e. the loss at ln(1 + 1/e)
+    let fitted = loss(&mlp).data();
+    assert!(
+        fitted < initial && fitted < 0.35,
+        "{} -> {}",
+        initial,
+        fitted
+    );
+}
+
+#[test]
+fn l2_penalty_grad_is_two_lambda_w() {
+    let params: Vec<Value> = [0.5, -2.0, 3.0].iter().map(|&w| Value::new(w)).collect();
+    let penalty = l2_penalty(&params, 0.1);
+    assert!((penalty.data() - 0.1 * (0.25 + 4.0 + 9.0)).abs() < 1e-6);
+    penalty.backward();
+    for p in &params {
+        assert!((p.grad() - 2.0 * 0.1 * p.data()).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn hinge_is_zero_past_the_margin() {
+    let build = |label: Scalar| move |x: &[Value]| hinge(&x[0], label);
+    // inside the margin on both sides of the decision boundary, and past it
+    for (score, label) in [
+        (0.5, 1.0),
+        (-0.3, 1.0),
+        (0.2, -1.0),
+        (1.7, -1.0),
+        (1.5, 1.0),
+        (-2.0, -1.0),
+    ] {
+        check_gradients(build(label), &[score], 1e-3, 1e-2).unwrap();
+    }
+
+    let s = Value::new(0.25);
+    let loss = hinge(&s, 1.0);
+    loss.backward();
+    assert_eq!(loss.data(), 0.75);
+    assert_eq!(s.grad(), -1.0);
+
+    let s = Value::new(-3.0);
+    let loss = hinge(&s, -1.0);
+    loss.backward();
+    assert_eq!(loss.data(), 0.0);
+    assert_eq!(s.grad(), 0.0);
+}
+
+#[test]
+fn huber_is_quadratic_then_linear() {
+    let delta = 1.0;
+    let build = |x: &[Value]| huber(&x[0], 2.0, delta);
+    // just inside and outside both kinks
+    for p in [2.5, 1.2, 2.9, 3.1, 1.1, 0.9, 5.0, -3.0] {
+        check_gradients(build, &[p], 1e-3, 1e-2).unwrap();
+    }
+
+    let p = Value::new(2.5);
+    let loss = huber(&p, 2.0, delta);
+    loss.backward();
+    assert_eq!(loss.data(), 0.125);
+    assert_eq!(p.grad(), 0.5);
+
+    let p = Value::new(6.0);
+    let loss = huber(&p, 2.0, delta);
+    loss.backward();
+    assert_eq!(loss.data(), 3.5);
+    assert_eq!(p.grad(), 1.0);
+
+    // the grad is delta * sign(r) at the boundary itself, from either side
+    for (at, sign) in [(3.0, 1.0), (1.0, -1.0)] {
+        let p = Value::new(at);
+        huber(&p, 2.0, delta).backward();
+        assert_eq!(p.grad(), sign * delta);
+        assert_eq!(huber(&Value::new(at), 2.0, delta).data(), 0.5);
+    }
+}
+
+#[test]
+fn huber_picks_its_piece_again_on_forward() {
+    let delta = 1.0;
+    let p = Value::new(2.5);
+    let loss = huber(&p, 2.0, delta);
+    // across delta and back, on both sides of the target
+    for at in [6.0, 2.5, -3.0, 1.5, 3.0] {
+        p.set_data(at);
+        loss.forward();
+        p.zero_grad();
+        loss.backward();
+
+        let fresh_p = Value::new(at);
+        let fresh = huber(&fresh_p, 2.0, delta);
+        fresh.backward();
+        assert_eq!(loss.data(), fresh.data(), "at {}", at);
+        assert_eq!(p.grad(), fresh_p.grad(), "at {}", at);
+    }
+}
+
+#[test]
+fn huber_is_less_perturbed_by_an_outlier() {
+    fn mean_huber(preds: &[Value], targets: &[Value]) -> Value {
+        let terms: Vec<Value> = preds
+            .iter()
+            .zip(targets)
+            .map(|(p, t)| huber(p, t.data(), 0.5))
+            .collect();
+        Value::sum(&terms) / preds.len() as Scalar
+    }
+    let with_huber = slope_with_outlier(mean_huber);
+    let with_mse = slope_with_outlier(mse);
+    assert!(
+        (with_huber - 2.0).abs() < (with_mse - 2.0).abs() / 2.0,
+        "huber slope {} mse slope {}",
+        with_huber,
+        with_mse
+    );
+}
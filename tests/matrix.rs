@@ -0,0 +1,87 @@
+use micrograd::matrix::{ShapeError, ValueMatrix};
+use micrograd::testing::check_gradients;
+use micrograd::{Scalar, Value};
+
+fn matrix(rows: usize, cols: usize, xs: &[Scalar]) -> ValueMatrix {
+    ValueMatrix::from_values(rows, cols, xs.iter().map(|&x| Value::new(x)).collect()).unwrap()
+}
+
+fn data(m: &ValueMatrix) -> Vec<String> {
+    m.data.iter().map(|v| format!("{}", v.data())).collect()
+}
+
+#[test]
+fn matmul_matches_hand_computation() {
+    let a = matrix(2, 3, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    let b = matrix(3, 2, &[7.0, 8.0, 9.0, 10.0, 11.0, 12.0]);
+    let c = a.matmul(&b).unwrap();
+    assert_eq!(c.shape(), (2, 2));
+    assert_eq!(data(&c), vec!["58", "64", "139", "154"]);
+}
+
+#[test]
+fn shape_errors() {
+    let a = matrix(2, 3, &[0.0; 6]);
+    assert_eq!(
+        a.matmul(&a).unwrap_err(),
+        ShapeError::Mismatch {
+            op: "matmul",
+            left: (2, 3),
+            right: (2, 3)
+        }
+    );
+    assert!(a.add_row_vector(&matrix(1, 2, &[0.0; 2])).is_err());
+    assert!(ValueMatrix::from_values(2, 2, vec![Value::new(1.0)]).is_err());
+}
+
+#[test]
+fn add_row_vector_map_and_flatten() {
+    let a = matrix(2, 2, &[1.0, 2.0, 3.0, 4.0]);
+    let b = matrix(1, 2, &[10.0, 20.0]);
+    let c = a
+        .add_row_vector(&b)
+        .unwrap()
+        .map(|v| v.clone() * Value::new(2.0));
+    assert_eq!(data(&c), vec!["22", "44", "26", "48"]);
+    assert_eq!(c.flatten().len(), 4);
+}
+
+#[test]
+fn randn_is_seeded() {
+    let a = ValueMatrix::randn(3, 4, 7);
+    let b = ValueMatrix::randn(3, 4, 7);
+    assert_eq!(data(&a), data(&b));
+    assert_ne!(data(&a), data(&ValueMatrix::randn(3, 4, 8)));
+    assert_eq!(data(&ValueMatrix::zeros(1, 2)), vec!["0", "0"]);
+}
+
+#[test]
+fn matmul_entries_are_dot_products() {
+    let a = matrix(2, 3, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    let b = matrix(3, 1, &[0.5, -1.0, 2.0]);
+    let c = a.matmul(&b).unwrap();
+    let entry = c.get(1, 0).unwrap();
+    assert_eq!(entry.op().name(), "sum");
+    assert_eq!(entry.children().len(), 3);
+}
+
+#[test]
+fn matmul_grads_match_finite_differences() {
+    // a 2x3 times a 3x2, every entry weighted differently so each grad is distinct
+    let inputs = [
+        0.3, -1.2, 0.8, 1.5, 0.1, -0.6, 0.9, -0.4, 1.1, 0.2, -0.7, 1.3,
+    ];
+    let build = |x: &[Value]| {
+        let a = ValueMatrix::from_values(2, 3, x[..6].to_vec()).unwrap();
+        let b = ValueMatrix::from_values(3, 2, x[6..].to_vec()).unwrap();
+        let c = a.matmul(&b).unwrap();
+        let weighted: Vec<Value> = c
+            .flatten()
+            .iter()
+            .enumerate()
+            .map(|(i, v)| v.tanh() * (i as Scalar + 1.0))
+            .collect();
+        Value::sum(&weighted)
+    };
+    check_gradients(build, &inputs, 1e-3, 1e-2).unwrap();
+}
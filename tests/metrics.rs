@@ -0,0 +1,334 @@
+use micrograd::data::stratified_split;
+use micrograd::metrics::{
+    accuracy, auc, best_threshold_by_f1, binary_accuracy, binary_confusion_matrix,
+    confusion_matrix, per_class_accuracy, roc_curve, StreamingMetrics,
+};
+use micrograd::train::{FitConfig, Loss, Validation};
+use micrograd::{Activation, Scalar, MLP};
+
+#[test]
+fn auc_with_ties() {
+    let scores = [0.9, 0.5, 0.5, 0.1];
+    let labels = [1, 1, 0, 0];
+    assert_eq!(
+        roc_curve(&scores, &labels),
+        vec![
+            (Scalar::INFINITY, 0.0, 0.0),
+            (0.9, 0.0, 0.5),
+            (0.5, 0.5, 1.0),
+            (0.1, 1.0, 1.0)
+        ]
+    );
+    // 3 correctly ordered pairs plus one tie out of 4 pairs
+    assert!((auc(&scores, &labels) - 0.875).abs() < 1e-6);
+}
+
+#[test]
+fn perfect_separation() {
+    let scores = [0.1, 0.8, 0.3, 0.95];
+    let labels = [0, 1, 0, 1];
+    assert_eq!(auc(&scores, &labels), 1.0);
+    // reversed scores are perfectly wrong
+    let flipped: Vec<Scalar> = scores.iter().map(|s| -s).collect();
+    assert_eq!(auc(&flipped, &labels), 0.0);
+}
+
+#[test]
+fn f1_optimal_threshold() {
+    let scores = [0.9, 0.8, 0.7, 0.6, 0.2];
+    let labels = [1, 1, 0, 1, 0];
+    let (threshold, f1) = best_threshold_by_f1(&scores, &labels);
+    assert_eq!(threshold, 0.6);
+    assert!((f1 - 6.0 / 7.0).abs() < 1e-6);
+}
+
+#[test]
+#[should_panic(expected = "need both positive and negative labels")]
+fn single_class_is_rejected() {
+    auc(&[0.1, 0.2], &[1, 1]);
+}
+
+#[test]
+fn streaming_regression_matches_batch() {
+    let preds: Vec<Vec<Scalar>> = (0..50)
+        .map(|i| vec![i as Scalar * 0.1, (i as Scalar).sin()])
+        .collect();
+    let targets: Vec<Vec<Scalar>> = (0..50)
+        .map(|i| vec![i as Scalar * 0.12, (i as Scalar).cos()])
+        .collect();
+    let mut m = StreamingMetrics::new();
+    for (p, t) in preds.iter().zip(&targets) {
+        m.update(p, t);
+    }
+    let errors: Vec<Scalar> = preds
+        .iter()
+        .flatten()
+        .zip(targets.iter().flatten())
+        .map(|(p, t)| p - t)
+        .collect();
+    let mse = errors.iter().map(|e| e * e).sum::<Scalar>() / errors.len() as Scalar;
+    let mae = errors.iter().map(|e| e.abs()).sum::<Scalar>() / errors.len() as Scalar;
+
+    let report = m.finalize();
+    assert_eq!(report.samples, 50);
+    assert!((report.mse - mse).abs() < 1e-5);
+    assert!((report.mae - mae).abs() < 1e-5);
+    assert_eq!(report.accuracy, None);
+}
+
+#[test]
+fn streaming_mean_is_stable_over_millions_of_updates() {
+    let mut m = StreamingMetrics::new();
+    for i in 0..3_000_000 {
+        // errors alternate between 0.1 and 0.3
+        let err = if i % 2 == 0 { 0.1 } else { 0.3 };
+        m.update(&[1.0 + err], &[1.0]);
+    }
+    let report = m.finalize();
+    assert!((report.mae - 0.2).abs() < 1e-5);
+    assert!((report.mse - 0.05).abs() < 1e-5);
+}
+
+#[test]
+fn streaming_classification() {
+    let mut m = StreamingMetrics::classification(3);
+    m.update(&[0.9, 0.05, 0.05], &[1.0, 0.0, 0.0]);
+    m.update(&[0.1, 0.8, 0.1], &[0.0, 0.0, 1.0]);
+    m.update(&[0.2, 0.2, 0.6], &[0.0, 0.0, 1.0]);
+    // tie resolves to the lowest index
+    m.update(&[0.5, 0.5, 0.0], &[1.0, 0.0, 0.0]);
+    let report = m.finalize();
+    assert_eq!(report.accuracy, Some(0.75));
+    assert_eq!(
+        report.confusion,
+        Some(vec![vec![2, 0, 0], vec![0, 0, 0], vec![0, 1, 1]])
+    );
+
+    let mut binary = StreamingMetrics::classification(2);
+    binary.update(&[0.7], &[1.0]);
+    binary.update(&[0.4], &[1.0]);
+    assert_eq!(binary.finalize().accuracy, Some(0.5));
+}
+
+/// A linear layer with identity weights, so the outputs are the inputs
+fn identity(n: usize) -> MLP {
+    let mlp = MLP::new_with_seed(n, &[n], 0).with_activations(&[Activation::Linear]);
+    for (i, neuron) in mlp.layers()[0].neurons().iter().enumerate() {
+        for (j, w) in neuron.weights().iter().enumerate() {
+            w.set_data(if i == j { 1.0 } else { 0.0 });
+        }
+        neuron.bias().unwrap().set_data(0.0);
+    }
+    mlp
+}
+
+#[test]
+fn accuracy_and_confusion_of_fixed_outputs() {
+    let model = identity(3);
+    let outputs = vec![
+        vec![0.9, 0.1, 0.0],
+        vec![0.2, 0.7, 0.1],
+        vec![0.3, 0.3, 0.1], // tie, goes to class 0
+        vec![0.1, 0.2, 0.6],
+        vec![0.5, 0.1, 0.4],
+    ];
+    let labels = [0, 1, 1, 2, 2];
+    assert_eq!(accuracy(&model, &outputs, &labels), 0.6);
+    assert_eq!(
+        confusion_matrix(&model, &outputs, &labels, 3),
+        vec![vec![1, 0, 0], vec![1, 1, 0], vec![1, 0, 1]]
+    );
+    assert_eq!(
+        per_class_accuracy(&confusion_matrix(&model, &outputs, &labels, 3)),
+        vec![Some(1.0), Some(0.5), Some(0.5)]
+    );
+    // a class with no samples has no accuracy
+    assert_eq!(
+        per_class_accuracy(&[vec![2, 1], vec![0, 0]]),
+        vec![Some(2.0 / 3.0), None]
+    );
+}
+
+#[test]
+fn binary_metrics_threshold_the_output() {
+    let model = identity(1);
+    let outputs: Vec<Vec<Scalar>> = [0.1, 0.4, 0.6, 0.8].iter().map(|&y| vec![y]).collect();
+    let labels = [0, 1, 0, 1];
+    assert_eq!(binary_accuracy(&model, &outputs, &labels, 0.5), 0.5);
+    assert_eq!(
+        binary_confusion_matrix(&model, &outputs, &labels, 0.5),
+        vec![vec![1, 1], vec![1, 1]]
+    );
+    // a tanh-style cutoff; >= counts as positive
+    assert_eq!(binary_accuracy(&model, &outputs, &labels, 0.4), 0.75);
+    assert_eq!(
+        binary_confusion_matrix(&model, &outputs, &labels, 0.4),
+        vec![vec![1, 1], vec![0, 2]]
+    );
+}
+
+#[test]
+#[should_panic(expected = "binary metrics need a single output, got 3")]
+fn binary_metrics_reject_several_outputs() {
+    binary_accuracy(&identity(3), &[vec![0.0, 1.0, 0.0]], &[1], 0.5);
+}
+
+/// Two well separated blobs on either side of the line x + y = 0
+fn blobs() -> (Vec<Vec<Scalar>>, Vec<usize>) {
+    let mut xs = vec![];
+    let mut labels = vec![];
+    for i in 0..10 {
+        let t = i as Scalar / 10.0;
+        xs.push(vec![1.0 + t, 0.5 - t]);
+        labels.push(1);
+        xs.push(vec![-1.0 - t, -0.5 + t]);
+        labels.push(0);
+    }
+    (xs, labels)
+}
+
+#[test]
+fn trained_classifier_separates_blobs() {
+    let (xs, labels) = blobs();
+
+    let binary = MLP::new_with_seed(2, &[4, 1], 3)
+        .with_activations(&[Activation::Tanh, Activation::Sigmoid]);
+    let ys: Vec<Vec<Scalar>> = labels.iter().map(|&l| vec![l as Scalar]).collect();
+    let config = FitConfig {
+        loss: Loss::BinaryCrossEntropy,
+        ..FitConfig::new(0.5, 200)
+    };
+    binary.fit(&xs, &ys, config);
+    assert_eq!(binary_accuracy(&binary, &xs, &labels, 0.5), 1.0);
+
+    let multi = MLP::new_with_seed(2, &[4, 2], 3);
+    let one_hot: Vec<Vec<Scalar>> = labels
+        .iter()
+        .map(|&l| {
+            if l == 0 {
+                vec![1.0, 0.0]
+            } else {
+                vec![0.0, 1.0]
+            }
+        })
+        .collect();
+    multi.fit(&xs, &one_hot, FitConfig::new(0.2, 200));
+    assert_eq!(accuracy(&multi, &xs, &labels), 1.0);
+    assert_eq!(
+        confusion_matrix(&multi, &xs, &labels, 2),
+        vec![vec![10, 0], vec![0, 10]]
+    );
+}
+
+fn one_hot(labels: &[usize], num_classes: usize) -> Vec<Vec<Scalar>> {
+    labels
+        .iter()
+        .map(|&l| (0..num_classes).map(|c| (c == l) as u8 as Scalar).collect())
+        .collect()
+}
+
+#[test]
+fn fit_reports_per_class_validation_accuracy() {
+    let (xs, labels) = blobs();
+    let split = stratified_split(&xs, &labels, 0.3, 1).unwrap();
+    let validation = Validation {
+        xs: split.test_inputs.clone(),
+        ys: one_hot(&split.test_targets, 2),
+        num_classes: Some(2),
+    };
+    let config = FitConfig {
+        validation: Some(validation.clone()),
+        ..FitConfig::new(0.2, 200)
+    };
+    let multi = MLP::new_with_seed(2, &[4, 2], 3);
+    let report = multi.fit(
+        &split.train_inputs,
+        &one_hot(&split.train_targets, 2),
+        config,
+    );
+    let confusion = confusion_matrix(&multi, &split.test_inputs, &split.test_targets, 2);
+    assert_eq!(report.class_accuracy, Some(per_class_accuracy(&confusion)));
+    assert_eq!(report.class_accuracy, Some(vec![Some(1.0), Some(1.0)]));
+    // AUC needs a single score per sample
+    assert_eq!((report.auc, report.best_threshold), (None, None));
+
+    // a single output is thresholded at 0.5
+    let binary = MLP::new_with_seed(2, &[4, 1], 3)
+        .with_activations(&[Activation::Tanh, Activation::Sigmoid]);
+    let ys = |labels: &[usize]| {
+        labels
+            .iter()
+            .map(|&l| vec![l as Scalar])
+            .collect::<Vec<_>>()
+    };
+    let config = FitConfig {
+        loss: Loss::BinaryCrossEntropy,
+        validation: Some(Validation {
+            ys: ys(&split.test_targets),
+            ..validation
+        }),
+        ..FitConfig::new(0.5, 1)
+    };
+    let report = binary.fit(&split.train_inputs, &ys(&split.train_targets), config);
+    let confusion = binary_confusion_matrix(&binary, &split.test_inputs, &split.test_targets, 0.5);
+    assert_eq!(report.class_accuracy, Some(per_class_accuracy(&confusion)));
+    // and scored for AUC and the F1-optimal threshold
+    let scores: Vec<Scalar> = split
+        .test_inputs
+        .iter()
+        .map(|x| binary.predict(x)[0])
+        .collect();
+    let bytes: Vec<u8> = split.test_targets.iter().map(|&l| l as u8).collect();
+    assert_eq!(report.auc, Some(auc(&scores, &bytes)));
+    assert_eq!(
+        report.best_threshold,
+        Some(best_threshold_by_f1(&scores, &bytes))
+    );
+
+    // no classification, no per-class accuracy
+    let report = MLP::new_with_seed(2, &[4, 1], 3).fit(&xs, &ys(&labels), FitConfig::new(0.2, 1));
+    assert_eq!(report.class_accuracy, None);
+    assert_eq!(report.auc, None);
+}
+
+#[test]
+fn fit_streams_the_validation_pass() {
+    let (xs, labels) = blobs();
+    let ys = one_hot(&labels, 2);
+    let (train, held_out) = (0..16, 16..20);
+    let validation = |num_classes| Validation {
+        xs: xs[held_out.clone()].to_vec(),
+        ys: ys[held_out.clone()].to_vec(),
+        num_classes,
+    };
+    let fit = |num_classes| {
+        let mlp = MLP::new_with_seed(2, &[4, 2], 3);
+        let config = FitConfig {
+            validation: Some(validation(num_classes)),
+            ..FitConfig::new(0.2, 20)
+        };
+        let report = mlp.fit(&xs[train.clone()], &ys[train.clone()], config);
+        (mlp, report)
+    };
+    let streamed = |mlp: &MLP, mut metrics: StreamingMetrics| {
+        for (x, y) in xs[held_out.clone()].iter().zip(&ys[held_out.clone()]) {
+            metrics.update(&mlp.predict(x), y);
+        }
+        metrics.finalize()
+    };
+
+    let (mlp, report) = fit(None);
+    let metrics = report.validation.unwrap();
+    assert_eq!(metrics, streamed(&mlp, StreamingMetrics::new()));
+    assert_eq!((metrics.samples, metrics.accuracy), (4, None));
+
+    let (mlp, report) = fit(Some(2));
+    let metrics = report.validation.unwrap();
+    assert_eq!(metrics, streamed(&mlp, StreamingMetrics::classification(2)));
+    let confusion = metrics.confusion.unwrap();
+    assert_eq!(report.class_accuracy, Some(per_class_accuracy(&confusion)));
+
+    // nothing is scored without a validation set
+    let report = MLP::new_with_seed(2, &[4, 2], 3).fit(&xs, &ys, FitConfig::new(0.2, 1));
+    assert_eq!(report.validation, None);
+}
@@ -0,0 +1,895 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use micrograd::functional::dot;
+use micrograd::losses::mse;
+use micrograd::optim::Sgd;
+use micrograd::testing::{assert_graph_budget, GraphBudget};
+use micrograd::with_tag;
+use micrograd::{
+    no_grad, Activation, BuildError, Dropout, Init, Layer, LayerConfig, MlpBuilder, Neuron, Op,
+    Scalar, Value, MLP,
+};
+
+#[test]
+fn mlp_parameter_count() {
+    let mlp = MLP::new(2, &[3, 1]);
+    // (2 weights + 1 bias) * 3 neurons + (3 weights + 1 bias) * 1 neuron
+    assert_eq!(mlp.get_parameters().len(), 13);
+    assert_eq!(mlp.layers().len(), 2);
+    assert_eq!(mlp.layers()[0].neurons().len(), 3);
+}
+
+#[test]
+fn weights_and_biases_partition_the_parameters() {
+    let mlp = MLP::new(2, &[3, 1]);
+    assert_eq!(mlp.weights().len(), 9);
+    assert_eq!(mlp.biases().len(), 4);
+    let neuron = &mlp.layers()[0].neurons()[0];
+    assert_eq!(neuron.weights().len(), 2);
+    assert_eq!(neuron.bias().unwrap().label(), "l0.n0.b");
+    assert!(mlp.biases().iter().all(|b| b.label().ends_with(".b")));
+}
+
+#[test]
+fn mlp_apply_and_backward() {
+    let mlp = MLP::new(2, &[3, 1]);
+    let out = mlp.apply(&[Value::new(0.5), Value::new(-1.0)]);
+    assert_eq!(out.len(), 1);
+    assert!(out[0].data() > -1.0 && out[0].data() < 1.0);
+
+    out[0].backward();
+    // the output neuron's bias always receives 1 - tanh²
+    let bias = mlp.get_parameters().pop().unwrap();
+    let t = out[0].data();
+    assert!((bias.grad() - (1.0 - t * t)).abs() < 1e-6);
+}
+
+#[test]
+#[should_panic(expected = "neuron expects 2 inputs, got 1")]
+fn mlp_rejects_wrong_input_size() {
+    MLP::new(2, &[1]).apply(&[Value::new(1.0)]);
+}
+
+#[test]
+fn relu_network_fits_one_point() {
+    // 2 -> 3 (relu) -> 1, with parameters kept as plain floats and the graph rebuilt
+    // every step so no grad carries over between iterations
+    let mut hidden = [[0.3, -0.2, 0.1], [0.5, 0.4, -0.3], [-0.6, 0.2, 0.2]];
+    let mut output = [0.4, -0.3, 0.6, 0.0];
+    let x = [0.5, -1.0];
+    let target = 0.8;
+    let lr = 0.1;
+
+    let mut loss = Scalar::INFINITY;
+    for _ in 0..100 {
+        let hidden_v: Vec<Vec<Value>> = hidden
+            .iter()
+            .map(|n| n.iter().map(|&w| Value::new(w)).collect())
+            .collect();
+        let output_v: Vec<Value> = output.iter().map(|&w| Value::new(w)).collect();
+        let activations: Vec<Value> = hidden_v
+            .iter()
+            .map(|n| (&n[0] * x[0] + &n[1] * x[1] + &n[2]).relu())
+            .collect();
+        let mut y = output_v[3].clone();
+        for (a, w) in activations.iter().zip(&output_v) {
+            y += a * w;
+        }
+        let l = (y - target).pow(2.0);
+        l.backward();
+        loss = l.data();
+
+        for (n, n_v) in hidden.iter_mut().zip(&hidden_v) {
+            for (w, w_v) in n.iter_mut().zip(n_v) {
+                *w -= lr * w_v.grad();
+            }
+        }
+        for (w, w_v) in output.iter_mut().zip(&output_v) {
+            *w -= lr * w_v.grad();
+        }
+    }
+    assert!(loss < 1e-4, "loss {}", loss);
+}
+
+#[test]
+fn backward_accumulate_adds_onto_existing_grads() {
+    let mlp = MLP::new(2, &[3, 1]);
+    let x = [Value::new(0.5), Value::new(-1.0)];
+    let pass = |accumulate: bool| {
+        let out = mlp.apply(&x).pop().unwrap();
+        if accumulate {
+            out.backward_accumulate();
+        } else {
+            out.backward();
+        }
+        mlp.get_parameters()
+            .iter()
+            .map(Value::grad)
+            .collect::<Vec<_>>()
+    };
+    let once = pass(false);
+    let twice = pass(true);
+    for (a, b) in once.iter().zip(&twice) {
+        assert!((b - 2.0 * a).abs() < 1e-6);
+    }
+
+    mlp.zero_grad();
+    assert!(mlp.get_parameters().iter().all(|p| p.grad() == 0.0));
+    assert_eq!(pass(true), once);
+}
+
+#[test]
+fn backward_resets_reachable_grads() {
+    let mlp = MLP::new(2, &[3, 1]);
+    let x = [Value::new(0.5), Value::new(-1.0)];
+    let out = mlp.apply(&x).pop().unwrap();
+    out.backward();
+    let once: Vec<Scalar> = mlp.get_parameters().iter().map(Value::grad).collect();
+    out.backward();
+    let again: Vec<Scalar> = mlp.get_parameters().iter().map(Value::grad).collect();
+    assert_eq!(once, again);
+
+    // a new graph over the same parameters doesn't pick up the old grads either
+    mlp.apply(&x).pop().unwrap().backward();
+    let rebuilt: Vec<Scalar> = mlp.get_parameters().iter().map(Value::grad).collect();
+    assert_eq!(once, rebuilt);
+}
+
+#[test]
+fn parameters_are_labeled_by_position() {
+    let mlp = MLP::new(2, &[3, 1]);
+    let labels: Vec<String> = mlp.get_parameters().iter().map(Value::label).collect();
+    assert_eq!(&labels[..3], ["l0.n0.w0", "l0.n0.w1", "l0.n0.b"]);
+    assert_eq!(labels[8], "l0.n2.b");
+    assert_eq!(labels[12], "l1.n0.b");
+}
+
+#[test]
+fn inference_without_grad_matches_tracked_forward() {
+    let mlp = MLP::new(3, &[4, 2]);
+    let x = [Value::new(0.1), Value::new(-0.7), Value::new(1.2)];
+    let tracked: Vec<_> = mlp.apply(&x).iter().map(|v| v.data().to_bits()).collect();
+    let untracked = no_grad(|| mlp.apply(&x));
+    assert!(untracked.iter().all(Value::is_leaf));
+    let untracked: Vec<_> = untracked.iter().map(|v| v.data().to_bits()).collect();
+    assert_eq!(tracked, untracked);
+}
+
+#[test]
+fn same_seed_same_parameters() {
+    let bits = |mlp: &MLP| -> Vec<_> {
+        mlp.get_parameters()
+            .iter()
+            .map(|p| p.data().to_bits())
+            .collect()
+    };
+    let a = MLP::new_with_seed(3, &[4, 2], 42);
+    let b = MLP::new_with_seed(3, &[4, 2], 42);
+    let c = MLP::new_with_seed(3, &[4, 2], 43);
+    assert_eq!(bits(&a), bits(&b));
+    assert_ne!(bits(&a), bits(&c));
+}
+
+/// Standard deviation of the last layer's pre-activations `w · x + b` over random inputs
+fn final_pre_activation_std(mlp: &MLP, nin: usize) -> Scalar {
+    let mut rng = StdRng::seed_from_u64(7);
+    let (hidden, last) = mlp.layers().split_at(mlp.layers().len() - 1);
+    let mut samples = Vec::new();
+    for _ in 0..20 {
+        let mut x: Vec<Value> = (0..nin)
+            .map(|_| Value::new(rng.gen_range(-1.0..=1.0)))
+            .collect();
+        for layer in hidden {
+            x = layer.apply(&x);
+        }
+        for n in last[0].neurons() {
+            let params = n.get_parameters();
+            let (b, w) = params.split_last().unwrap();
+            let z: Scalar = b.data()
+                + w.iter()
+                    .zip(&x)
+                    .map(|(w, x)| w.data() * x.data())
+                    .sum::<Scalar>();
+            samples.push(z);
+        }
+    }
+    let mean = samples.iter().sum::<Scalar>() / samples.len() as Scalar;
+    let var = samples.iter().map(|z| (z - mean).powi(2)).sum::<Scalar>() / samples.len() as Scalar;
+    var.sqrt()
+}
+
+#[test]
+fn xavier_keeps_pre_activations_in_range() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let xavier = MLP::new_with_init(32, &[32, 32, 32], Init::XavierUniform, &mut rng);
+    let uniform = MLP::new_with_init(32, &[32, 32, 32], Init::default(), &mut rng);
+    let std = final_pre_activation_std(&xavier, 32);
+    assert!(std > 0.2 && std < 2.0, "pre-activation std {}", std);
+    // the original init saturates the tanh units
+    assert!(final_pre_activation_std(&uniform, 32) > 2.0);
+}
+
+#[test]
+fn xavier_and_he_biases_start_at_zero() {
+    let mut rng = StdRng::seed_from_u64(1);
+    for init in [
+        Init::XavierUniform,
+        Init::XavierNormal,
+        Init::HeUniform,
+        Init::HeNormal,
+    ] {
+        let layer = Layer::new_with_init(8, 4, init, &mut rng);
+        for n in layer.neurons() {
+            assert_eq!(n.get_parameters().last().unwrap().data(), 0.0);
+        }
+    }
+    let layer = Layer::new_with_init(100, 1, Init::HeUniform, &mut rng);
+    let limit = Scalar::sqrt(6.0 / 100.0);
+    assert!(layer
+        .get_parameters()
+        .iter()
+        .all(|p| p.data().abs() <= limit));
+}
+
+/// Mean squared error after `steps` steps of plain SGD fitting a linear target
+fn loss_after_training(init: Init, steps: usize) -> Scalar {
+    let mlp = MLP::new_with_init(4, &[12, 12, 12, 1], init, &mut StdRng::seed_from_u64(3));
+    let mut rng = StdRng::seed_from_u64(5);
+    let data: Vec<(Vec<Scalar>, Scalar)> = (0..24)
+        .map(|_| {
+            let x: Vec<Scalar> = (0..4).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+            let y = 0.5 * x[0] - 0.3 * x[1];
+            (x, y)
+        })
+        .collect();
+    let mut optim = Sgd::new(mlp.get_parameters(), 0.05);
+    let mut last = Scalar::INFINITY;
+    for _ in 0..steps {
+        let preds: Vec<Value> = data
+            .iter()
+            .map(|(x, _)| {
+                let x: Vec<Value> = x.iter().map(|&xi| Value::new(xi)).collect();
+                mlp.apply(&x).pop().unwrap()
+            })
+            .collect();
+        let targets: Vec<Value> = data.iter().map(|(_, t)| Value::new(*t)).collect();
+        let loss = mse(&preds, &targets);
+        last = loss.data();
+        optim.zero_grad();
+        loss.backward();
+        optim.step();
+    }
+    last
+}
+
+#[test]
+fn xavier_trains_faster_than_uniform() {
+    let xavier = loss_after_training(Init::XavierUniform, 40);
+    let uniform = loss_after_training(Init::default(), 40);
+    assert!(
+        xavier * 4.0 < uniform,
+        "xavier {} uniform {}",
+        xavier,
+        uniform
+    );
+}
+
+#[test]
+fn relu_hidden_linear_output_fits_beyond_unit_range() {
+    let mlp =
+        MLP::new_with_seed(2, &[4, 1], 1).with_activations(&[Activation::ReLU, Activation::Linear]);
+    assert_eq!(mlp.layers()[0].neurons()[0].activation(), Activation::ReLU);
+    let x = [Value::new(0.5), Value::new(-1.0)];
+    let mut optim = Sgd::new(mlp.get_parameters(), 0.05);
+    let mut loss = Scalar::INFINITY;
+    for _ in 0..200 {
+        let l = (mlp.apply(&x).pop().unwrap() - 3.0).pow(2.0);
+        loss = l.data();
+        l.backward();
+        optim.step();
+    }
+    assert!(loss < 1e-4, "loss {}", loss);
+}
+
+#[test]
+#[should_panic(expected = "got 1 activations for 2 layers")]
+fn activations_must_match_layers() {
+    MLP::new_with_activations(2, &[3, 1], &[Activation::ReLU]);
+}
+
+/// Loss after fitting the single target 3.5 with plain SGD
+fn fit_target(mlp: &MLP) -> Scalar {
+    let x = [Value::new(0.5), Value::new(-1.0)];
+    let mut optim = Sgd::new(mlp.get_parameters(), 0.02);
+    let mut loss = Scalar::INFINITY;
+    for _ in 0..200 {
+        let l = (mlp.apply(&x).pop().unwrap() - 3.5).pow(2.0);
+        loss = l.data();
+        l.backward();
+        optim.step();
+    }
+    loss
+}
+
+#[test]
+fn regressor_fits_targets_beyond_tanh_range() {
+    let regressor = MLP::new_with_seed(2, &[4, 1], 1).into_regressor();
+    let out = regressor
+        .apply(&[Value::new(0.5), Value::new(-1.0)])
+        .pop()
+        .unwrap();
+    assert!(matches!(*out.op(), Op::MulAdd(..)));
+    let loss = fit_target(&regressor);
+    assert!(loss < 0.01, "regressor loss {}", loss);
+
+    // an all-tanh network stays at least (3.5 - 1)² away
+    let loss = fit_target(&MLP::new_with_seed(2, &[4, 1], 1));
+    assert!(loss > 6.0, "tanh loss {}", loss);
+
+    let hidden = MLP::new_regressor(2, &[4, 1]);
+    assert_eq!(
+        hidden.layers()[0].neurons()[0].activation(),
+        Activation::Tanh
+    );
+}
+
+#[test]
+fn apply_batch_matches_individual_calls() {
+    let mlp = MLP::new_with_seed(2, &[4, 1], 2);
+    let xs: Vec<Vec<Value>> = [[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]]
+        .iter()
+        .map(|x| x.iter().map(|&xi| Value::new(xi)).collect())
+        .collect();
+    let batch = mlp.apply_batch(&xs);
+    assert_eq!(batch.len(), 4);
+    for (x, out) in xs.iter().zip(&batch) {
+        let single: Vec<Scalar> = mlp.apply(x).iter().map(Value::data).collect();
+        let batched: Vec<Scalar> = out.iter().map(Value::data).collect();
+        assert_eq!(single, batched);
+    }
+
+    let hidden = mlp.layers()[0].apply_batch(&xs);
+    assert!(hidden.iter().all(|row| row.len() == 4));
+}
+
+#[test]
+#[should_panic(expected = "batch row 1 has 1 inputs, expected 2")]
+fn apply_batch_checks_every_row_first() {
+    let xs = vec![
+        vec![Value::new(0.0), Value::new(1.0)],
+        vec![Value::new(1.0)],
+    ];
+    MLP::new(2, &[3, 1]).apply_batch(&xs);
+}
+
+#[test]
+fn predict_matches_apply() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let acts = [
+        Activation::Tanh,
+        Activation::ReLU,
+        Activation::Sigmoid,
+        Activation::LeakyReLU(0.1),
+        Activation::Gelu,
+        Activation::Linear,
+    ];
+    for seed in 0..20 {
+        let act = acts[seed as usize % acts.len()];
+        let mlp =
+            MLP::new_with_seed(3, &[5, 4, 2], seed).with_activations(&[act, Activation::Tanh, act]);
+        for _ in 0..10 {
+            let x: Vec<Scalar> = (0..3).map(|_| rng.gen_range(-2.0..=2.0)).collect();
+            let inputs: Vec<Value> = x.iter().map(|&xi| Value::new(xi)).collect();
+            let applied: Vec<Scalar> = mlp.apply(&inputs).iter().map(Value::data).collect();
+            let predicted = mlp.predict(&x);
+            assert_eq!(predicted.len(), 2);
+            for (a, p) in applied.iter().zip(&predicted) {
+                assert!(
+                    (a - p).abs() < 1e-6,
+                    "apply {:?} predict {:?}",
+                    applied,
+                    predicted
+                );
+            }
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "neuron expects 2 inputs, got 3")]
+fn predict_rejects_wrong_input_size() {
+    MLP::new(2, &[1]).predict(&[1.0, 2.0, 3.0]);
+}
+
+#[test]
+fn dropout_zeroes_about_half_the_units() {
+    let dropout = Dropout::new(0.5, 3);
+    let x: Vec<Value> = (0..1000).map(|_| Value::new(1.0)).collect();
+    let out = dropout.apply(&x);
+    let zeroed = out.iter().filter(|v| v.data() == 0.0).count();
+    assert!((400..600).contains(&zeroed), "{} zeroed", zeroed);
+    assert!(out.iter().all(|v| v.data() == 0.0 || v.data() == 2.0));
+
+    // no grad flows back through a dropped unit
+    let total = Value::sum(&out);
+    total.backward();
+    for (xi, yi) in x.iter().zip(&out) {
+        assert_eq!(xi.grad(), yi.data());
+    }
+}
+
+#[test]
+fn eval_mode_disables_dropout() {
+    let plain = MLP::new_with_seed(3, &[16, 16, 1], 8);
+    let mlp = plain.clone().with_dropout(0.5, 1);
+    assert!(mlp.layers()[0].dropout().unwrap().is_training());
+    assert!(mlp.layers()[2].dropout().is_none());
+    let x = [Value::new(0.5), Value::new(-0.2), Value::new(0.9)];
+    let forward = |m: &MLP| m.apply(&x).pop().unwrap().data();
+
+    let first = forward(&mlp);
+    assert!((0..5).any(|_| forward(&mlp) != first));
+
+    mlp.set_training(false);
+    assert_eq!(forward(&mlp), forward(&plain));
+    assert_eq!(forward(&mlp), forward(&mlp));
+
+    mlp.set_training(true);
+    assert!(mlp.layers()[1].dropout().unwrap().is_training());
+}
+
+#[test]
+fn parameter_counts_and_summary() {
+    let mlp = MLP::new(2, &[3, 1]);
+    assert_eq!(mlp.num_parameters(), 3 * (2 + 1) + (3 + 1));
+    assert_eq!(mlp.num_parameters(), mlp.get_parameters().len());
+    assert_eq!(mlp.layers()[0].num_parameters(), 9);
+    assert_eq!(mlp.layers()[1].neurons()[0].num_parameters(), 4);
+
+    let mlp = MLP::new_regressor(2, &[3, 1]).with_dropout(0.1, 0);
+    let expected = "\
+layer  shape        activation     params
+0      2 -> 3       tanh+dropout        9
+1      3 -> 1       linear              4
+total                                  13";
+    assert_eq!(mlp.summary(), expected);
+}
+
+/// Loss after fitting `y = x` with every hidden unit starting negative on positive inputs
+fn loss_from_dead_start(hidden: Activation) -> Scalar {
+    let init = Init::Uniform { lo: -1.0, hi: -0.5 };
+    let mlp = MLP::new_with_init(1, &[4, 1], init, &mut StdRng::seed_from_u64(2))
+        .with_activations(&[hidden, Activation::Linear]);
+    let xs: [Scalar; 4] = [0.25, 0.5, 0.75, 1.0];
+    let inputs: Vec<Vec<Value>> = xs.iter().map(|&x| vec![Value::new(x)]).collect();
+    let targets: Vec<Value> = xs.iter().map(|&x| Value::new(x)).collect();
+    let mut optim = Sgd::new(mlp.get_parameters(), 0.2);
+    let mut loss = Scalar::INFINITY;
+    for _ in 0..300 {
+        let preds: Vec<Value> = inputs.iter().map(|x| mlp.apply(x).pop().unwrap()).collect();
+        let l = mse(&preds, &targets);
+        loss = l.data();
+        l.backward();
+        optim.step();
+    }
+    loss
+}
+
+#[test]
+fn leaky_relu_recovers_where_relu_stays_dead() {
+    let relu = loss_from_dead_start(Activation::ReLU);
+    let leaky = loss_from_dead_start(Activation::LeakyReLU(0.1));
+    // a dead ReLU layer leaves only the output bias: the best it can do is the mean
+    assert!(relu > 0.05, "relu loss {}", relu);
+    assert!(leaky < 0.01, "leaky relu loss {}", leaky);
+}
+
+#[test]
+fn neuron_graph_stats() {
+    let n = Neuron::new(3);
+    let x = [Value::new(0.1), Value::new(0.2), Value::new(0.3)];
+    let out = n.apply(&x);
+    // 3 inputs, 3 weights and the bias; 3 fused multiply-adds onto b and tanh
+    assert_graph_budget(
+        &out,
+        GraphBudget {
+            max_nodes: Some(7 + 4),
+            max_per_op: vec![("mul_add", 3), ("mul", 0), ("plus", 0), ("tanh", 1)],
+            max_depth: Some(4),
+        },
+    );
+    let stats = out.graph_stats();
+    assert_eq!(stats.nodes, 7 + 4);
+    assert_eq!(stats.edges, 3 * 3 + 1);
+}
+
+#[test]
+fn fused_neuron_graph_is_smaller_than_the_unfused_one() {
+    let n = Neuron::new(32);
+    let x: Vec<Value> = (0..32).map(|i| Value::new(i as Scalar / 32.0)).collect();
+    // 32 inputs, 32 weights and the bias are shared; the ops go from 32 + 3 to 32 + 1
+    assert_graph_budget(
+        &n.apply(&x),
+        GraphBudget {
+            max_nodes: Some(65 + 33),
+            max_per_op: vec![("mul_add", 32), ("mul", 0), ("sum", 0)],
+            max_depth: Some(33),
+        },
+    );
+    let unfused = (dot(&x, n.weights()) + n.bias().unwrap()).tanh();
+    assert_eq!(unfused.graph_stats().nodes, 65 + 35);
+}
+
+#[test]
+fn fused_neuron_grads_match_the_unfused_chain() {
+    let mut rng = StdRng::seed_from_u64(6);
+    let n = Neuron::new_with_rng(5, &mut rng);
+    for _ in 0..10 {
+        let x: Vec<Value> = (0..5)
+            .map(|_| Value::new(rng.gen_range(-2.0..=2.0)))
+            .collect();
+        n.zero_grad();
+        let fused = n.apply(&x);
+        fused.backward();
+        let fused_grads: Vec<Scalar> = n.get_parameters().iter().map(Value::grad).collect();
+
+        // the same association built from plain products and sums
+        let mut s = n.bias().unwrap().clone();
+        for (xi, wi) in x.iter().zip(n.weights()) {
+            s = &(xi * wi) + &s;
+        }
+        let unfused = s.tanh();
+        unfused.backward();
+        let unfused_grads: Vec<Scalar> = n.get_parameters().iter().map(Value::grad).collect();
+
+        assert_eq!(fused.data().to_bits(), unfused.data().to_bits());
+        assert_eq!(fused_grads, unfused_grads);
+    }
+}
+
+#[test]
+fn loss_graph_has_one_tanh_per_neuron() {
+    let mlp = MLP::new_with_seed(3, &[4, 4, 1], 2);
+    let x: Vec<Value> = [0.5, -1.0, 2.0].iter().map(|&v| Value::new(v)).collect();
+    let loss = mse(&mlp.apply(&x), &[Value::new(1.0)]);
+    // 3 inputs, 41 parameters, the target and mse's count; one multiply-add per weight,
+    // then the squared error over the count
+    assert_graph_budget(
+        &loss,
+        GraphBudget {
+            max_nodes: Some(46 + 32 + 9 + 3),
+            max_per_op: vec![("mul_add", 12 + 16 + 4), ("tanh", 4 + 4 + 1), ("none", 46)],
+            max_depth: Some(17),
+        },
+    );
+    let tanh_nodes = loss
+        .iter_graph()
+        .filter(|v| matches!(*v.op(), Op::Tanh(_)))
+        .count();
+    assert_eq!(tanh_nodes, 4 + 4 + 1);
+
+    // every parameter takes part in the loss
+    let ids: std::collections::HashSet<usize> = loss.iter_graph().map(|v| v.id()).collect();
+    assert!(mlp.get_parameters().iter().all(|p| ids.contains(&p.id())));
+}
+
+#[test]
+fn builder_matches_mlp_new() {
+    let built = MlpBuilder::new(2)
+        .layer(3, Activation::Tanh)
+        .layer(1, Activation::Tanh)
+        .seed(7)
+        .build()
+        .unwrap();
+    let direct = MLP::new_with_seed(2, &[3, 1], 7);
+    assert_eq!(built.sizes(), vec![2, 3, 1]);
+    assert_eq!(built.num_parameters(), 3 * 3 + 4);
+    let data = |m: &MLP| {
+        m.get_parameters()
+            .iter()
+            .map(Value::data)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(data(&built), data(&direct));
+    let labels = |m: &MLP| {
+        m.get_parameters()
+            .iter()
+            .map(|p| p.label())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(labels(&built), labels(&direct));
+}
+
+#[test]
+fn builder_applies_per_layer_config() {
+    let mlp = MlpBuilder::new(4)
+        .layer(8, Activation::ReLU)
+        .dropout(0.25)
+        .layer_with(
+            6,
+            LayerConfig {
+                activation: Activation::Sigmoid,
+                init: Init::HeNormal,
+                ..Default::default()
+            },
+        )
+        .layer(1, Activation::Linear)
+        .seed(1)
+        .build()
+        .unwrap();
+    let layers = mlp.layers();
+    assert_eq!(layers[0].neurons()[0].activation(), Activation::ReLU);
+    assert_eq!(layers[0].dropout().map(Dropout::p), Some(0.25));
+    assert!(layers[1].dropout().is_none());
+    assert_eq!(layers[1].neurons()[0].activation(), Activation::Sigmoid);
+    // He init starts the biases at zero
+    assert!(layers[1].biases().iter().all(|b| b.data() == 0.0));
+    assert_eq!(layers[2].neurons()[0].activation(), Activation::Linear);
+}
+
+#[test]
+fn builder_rejects_invalid_configs() {
+    assert_eq!(
+        MlpBuilder::new(2).build().unwrap_err(),
+        BuildError::NoLayers
+    );
+    assert_eq!(
+        MlpBuilder::new(0)
+            .layer(1, Activation::Tanh)
+            .build()
+            .unwrap_err(),
+        BuildError::NoInputs
+    );
+    let err = MlpBuilder::new(2)
+        .layer(3, Activation::Tanh)
+        .layer(0, Activation::Tanh)
+        .build()
+        .unwrap_err();
+    assert_eq!(err, BuildError::ZeroWidth { layer: 1 });
+    assert_eq!(err.to_string(), "layer 1 has no neurons");
+    assert_eq!(
+        MlpBuilder::new(2)
+            .dropout(0.5)
+            .layer(1, Activation::Tanh)
+            .build()
+            .unwrap_err(),
+        BuildError::DropoutWithoutLayer
+    );
+    assert_eq!(
+        MlpBuilder::new(2)
+            .layer(3, Activation::Tanh)
+            .dropout(1.0)
+            .build()
+            .unwrap_err(),
+        BuildError::DropoutProbability { layer: 0, p: 1.0 }
+    );
+}
+
+#[test]
+fn apply_scalars_matches_apply() {
+    let mlp = MLP::new_with_seed(3, &[4, 2], 8);
+    let x = [0.3, -0.6, 1.2];
+    let manual: Vec<Scalar> = mlp
+        .apply(&x.iter().map(|&v| Value::new(v)).collect::<Vec<_>>())
+        .iter()
+        .map(Value::data)
+        .collect();
+    let short: Vec<Scalar> = mlp.apply_scalars(&x).iter().map(Value::data).collect();
+    assert_eq!(manual, short);
+
+    mse(&mlp.apply_scalars(&x), &micrograd::values![1.0, -1.0]).backward();
+    assert!(mlp.get_parameters().iter().all(|p| p.grad() != 0.0));
+}
+
+#[test]
+fn bias_free_neuron_maps_zero_to_zero() {
+    let n = Neuron::new_no_bias(3);
+    assert!(n.bias().is_none());
+    assert_eq!(n.num_parameters(), 3);
+    assert_eq!(n.get_parameters().len(), 3);
+
+    let y = n.apply(&Value::from_slice(&[0.0, 0.0, 0.0]));
+    assert_eq!(y.data(), 0.0);
+    assert_eq!(n.predict(&[0.0, 0.0, 0.0]), 0.0);
+}
+
+#[test]
+fn bias_free_neuron_grads_reach_the_weights() {
+    let n = Neuron::new_no_bias(2);
+    let xs: [Scalar; 2] = [0.5, -1.5];
+    let y = n.apply(&xs.map(Value::new));
+    y.backward();
+    let t = y.data();
+    for (w, x) in n.weights().iter().zip(xs) {
+        assert!((w.grad() - (1.0 - t * t) * x).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn bias_free_layers_drop_nout_parameters() {
+    let build = |bias: bool| {
+        MlpBuilder::new(3)
+            .layer_with(
+                4,
+                LayerConfig {
+                    bias,
+                    ..Default::default()
+                },
+            )
+            .layer(2, Activation::Linear)
+            .seed(5)
+            .build()
+            .unwrap()
+    };
+    let (with, without) = (build(true), build(false));
+    assert_eq!(with.num_parameters() - without.num_parameters(), 4);
+    assert!(without.layers()[0].biases().is_empty());
+    assert_eq!(without.biases().len(), 2);
+    // the dropped biases are still drawn, so the weights match
+    let weights = |m: &MLP| -> Vec<Scalar> { m.weights().iter().map(Value::data).collect() };
+    assert_eq!(weights(&with), weights(&without));
+
+    let layer = Layer::new(3, 5).without_bias();
+    assert_eq!(layer.num_parameters(), 15);
+}
+
+#[test]
+fn uniform_init_stays_in_range() {
+    let layer = Layer::new_uniform(200, 50, -0.05, 0.1);
+    let params = layer.get_parameters();
+    assert_eq!(params.len(), 50 * 201);
+    assert!(params.iter().all(|p| (-0.05..=0.1).contains(&p.data())));
+
+    let n = Neuron::new_uniform(1000, 2.0, 3.0);
+    assert!(n
+        .get_parameters()
+        .iter()
+        .all(|p| (2.0..=3.0).contains(&p.data())));
+
+    let mlp = MLP::new_uniform(8, &[16, 1], -0.5, 0.5);
+    assert!(mlp.get_parameters().iter().all(|p| p.data().abs() <= 0.5));
+}
+
+#[test]
+fn normal_init_has_the_requested_moments() {
+    let init = Init::Normal {
+        mean: 0.5,
+        std: 2.0,
+    };
+    let layer = Layer::new_with_init(100, 100, init, &mut StdRng::seed_from_u64(6));
+    let xs: Vec<Scalar> = layer.get_parameters().iter().map(Value::data).collect();
+    let n = xs.len() as Scalar;
+    let mean = xs.iter().sum::<Scalar>() / n;
+    let std = (xs.iter().map(|x| (x - mean).powi(2)).sum::<Scalar>() / n).sqrt();
+    assert!((mean - 0.5).abs() < 0.1, "mean {}", mean);
+    assert!((std - 2.0).abs() < 0.1, "std {}", std);
+
+    let n = Neuron::new_normal(4, 0.0, 0.01);
+    assert!(n.get_parameters().iter().all(|p| p.data().abs() < 0.1));
+}
+
+#[test]
+#[should_panic(expected = "uniform init needs lo < hi, got [1, 1]")]
+fn uniform_init_rejects_an_empty_range() {
+    Layer::new_uniform(4, 2, 1.0, 1.0);
+}
+
+#[test]
+#[should_panic(expected = "normal init needs a positive, finite std, got -1")]
+fn normal_init_rejects_a_negative_std() {
+    MLP::new_normal(4, &[2], 0.0, -1.0);
+}
+
+#[test]
+fn builder_rejects_invalid_init() {
+    let err = MlpBuilder::new(2)
+        .layer(3, Activation::Tanh)
+        .layer_with(
+            1,
+            LayerConfig {
+                init: Init::Uniform { lo: 1.0, hi: 0.0 },
+                ..Default::default()
+            },
+        )
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, BuildError::InvalidInit { layer: 1, .. }));
+    assert_eq!(
+        err.to_string(),
+        "layer 1: uniform init needs lo < hi, got [1, 0]"
+    );
+}
+
+#[test]
+fn grad_stats_show_vanishing_gradients() {
+    let init = Init::Uniform { lo: -0.5, hi: 0.5 };
+    let mlp = MLP::new_with_init(8, &[8, 8, 8, 8, 1], init, &mut StdRng::seed_from_u64(19));
+    let out = mlp
+        .apply_scalars(&[1.0, -1.0, 1.0, -1.0, 0.5, -0.5, 0.5, -0.5])
+        .pop()
+        .unwrap();
+    out.backward();
+    let grads: Vec<Scalar> = mlp.get_parameters().iter().map(|p| p.grad()).collect();
+
+    let stats = mlp.grad_stats();
+    assert_eq!(stats.layers.len(), 5);
+    let means: Vec<Scalar> = stats.layers.iter().map(|l| l.mean).collect();
+    assert!(means.windows(2).all(|w| w[0] < w[1]), "{:?}", means);
+    for l in &stats.layers {
+        assert!(0.0 <= l.min && l.min <= l.mean && l.mean <= l.max);
+    }
+
+    let shown = stats.to_string();
+    assert_eq!(shown.lines().count(), 5);
+    assert!(shown.starts_with("layer 0: |grad| min "));
+
+    assert_eq!(
+        mlp.get_parameters()
+            .iter()
+            .map(|p| p.grad())
+            .collect::<Vec<_>>(),
+        grads
+    );
+}
+
+#[test]
+fn input_gradients_match_finite_differences() {
+    let mlp = MLP::new_with_seed(3, &[4, 1], 8);
+    let x = [0.4, -0.7, 1.1];
+    let grads = mlp.input_gradients(&x);
+    assert_eq!(grads.len(), 3);
+
+    let h = 1e-2;
+    for i in 0..3 {
+        let (mut up, mut down) = (x, x);
+        up[i] += h;
+        down[i] -= h;
+        let numeric = (mlp.predict(&up)[0] - mlp.predict(&down)[0]) / (2.0 * h);
+        assert!(
+            (grads[i] - numeric).abs() < 1e-3,
+            "{} vs {}",
+            grads[i],
+            numeric
+        );
+    }
+    assert!(mlp.get_parameters().iter().all(|p| p.grad() == 0.0));
+}
+
+#[test]
+#[should_panic(expected = "input gradients need a single output, the network has 2")]
+fn input_gradients_need_a_single_output() {
+    MLP::new(2, &[3, 2]).input_gradients(&[0.0, 1.0]);
+}
+
+#[test]
+fn loss_graph_tags_neuron_dot_products_and_activations() {
+    let mlp =
+        MLP::new_with_seed(2, &[3, 1], 0).with_activations(&[Activation::Tanh, Activation::Tanh]);
+    let xs = [
+        Value::from_slice(&[0.5, -1.0]),
+        Value::from_slice(&[1.0, 2.0]),
+    ];
+    let ys = Value::from_slice(&[1.0, -1.0]);
+    let loss = with_tag("loss", || {
+        let preds: Vec<Value> = xs.iter().map(|x| mlp.apply(x)[0].clone()).collect();
+        mse(&preds, &ys)
+    });
+
+    // per row, one mul_add per weight (2 * 3 + 3 * 1) and one tanh per neuron (3 + 1);
+    // the 13 parameters, 4 inputs and 2 targets were built outside the scope, and the
+    // loss itself is 2 subs, 2 squares, their sum, and a division by a leaf holding 2
+    assert_eq!(
+        loss.bloat_report(),
+        vec![
+            ("untagged".to_string(), 19),
+            ("neuron.dot".to_string(), 2 * 9),
+            ("neuron.activation".to_string(), 2 * 4),
+            ("loss".to_string(), 7),
+        ]
+    );
+
+    // without a scope nothing is tagged
+    let untracked = mse(&mlp.apply(&xs[0]), &ys[..1]);
+    assert_eq!(untracked.bloat_report().len(), 1);
+}
@@ -0,0 +1,328 @@
+use micrograd::losses::mse;
+use micrograd::optim::{
+    clip_grad_norm, clip_grad_value, grad_norm, CosineAnnealing, ExponentialDecay, LrScheduler,
+    Sgd, StepDecay,
+};
+use micrograd::{consts, Scalar, Value, MLP};
+
+/// Fixed 2 -> 3 -> 1 tanh network, so runs can be compared from the same starting point
+struct Net {
+    hidden: Vec<Vec<Value>>,
+    output: Vec<Value>,
+}
+
+impl Net {
+    fn new() -> Self {
+        let hidden = [[0.3, -0.2, 0.1], [0.5, 0.4, -0.3], [-0.6, 0.2, 0.2]];
+        Net {
+            hidden: hidden
+                .iter()
+                .map(|n| n.iter().map(|&w| Value::new(w)).collect())
+                .collect(),
+            output: [0.4, -0.3, 0.6, 0.0]
+                .iter()
+                .map(|&w| Value::new(w))
+                .collect(),
+        }
+    }
+
+    fn params(&self) -> Vec<Value> {
+        self.hidden
+            .iter()
+            .flatten()
+            .chain(&self.output)
+            .cloned()
+            .collect()
+    }
+
+    fn loss(&self, x: [Scalar; 2], target: Scalar) -> Value {
+        let mut y = self.output[3].clone();
+        for (n, w) in self.hidden.iter().zip(&self.output) {
+            let a = (&n[0] * x[0] + &n[1] * x[1] + &n[2]).tanh();
+            y += a * w;
+        }
+        (y.tanh() - target).pow(2.0)
+    }
+}
+
+/// Iterations until the loss drops below 1e-4
+fn iterations_to_fit(optim: &mut Sgd, net: &Net) -> usize {
+    for i in 0..1000 {
+        let loss = net.loss([0.5, -1.0], 0.8);
+        if loss.data() < 1e-4 {
+            return i;
+        }
+        optim.zero_grad();
+        loss.backward();
+        optim.step();
+    }
+    panic!("did not converge");
+}
+
+#[test]
+fn sgd_step_follows_the_gradient() {
+    let p = Value::new(1.0);
+    let mut sgd = Sgd::new(vec![p.clone()], 0.1);
+    (&p * 3.0).backward();
+    sgd.step();
+    assert!((p.data() - 0.7).abs() < 1e-6);
+    sgd.zero_grad();
+    assert_eq!(p.grad(), 0.0);
+}
+
+#[test]
+fn momentum_accumulates_velocity() {
+    let p = Value::new(0.0);
+    let mut sgd = Sgd::with_momentum(vec![p.clone()], 1.0, 0.5);
+    // constant grad of 1: velocities 1, 1.5, 1.75
+    for _ in 0..3 {
+        sgd.zero_grad();
+        (&p + 0.0).backward();
+        sgd.step();
+    }
+    assert!((p.data() + 4.25).abs() < 1e-6);
+}
+
+#[test]
+fn momentum_converges_in_fewer_iterations() {
+    let net = Net::new();
+    let plain = iterations_to_fit(&mut Sgd::new(net.params(), 0.05), &net);
+
+    let net = Net::new();
+    let with_momentum = iterations_to_fit(&mut Sgd::with_momentum(net.params(), 0.05, 0.7), &net);
+    assert!(
+        with_momentum < plain,
+        "momentum took {} iterations, plain {}",
+        with_momentum,
+        plain
+    );
+}
+
+#[test]
+fn weight_decay_shrinks_parameters() {
+    let p = Value::new(2.0);
+    let mut sgd = Sgd::new(vec![p.clone()], 0.1);
+    sgd.set_weight_decay(0.5);
+    // zero grad: only the decay moves p
+    (&p * 0.0).backward();
+    sgd.step();
+    assert!((p.data() - 1.9).abs() < 1e-6);
+}
+
+/// L2 norm of the weights after fitting a few points, decaying the weights but not the
+/// biases
+fn weight_norm_after_training(weight_decay: Scalar) -> Scalar {
+    let mlp = MLP::new_with_seed(2, &[8, 1], 3);
+    let xs = [[0.5, -1.0], [0.1, 0.2], [-0.7, 0.9], [1.0, 1.0]];
+    let inputs: Vec<Vec<Value>> = xs
+        .iter()
+        .map(|x| x.iter().map(|&xi| Value::new(xi)).collect())
+        .collect();
+    let targets: Vec<Value> = [0.3, -0.2, 0.5, 0.0]
+        .iter()
+        .map(|&t| Value::new(t))
+        .collect();
+    let mut weights = Sgd::new(mlp.weights(), 0.1);
+    weights.set_weight_decay(weight_decay);
+    let mut biases = Sgd::new(mlp.biases(), 0.1);
+    for _ in 0..100 {
+        let preds: Vec<Value> = inputs.iter().map(|x| mlp.apply(x).pop().unwrap()).collect();
+        mse(&preds, &targets).backward();
+        weights.step();
+        biases.step();
+    }
+    mlp.weights()
+        .iter()
+        .map(|w| w.data() * w.data())
+        .sum::<Scalar>()
+        .sqrt()
+}
+
+#[test]
+fn weight_decay_gives_smaller_weights() {
+    let plain = weight_norm_after_training(0.0);
+    let decayed = weight_norm_after_training(0.5);
+    assert!(decayed < plain * 0.9, "decayed {} plain {}", decayed, plain);
+}
+
+#[test]
+fn accumulated_micro_batches_match_the_mean_loss() {
+    let xs = [[0.5, -1.0], [0.1, 0.2], [-0.7, 0.9], [1.0, 1.0]];
+    let ys = [0.3, -0.2, 0.5, 0.0];
+    let sample_loss = |mlp: &MLP, i: usize| {
+        let x: Vec<Value> = xs[i].iter().map(|&xi| Value::new(xi)).collect();
+        (mlp.apply(&x).pop().unwrap() - ys[i]).pow(2.0)
+    };
+
+    let full = MLP::new_with_seed(2, &[3, 1], 6);
+    let mut optim = Sgd::new(full.get_parameters(), 0.1);
+    let losses: Vec<Value> = (0..4).map(|i| sample_loss(&full, i)).collect();
+    (Value::sum(&losses) / 4.0).backward();
+    optim.step();
+
+    let accumulated = MLP::new_with_seed(2, &[3, 1], 6);
+    let mut optim = Sgd::new(accumulated.get_parameters(), 0.1);
+    for i in 0..4 {
+        sample_loss(&accumulated, i).backward_accumulate();
+    }
+    optim.step_accumulated(4);
+
+    for (a, b) in full
+        .get_parameters()
+        .iter()
+        .zip(accumulated.get_parameters())
+    {
+        assert!((a.data() - b.data()).abs() < 1e-6);
+        assert_eq!(b.grad(), 0.0);
+    }
+}
+
+#[test]
+fn accumulated_losses_over_a_shared_graph_match_one_backward_on_their_sum() {
+    let ys = [0.3, -0.2, 0.5];
+    let x = [Value::new(0.5), Value::new(-1.0)];
+    let losses = |mlp: &MLP| {
+        // one forward pass feeds every loss, so its nodes are shared between them
+        let out = mlp.apply(&x).pop().unwrap();
+        ys.iter().map(|&y| (&out - y).pow(2.0)).collect::<Vec<_>>()
+    };
+
+    let full = MLP::new_with_seed(2, &[3, 1], 6);
+    let mut optim = Sgd::new(full.get_parameters(), 0.1);
+    (Value::sum(&losses(&full)) / 3.0).backward();
+    optim.step();
+
+    let accumulated = MLP::new_with_seed(2, &[3, 1], 6);
+    let mut optim = Sgd::new(accumulated.get_parameters(), 0.1);
+    for loss in losses(&accumulated) {
+        loss.backward_accumulate();
+    }
+    optim.step_accumulated(3);
+
+    for (a, b) in full
+        .get_parameters()
+        .iter()
+        .zip(accumulated.get_parameters())
+    {
+        assert!((a.data() - b.data()).abs() < 1e-6);
+    }
+}
+
+fn lr_sequence(scheduler: &mut impl LrScheduler, epochs: usize) -> Vec<Scalar> {
+    (0..epochs)
+        .map(|_| {
+            let lr = scheduler.lr();
+            scheduler.step_epoch();
+            lr
+        })
+        .collect()
+}
+
+fn assert_close(actual: &[Scalar], expected: &[Scalar]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-6, "{:?} vs {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn step_decay_sequence() {
+    let expected: Vec<Scalar> = (0..10).map(|e| 0.1 * Scalar::powi(0.5, e / 3)).collect();
+    assert_close(
+        &lr_sequence(&mut StepDecay::new(0.1, 0.5, 3), 10),
+        &expected,
+    );
+}
+
+#[test]
+fn exponential_decay_sequence() {
+    let expected: Vec<Scalar> = (0..10).map(|e| 0.1 * Scalar::powi(0.9, e)).collect();
+    assert_close(
+        &lr_sequence(&mut ExponentialDecay::new(0.1, 0.9), 10),
+        &expected,
+    );
+}
+
+#[test]
+fn cosine_annealing_sequence() {
+    let expected: Vec<Scalar> = (0..10)
+        .map(|e| {
+            let t = (e.min(8) as Scalar) / 8.0;
+            0.01 + 0.09 * (1.0 + (consts::PI * t).cos()) / 2.0
+        })
+        .collect();
+    let lrs = lr_sequence(&mut CosineAnnealing::new(0.1, 0.01, 8), 10);
+    assert_close(&lrs, &expected);
+    assert!((lrs[0] - 0.1).abs() < 1e-6);
+    assert!((lrs[9] - 0.01).abs() < 1e-6);
+}
+
+/// Final loss of a quadratic bowl after `epochs` epochs of 5 steps each. At lr 1.0 plain
+/// gradient descent bounces between the walls forever.
+fn train(scheduler: &mut impl LrScheduler, epochs: usize) -> Scalar {
+    let (p, q) = (Value::new(0.0), Value::new(0.0));
+    let loss = || (&p - 3.0).pow(2.0) + (&q + 1.0).pow(2.0) * 0.5;
+    let mut optim = Sgd::new(vec![p.clone(), q.clone()], scheduler.lr());
+    for _ in 0..epochs {
+        scheduler.apply(&mut optim);
+        for _ in 0..5 {
+            optim.zero_grad();
+            loss().backward();
+            optim.step();
+        }
+        scheduler.step_epoch();
+    }
+    loss().data()
+}
+
+#[test]
+fn decayed_lr_reaches_a_lower_loss() {
+    let constant = train(&mut ExponentialDecay::new(1.0, 1.0), 10);
+    let decayed = train(&mut ExponentialDecay::new(1.0, 0.7), 10);
+    assert!(constant > 1.0, "constant {}", constant);
+    assert!(decayed < 1e-3, "decayed {}", decayed);
+}
+
+/// Leaves whose grads are exactly `grads`
+fn with_grads(grads: &[Scalar]) -> Vec<Value> {
+    let params: Vec<Value> = grads.iter().map(|_| Value::new(1.0)).collect();
+    let mut sum = Value::new(0.0);
+    for (p, g) in params.iter().zip(grads) {
+        sum += p * *g;
+    }
+    sum.backward();
+    params
+}
+
+#[test]
+fn clip_grad_value_clamps_each_grad() {
+    let params = with_grads(&[5.0, -0.5, -3.0]);
+    clip_grad_value(&params, 1.0);
+    let grads: Vec<Scalar> = params.iter().map(Value::grad).collect();
+    assert_eq!(grads, vec![1.0, -0.5, -1.0]);
+}
+
+#[test]
+fn grad_norm_is_the_global_l2_norm() {
+    let params = with_grads(&[3.0, 4.0]);
+    assert_eq!(grad_norm(&params), 5.0);
+    assert_eq!(params[0].grad(), 3.0);
+    assert_eq!(grad_norm(&[]), 0.0);
+}
+
+#[test]
+fn clip_grad_norm_rescales_globally() {
+    let params = with_grads(&[3.0, 4.0]);
+    assert_eq!(clip_grad_norm(&params, 1.0), 5.0);
+    assert!((params[0].grad() - 0.6).abs() < 1e-6);
+    assert!((params[1].grad() - 0.8).abs() < 1e-6);
+
+    // already within the limit
+    let params = with_grads(&[3.0, 4.0]);
+    assert_eq!(clip_grad_norm(&params, 10.0), 5.0);
+    assert_eq!(params[0].grad(), 3.0);
+
+    let params = with_grads(&[0.0, 0.0]);
+    assert_eq!(clip_grad_norm(&params, 1.0), 0.0);
+    assert!(params.iter().all(|p| p.grad() == 0.0));
+}
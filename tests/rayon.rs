@@ -0,0 +1,94 @@
+#![cfg(feature = "rayon")]
+
+use std::time::Instant;
+
+use micrograd::{no_grad, with_tag, Layer, Scalar, Value, MLP};
+
+fn inputs(n: usize, offset: Scalar) -> Vec<Value> {
+    Value::from_slice(
+        &(0..n)
+            .map(|i| ((i as Scalar) * 0.37 + offset).sin())
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn bits(xs: &[Value], of: impl Fn(&Value) -> Scalar) -> Vec<String> {
+    xs.iter()
+        .map(|v| format!("{:?}", of(v).to_bits()))
+        .collect()
+}
+
+/// The same network twice, one forced parallel everywhere and one forced serial
+fn twins(nin: usize, nouts: &[usize]) -> (MLP, MLP) {
+    (
+        MLP::new_with_seed(nin, nouts, 7).with_parallel_threshold(1),
+        MLP::new_with_seed(nin, nouts, 7).with_parallel_threshold(usize::MAX),
+    )
+}
+
+#[test]
+fn parallel_apply_matches_serial_data_and_grads() {
+    let (parallel, serial) = twins(6, &[40, 40, 3]);
+    let run = |mlp: &MLP| {
+        let out = mlp.apply(&inputs(6, 0.0));
+        let loss = Value::sum(&out.iter().map(|y| y * y).collect::<Vec<_>>());
+        loss.backward();
+        (out, loss)
+    };
+    let (out_p, loss_p) = run(&parallel);
+    let (out_s, loss_s) = run(&serial);
+    assert_eq!(bits(&out_p, Value::data), bits(&out_s, Value::data));
+    assert_eq!(loss_p.graph_stats(), loss_s.graph_stats());
+    assert_eq!(
+        bits(&parallel.get_parameters(), Value::grad),
+        bits(&serial.get_parameters(), Value::grad)
+    );
+}
+
+#[test]
+fn parallel_batches_and_predict_match_serial() {
+    let (parallel, serial) = twins(4, &[8, 2]);
+    let xs: Vec<Vec<Value>> = (0..50).map(|i| inputs(4, i as Scalar)).collect();
+    let batch_p = parallel.apply_batch(&xs);
+    let batch_s = serial.apply_batch(&xs);
+    for (p, s) in batch_p.iter().zip(&batch_s) {
+        assert_eq!(bits(p, Value::data), bits(s, Value::data));
+    }
+
+    let x: Vec<Scalar> = inputs(4, 0.5).iter().map(Value::data).collect();
+    assert_eq!(parallel.predict(&x), serial.predict(&x));
+}
+
+#[test]
+fn workers_follow_no_grad_and_tags() {
+    let layer = Layer::new(3, 64).with_parallel_threshold(1);
+    let x = inputs(3, 0.0);
+    assert!(no_grad(|| layer.apply(&x)).iter().all(Value::is_leaf));
+
+    // inside a scope, the neurons tag their nodes by site
+    let out = with_tag("hidden", || layer.apply(&x));
+    assert!(out.iter().all(|y| y.tag() == Some("neuron.activation")));
+    assert!(layer.apply(&x).iter().all(|y| y.tag().is_none()));
+}
+
+#[test]
+#[ignore = "timing; run with --ignored --nocapture on a multi-core machine"]
+fn bench_wide_layer() {
+    let x = inputs(128, 0.0);
+    let time = |threshold: usize| {
+        let layer = Layer::new(128, 128).with_parallel_threshold(threshold);
+        let start = Instant::now();
+        for _ in 0..20 {
+            layer.apply(&x);
+        }
+        start.elapsed()
+    };
+    let (serial, parallel) = (time(usize::MAX), time(1));
+    println!(
+        "128x128 layer, 20 forward passes: serial {:?}, parallel {:?}",
+        serial, parallel
+    );
+    if std::thread::available_parallelism().map_or(1, usize::from) > 1 {
+        assert!(parallel < serial);
+    }
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "sync")]
+
+use std::sync::Arc;
+
+use micrograd::{Scalar, Value, MLP};
+
+/// Input grads of `mlp` at `x`; they depend on the weights' data, not on their grads.
+/// The weights' own grads are written by every thread at once, so they are not checked.
+fn input_grads(mlp: &MLP, x: [Scalar; 2]) -> Vec<Scalar> {
+    let inputs = [Value::new(x[0]), Value::new(x[1])];
+    let out = mlp.apply(&inputs).pop().unwrap();
+    (&out * &out).backward();
+    inputs.iter().map(Value::grad).collect()
+}
+
+#[test]
+fn mlp_is_shared_across_threads() {
+    let mlp = Arc::new(MLP::new(2, &[3, 1]));
+    let xs = [[0.5, -1.0], [0.1, 0.2], [-0.7, 0.9], [1.0, 1.0]];
+    let expected: Vec<_> = xs.iter().map(|&x| input_grads(&mlp, x)).collect();
+
+    let handles: Vec<_> = xs
+        .iter()
+        .map(|&x| {
+            let mlp = Arc::clone(&mlp);
+            std::thread::spawn(move || input_grads(&mlp, x))
+        })
+        .collect();
+    for (handle, expected) in handles.into_iter().zip(expected) {
+        assert_eq!(handle.join().unwrap(), expected);
+    }
+}
+
+#[test]
+fn backward_through_a_node_used_twice() {
+    let a = Value::new(3.0);
+    let y = &a * &a;
+    y.backward();
+    assert_eq!(a.grad(), 6.0);
+}
@@ -0,0 +1,154 @@
+use micrograd::testing::{assert_graph_budget, check_gradients, check_graph_budget, GraphBudget};
+use micrograd::{Scalar, Value};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// (x0 * w0 + x1 * w1) + b: 5 leaves, 2 muls, 2 adds, depth 3
+fn weighted_sum() -> Value {
+    let terms = (0..2).map(|i| Value::new(i as Scalar) * Value::new(0.5));
+    let dot = terms.reduce(|acc, t| acc + t).unwrap();
+    dot + Value::new(1.0)
+}
+
+#[test]
+fn within_budget() {
+    assert_graph_budget(
+        &weighted_sum(),
+        GraphBudget {
+            max_nodes: Some(9),
+            max_per_op: vec![("mul", 2), ("plus", 2), ("none", 5)],
+            max_depth: Some(3),
+        },
+    );
+}
+
+#[test]
+fn a_budget_at_the_graph_stats_passes() {
+    let root = weighted_sum();
+    let stats = root.graph_stats();
+    let at = GraphBudget {
+        max_nodes: Some(stats.nodes),
+        max_per_op: stats.ops.iter().map(|(&op, &n)| (op, n)).collect(),
+        max_depth: Some(stats.depth),
+    };
+    assert_eq!(check_graph_budget(&root, &at), Ok(()));
+    let tighter = GraphBudget {
+        max_depth: Some(stats.depth - 1),
+        ..at
+    };
+    assert!(check_graph_budget(&root, &tighter).is_err());
+}
+
+#[test]
+fn over_budget_report_lists_every_category() {
+    let report = check_graph_budget(
+        &weighted_sum(),
+        &GraphBudget {
+            max_nodes: Some(7),
+            max_per_op: vec![("mul", 2), ("plus", 1)],
+            max_depth: Some(10),
+        },
+    )
+    .unwrap_err();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0], "graph budget exceeded:");
+    assert!(lines[1].contains("nodes") && lines[1].contains("actual      9"));
+    assert!(lines[1].ends_with("over budget"));
+    assert!(lines[2].contains("op mul") && !lines[2].ends_with("over budget"));
+    assert!(lines[3].contains("op plus") && lines[3].contains("allowed      1"));
+    assert!(lines[3].ends_with("over budget"));
+    assert!(lines[4].contains("depth") && lines[4].contains("actual      3"));
+    assert_eq!(lines.len(), 5);
+}
+
+#[test]
+#[should_panic(expected = "graph budget exceeded")]
+fn assert_panics_when_over() {
+    assert_graph_budget(
+        &weighted_sum(),
+        GraphBudget {
+            max_depth: Some(2),
+            ..Default::default()
+        },
+    );
+}
+
+#[test]
+fn check_gradients_accepts_correct_grads() {
+    let build = |x: &[Value]| (&x[0] * &x[1]).tanh() + x[2].exp() / &x[0];
+    assert_eq!(
+        check_gradients(build, &[0.4, -1.3, 0.2], 1e-3, 1e-2),
+        Ok(())
+    );
+}
+
+#[test]
+fn check_gradients_evaluates_at_exactly_x_plus_and_minus_eps() {
+    let inputs = [0.1, 0.7, -2.3];
+    let eps = 1e-3;
+    let seen = std::cell::RefCell::new(vec![]);
+    let build = |x: &[Value]| {
+        seen.borrow_mut()
+            .push(x.iter().map(Value::data).collect::<Vec<_>>());
+        &x[0] + &x[1] + &x[2]
+    };
+    check_gradients(build, &inputs, eps, 1e-2).unwrap();
+
+    // the backward pass, then a plus and a minus evaluation per input
+    let seen = seen.into_inner();
+    assert_eq!(seen.len(), 1 + 2 * inputs.len());
+    assert_eq!(seen[0], inputs);
+    for (i, pair) in seen[1..].chunks(2).enumerate() {
+        for (sign, point) in [1.0, -1.0].iter().zip(pair) {
+            let mut expected = inputs.to_vec();
+            expected[i] = inputs[i] + sign * eps;
+            assert_eq!(point, &expected, "input {}", i);
+        }
+    }
+}
+
+#[test]
+fn check_gradients_reports_the_diverging_input() {
+    // detaching hides the dependency from backward but not from the forward pass
+    let build = |x: &[Value]| &x[0] * 2.0 + (&x[1] * 3.0).detach();
+    let err = check_gradients(build, &[1.0, 1.0], 1e-2, 1e-2).unwrap_err();
+    assert_eq!(err.index, 1);
+    assert_eq!(err.analytic, 0.0);
+    assert!((err.numeric - 3.0).abs() < 1e-2);
+    assert!(err.to_string().starts_with("gradient of input 1 diverged"));
+}
+
+/// A random expression over `leaves`, `depth` ops deep, using every smooth op
+fn random_expression(rng: &mut StdRng, leaves: &[Value], depth: usize) -> Value {
+    if depth == 0 {
+        return leaves[rng.gen_range(0..leaves.len())].clone();
+    }
+    let a = random_expression(rng, leaves, depth - 1);
+    match rng.gen_range(0..11) {
+        0 => &a + &random_expression(rng, leaves, depth - 1),
+        1 => &a - &random_expression(rng, leaves, depth - 1),
+        2 => &a * &random_expression(rng, leaves, depth - 1),
+        // denominators and logs are kept away from zero
+        3 => &a / &(random_expression(rng, leaves, depth - 1).pow(2.0) + 1.0),
+        4 => (a.pow(2.0) + 1.0).ln(),
+        5 => a.pow(3.0),
+        6 => a.tanh(),
+        7 => (&a * 0.5).exp(),
+        8 => a.sigmoid(),
+        9 => -&a,
+        _ => &a * 2.0 - 1.0,
+    }
+}
+
+#[test]
+fn random_expressions_pass_the_gradient_check() {
+    let mut rng = StdRng::seed_from_u64(7);
+    for case in 0..50 {
+        let seed: u64 = rng.gen();
+        let inputs: Vec<Scalar> = (0..3).map(|_| rng.gen_range(-1.0..1.0)).collect();
+        let build = |x: &[Value]| random_expression(&mut StdRng::seed_from_u64(seed), x, 3);
+        if let Err(err) = check_gradients(build, &inputs, 1e-2, 5e-2) {
+            panic!("case {} at {:?}: {}", case, inputs, err);
+        }
+    }
+}
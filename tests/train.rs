@@ -0,0 +1,293 @@
+use std::cell::RefCell;
+use std::ops::ControlFlow;
+use std::rc::Rc;
+
+use micrograd::data::LastBatch;
+use micrograd::losses::{l2_penalty, mse, CompositeLoss};
+use micrograd::train::{
+    Callback, CheckpointSaver, EarlyStopping, FitConfig, Loss, LossLogger, StopReason,
+};
+use micrograd::{Activation, Scalar, Value, MLP};
+
+fn xor() -> (Vec<Vec<Scalar>>, Vec<Vec<Scalar>>) {
+    let xs = vec![
+        vec![0.0, 0.0],
+        vec![0.0, 1.0],
+        vec![1.0, 0.0],
+        vec![1.0, 1.0],
+    ];
+    let ys = vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]];
+    (xs, ys)
+}
+
+#[test]
+fn fit_learns_xor() {
+    let (xs, ys) = xor();
+    let mlp = MLP::new_with_seed(2, &[4, 1], 1);
+    let report = mlp.fit(&xs, &ys, FitConfig::new(0.2, 500));
+    assert_eq!(report.history.len(), 500);
+    assert_eq!(report.stop_reason, StopReason::MaxEpochs);
+    assert!(report.final_loss < 0.05, "loss {}", report.final_loss);
+    assert!(report.history[0] > report.final_loss);
+}
+
+#[test]
+fn fit_stops_at_target_loss() {
+    let (xs, ys) = xor();
+    let mlp = MLP::new_with_seed(2, &[4, 1], 1);
+    let mut config = FitConfig::new(0.2, 500);
+    config.stopping.target_loss = Some(0.1);
+    let report = mlp.fit(&xs, &ys, config);
+    assert_eq!(report.stop_reason, StopReason::Converged);
+
+    // the first epoch under the target is the last one run
+    let first_below = report.history.iter().position(|&l| l < 0.1).unwrap();
+    assert_eq!(report.history.len(), first_below + 1);
+    assert_eq!(report.best_epoch, first_below);
+    assert_eq!(report.best_loss, report.history[first_below]);
+    assert_eq!(report.final_loss, report.best_loss);
+}
+
+#[test]
+fn patience_stops_a_diverging_run_and_restores_the_best() {
+    let (xs, ys) = xor();
+    let mlp = MLP::new_with_seed(2, &[4, 1], 1).into_regressor();
+    let config = FitConfig {
+        stopping: EarlyStopping {
+            max_epochs: 500,
+            patience: Some(5),
+            restore_best: true,
+            ..EarlyStopping::default()
+        },
+        ..FitConfig::new(50.0, 500)
+    };
+    let report = mlp.fit(&xs, &ys, config);
+    assert_eq!(report.stop_reason, StopReason::PatienceExhausted);
+    assert_eq!(report.history.len(), report.best_epoch + 6);
+    assert_eq!(report.final_loss, report.best_loss);
+}
+
+#[test]
+fn fit_with_binary_cross_entropy() {
+    let (xs, ys) = xor();
+    let mlp = MLP::new_with_seed(2, &[4, 1], 1)
+        .with_activations(&[Activation::Tanh, Activation::Sigmoid]);
+    let config = FitConfig {
+        loss: Loss::BinaryCrossEntropy,
+        ..FitConfig::new(0.5, 500)
+    };
+    let report = mlp.fit(&xs, &ys, config);
+    assert!(report.final_loss < 0.1, "loss {}", report.final_loss);
+}
+
+#[test]
+#[should_panic(expected = "got 4 inputs but 3 targets")]
+fn fit_rejects_mismatched_samples() {
+    let (xs, mut ys) = xor();
+    ys.pop();
+    MLP::new(2, &[1]).fit(&xs, &ys, FitConfig::default());
+}
+
+#[test]
+fn fit_a_sine_wave_from_periodic_features() {
+    let xs: Vec<Scalar> = (0..20).map(|i| -3.0 + 6.0 * i as Scalar / 19.0).collect();
+    let features: Vec<Vec<Scalar>> = xs
+        .iter()
+        .map(|&x| {
+            let v = Value::new(x);
+            vec![x, v.sin().data(), v.cos().data()]
+        })
+        .collect();
+    let ys: Vec<Vec<Scalar>> = xs.iter().map(|x| vec![x.sin()]).collect();
+    let mlp = MLP::new_with_seed(3, &[8, 1], 2).into_regressor();
+    let report = mlp.fit(&features, &ys, FitConfig::new(0.05, 500));
+    assert!(report.final_loss < 0.01, "loss {}", report.final_loss);
+}
+
+#[test]
+fn fit_a_sine_wave_with_gelu_hidden_layers() {
+    let xs: Vec<Vec<Scalar>> = (0..20)
+        .map(|i| vec![-3.0 + 6.0 * i as Scalar / 19.0])
+        .collect();
+    let ys: Vec<Vec<Scalar>> = xs.iter().map(|x| vec![x[0].sin()]).collect();
+    let mlp = MLP::new_with_seed(1, &[8, 8, 1], 4).with_activations(&[
+        Activation::Gelu,
+        Activation::Gelu,
+        Activation::Linear,
+    ]);
+    let report = mlp.fit(&xs, &ys, FitConfig::new(0.05, 500));
+    assert!(report.final_loss < 0.02, "loss {}", report.final_loss);
+}
+
+/// Remembers the epochs it saw and breaks at `stop_at`
+struct Recorder {
+    stop_at: Option<usize>,
+    seen: Rc<RefCell<Vec<usize>>>,
+}
+
+impl Callback for Recorder {
+    fn on_epoch_end(&mut self, epoch: usize, _loss: Scalar, _model: &MLP) -> ControlFlow<()> {
+        self.seen.borrow_mut().push(epoch);
+        if Some(epoch) == self.stop_at {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+#[test]
+fn callbacks_fire_once_per_epoch() {
+    let (xs, ys) = xor();
+    let seen = Default::default();
+    let logger = LossLogger::new();
+    let config = FitConfig::new(0.2, 30)
+        .with_callback(Recorder {
+            stop_at: None,
+            seen: Rc::clone(&seen),
+        })
+        .with_callback(logger.clone());
+    let report = MLP::new_with_seed(2, &[4, 1], 1).fit(&xs, &ys, config);
+    assert_eq!(*seen.borrow(), (0..30).collect::<Vec<_>>());
+    assert_eq!(logger.history(), report.history);
+}
+
+#[test]
+fn callback_break_stops_training() {
+    let (xs, ys) = xor();
+    let seen = Default::default();
+    let config = FitConfig::new(0.2, 500).with_callback(Recorder {
+        stop_at: Some(9),
+        seen: Rc::clone(&seen),
+    });
+    let report = MLP::new_with_seed(2, &[4, 1], 1).fit(&xs, &ys, config);
+    assert_eq!(report.stop_reason, StopReason::Callback);
+    assert_eq!(report.history.len(), 10);
+    assert_eq!(seen.borrow().len(), 10);
+}
+
+#[test]
+fn checkpoint_saver_keeps_the_best_weights() {
+    let (xs, ys) = xor();
+    let path = std::env::temp_dir().join(format!("micrograd_saver_{}.bin", std::process::id()));
+    let saver = CheckpointSaver::new(&path);
+    let mlp = MLP::new_with_seed(2, &[4, 1], 1);
+    let report = mlp.fit(
+        &xs,
+        &ys,
+        FitConfig::new(0.2, 50).with_callback(saver.clone()),
+    );
+    assert!(saver.take_error().is_none());
+
+    // plain gradient descent at this rate improves every epoch
+    let saved = saver.saved_epochs();
+    assert_eq!(saved, (0..50).collect::<Vec<_>>());
+
+    let mut restored = MLP::new_with_seed(2, &[4, 1], 2);
+    restored.load_weights(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    // the file holds the parameters of the last epoch's loss, before its step
+    let best = report.history[49];
+    let targets = [0.0, 1.0, 1.0, 0.0];
+    let loss: Scalar = xs
+        .iter()
+        .zip(targets)
+        .map(|(x, t)| {
+            let x: Vec<Value> = x.iter().map(|&v| Value::new(v)).collect();
+            (restored.apply(&x)[0].data() - t).powi(2)
+        })
+        .sum::<Scalar>()
+        / 4.0;
+    assert!((loss - best).abs() < 1e-6, "{} vs {}", loss, best);
+
+    let missing = CheckpointSaver::new("/nonexistent/dir/weights.bin");
+    let report = MLP::new_with_seed(2, &[4, 1], 1).fit(
+        &xs,
+        &ys,
+        FitConfig::new(0.2, 50).with_callback(missing.clone()),
+    );
+    assert_eq!(report.stop_reason, StopReason::Callback);
+    assert!(missing.take_error().is_some());
+    assert!(missing.saved_epochs().is_empty());
+}
+
+#[test]
+fn fit_on_mini_batches() {
+    let (xs, ys) = xor();
+    let mlp = MLP::new_with_seed(2, &[4, 1], 1);
+    let config = FitConfig {
+        batch_size: Some(2),
+        seed: 3,
+        ..FitConfig::new(0.2, 300)
+    };
+    let report = mlp.fit(&xs, &ys, config);
+    assert_eq!(report.history.len(), 300);
+    // two steps per epoch get further than full-batch training in the same epochs
+    let full = MLP::new_with_seed(2, &[4, 1], 1).fit(&xs, &ys, FitConfig::new(0.2, 300));
+    assert!(
+        report.final_loss < full.final_loss,
+        "{} vs {}",
+        report.final_loss,
+        full.final_loss
+    );
+}
+
+#[test]
+fn mini_batch_fit_is_reproducible() {
+    let (xs, ys) = xor();
+    let run = |seed| {
+        let config = FitConfig {
+            batch_size: Some(3),
+            last_batch: LastBatch::Drop,
+            seed,
+            ..FitConfig::new(0.2, 50)
+        };
+        MLP::new_with_seed(2, &[4, 1], 1)
+            .fit(&xs, &ys, config)
+            .history
+    };
+    assert_eq!(run(1), run(1));
+    assert_ne!(run(1), run(2));
+}
+
+#[test]
+fn fit_on_a_composite_loss_logs_every_term() {
+    let (xs, ys) = xor();
+    let config = FitConfig::new(0.2, 100).with_composite_loss(|model, outputs, targets| {
+        let mut loss = CompositeLoss::new();
+        let mut task = Value::new(0.0);
+        for (y, t) in outputs.iter().zip(targets) {
+            task += mse(y, &Value::from_slice(t));
+        }
+        loss.add_term("task", task / outputs.len() as Scalar, 1.0);
+        loss.add_term("l2", l2_penalty(&model.get_parameters(), 1.0), 0.01);
+        loss
+    });
+    let report = MLP::new_with_seed(2, &[4, 1], 1).fit(&xs, &ys, config);
+
+    assert_eq!(report.term_history.len(), report.history.len());
+    for (terms, &total) in report.term_history.iter().zip(&report.history) {
+        let names: Vec<&str> = terms.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["task", "l2"]);
+        let weighted = terms[0].1 + 0.01 * terms[1].1;
+        assert!((weighted - total).abs() < 1e-5, "{} vs {}", weighted, total);
+    }
+    let task = |epoch: usize| report.term_history[epoch][0].1;
+    assert!(task(99) < task(0), "{} vs {}", task(99), task(0));
+
+    // without a composite loss no terms are recorded
+    let plain = MLP::new_with_seed(2, &[4, 1], 1).fit(&xs, &ys, FitConfig::new(0.2, 5));
+    assert!(plain.term_history.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "batch size 5 with LastBatch::Drop leaves no batch of 4 samples")]
+fn fit_rejects_dropping_every_batch() {
+    let (xs, ys) = xor();
+    let config = FitConfig {
+        batch_size: Some(5),
+        last_batch: LastBatch::Drop,
+        ..FitConfig::new(0.2, 10)
+    };
+    MLP::new_with_seed(2, &[4, 1], 1).fit(&xs, &ys, config);
+}
@@ -0,0 +1,1392 @@
+use std::collections::HashMap;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use micrograd::functional::dot;
+use micrograd::losses::{bce_with_logits, binary_cross_entropy};
+use micrograd::optim::Sgd;
+use micrograd::testing::check_gradients;
+use micrograd::{
+    backward_graph, consts, detect_anomaly, no_grad, with_tag, Scalar, UnreachableLeaf, Value,
+};
+
+fn diamond() -> Value {
+    let a = Value::new(2.0);
+    a.set_label("a");
+    let b = &a * &Value::new(3.0);
+    b.set_label("b");
+    let c = &a + &Value::new(1.0);
+    c.set_label("c");
+    let d = b + c;
+    d.set_label("d");
+    d
+}
+
+#[test]
+fn visit_orders_children_first() {
+    let d = diamond();
+    let mut order = vec![];
+    d.visit(&mut order, |order: &mut Vec<String>, v, children| {
+        for c in children {
+            assert!(order.contains(&c.label()) || c.label().is_empty());
+        }
+        order.push(v.label());
+    });
+    // `a` feeds both branches but is only visited once
+    assert_eq!(order, vec!["a", "", "b", "", "c", "d"]);
+
+    let mut reverse = vec![];
+    d.visit_reverse(&mut reverse, |order: &mut Vec<String>, v, _| {
+        order.push(v.label())
+    });
+    assert_eq!(reverse, vec!["d", "c", "", "b", "", "a"]);
+}
+
+#[test]
+fn visit_computes_max_depth() {
+    fn depth(root: &Value) -> usize {
+        let mut depths: HashMap<usize, usize> = HashMap::new();
+        root.visit(&mut depths, |depths, v, children| {
+            let below = children.iter().map(|c| depths[&c.id()]).max();
+            depths.insert(v.id(), 1 + below.unwrap_or(0));
+        });
+        depths[&root.id()]
+    }
+    assert_eq!(depth(&diamond()), 3);
+    assert_eq!(depth(&Value::new(1.0)), 1);
+}
+
+#[test]
+fn bloat_report_attributes_nodes_to_tags() {
+    let xs = [1.0, 2.0, 3.0];
+    let ws: Vec<Value> = (0..3)
+        .map(|i| Value::new_tagged(i as Scalar, "weight"))
+        .collect();
+    // a hand-rolled neuron: sum of x_i * w_i, plus a bias
+    let dot = with_tag("neuron.dot", || {
+        let mut terms = xs.iter().zip(&ws).map(|(x, w)| &Value::new(*x) * w);
+        let first = terms.next().unwrap();
+        terms.fold(first, |acc, t| acc + t)
+    });
+    let out = with_tag("neuron.bias_add", || dot + Value::new_tagged(0.5, "bias"));
+
+    // 3 input leaves + 3 muls + 2 adds
+    assert_eq!(
+        out.bloat_report(),
+        vec![
+            ("neuron.dot".to_string(), 8),
+            ("weight".to_string(), 3),
+            ("bias".to_string(), 1),
+            ("neuron.bias_add".to_string(), 1),
+        ]
+    );
+
+    // the scope is gone once `with_tag` returns
+    assert_eq!(
+        Value::new(1.0).bloat_report(),
+        vec![("untagged".to_string(), 1)]
+    );
+}
+
+#[test]
+fn structural_hash_ignores_labels_and_ids() {
+    let relabeled = diamond();
+    relabeled.set_label("something else");
+    assert_eq!(diamond().structural_hash(), relabeled.structural_hash());
+
+    // changing one op changes the hash
+    let a = Value::new(2.0);
+    let b = Value::new(3.0);
+    assert_ne!((&a + &b).structural_hash(), (&a * &b).structural_hash());
+    // so does operand order
+    assert_ne!((&a + &b).structural_hash(), (&b + &a).structural_hash());
+}
+
+#[test]
+fn structural_hash_rounds_leaf_data() {
+    let x = Value::new(1.0) * Value::new(2.0);
+    let y = Value::new(1.00001) * Value::new(2.0);
+    assert_ne!(x.structural_hash(), y.structural_hash());
+    assert_eq!(
+        x.structural_hash_with_precision(3),
+        y.structural_hash_with_precision(3)
+    );
+}
+
+#[test]
+fn structural_hash_is_pinned() {
+    // the same on every target and both scalar widths, so fingerprints can be stored
+    let x = (Value::new(2.0) + Value::new(3.0)) * Value::new(0.5).pow(2.0);
+    assert_eq!(x.structural_hash(), 17821356062766989714);
+}
+
+#[test]
+fn structural_hash_rounds_op_constants() {
+    let x = Value::new(0.5);
+    let pairs = [
+        (x.pow(2.0), x.pow(2.00001)),
+        (x.leaky_relu(0.1), x.leaky_relu(0.10001)),
+        (
+            binary_cross_entropy(&x, 0.3),
+            binary_cross_entropy(&x, 0.30001),
+        ),
+        (bce_with_logits(&x, 0.3), bce_with_logits(&x, 0.30001)),
+        (x.clamp(-1.0, 1.0), x.clamp(-1.00001, 1.0)),
+        (x.clamp(-1.0, 1.0), x.clamp(-1.0, 1.00001)),
+    ];
+    for (i, (a, b)) in pairs.iter().enumerate() {
+        assert_ne!(a.structural_hash(), b.structural_hash(), "{}", i);
+        assert_eq!(
+            a.structural_hash_with_precision(3),
+            b.structural_hash_with_precision(3),
+            "{}",
+            i
+        );
+    }
+}
+
+#[test]
+fn backward_accumulates_over_shared_nodes() {
+    let a = Value::new(2.0);
+    let b = Value::new(-3.0);
+    let c = Value::new(10.0);
+    let e = &a * &b;
+    let d = &e + &c;
+    let f = &d - &a;
+    f.backward();
+    assert_eq!(f.data(), 2.0);
+    assert_eq!(f.grad(), 1.0);
+    // df/da = b - 1, through both the product and the subtraction
+    assert_eq!(a.grad(), -4.0);
+    assert_eq!(b.grad(), 2.0);
+    assert_eq!(c.grad(), 1.0);
+}
+
+#[test]
+fn backward_accumulate_passes_on_only_its_own_grad_through_shared_nodes() {
+    let (a, b) = (Value::new(2.0), Value::new(3.0));
+    let e = &a * &b;
+    let l1 = &e + &Value::new(1.0);
+    let l2 = &e * 3.0;
+    l1.backward_accumulate();
+    l2.backward_accumulate();
+    // d(l1 + l2)/da = b + 3b, d/db = a + 3a
+    assert_eq!((a.grad(), b.grad()), (12.0, 8.0));
+
+    (&l1 + &l2).backward();
+    assert_eq!((a.grad(), b.grad()), (12.0, 8.0));
+}
+
+#[test]
+fn tanh_grad() {
+    let x = Value::new(0.5);
+    let y = x.tanh();
+    y.backward();
+    let t = Scalar::tanh(0.5);
+    assert!((y.data() - t).abs() < 1e-6);
+    assert!((x.grad() - (1.0 - t * t)).abs() < 1e-6);
+}
+
+#[test]
+fn div_grad_matches_finite_differences() {
+    // f(x, y) = (x * y + x) / (y - 0.5)
+    fn f(x: &Value, y: &Value) -> Value {
+        &(&(x * y) + x) / &(y - &Value::new(0.5))
+    }
+    let (x0, y0): (Scalar, Scalar) = (1.5, 2.0);
+    let x = Value::new(x0);
+    let y = Value::new(y0);
+    f(&x, &y).backward();
+
+    let h = 1e-3;
+    let eval = |x: Scalar, y: Scalar| f(&Value::new(x), &Value::new(y)).data();
+    let dx = (eval(x0 + h, y0) - eval(x0 - h, y0)) / (2.0 * h);
+    let dy = (eval(x0, y0 + h) - eval(x0, y0 - h)) / (2.0 * h);
+    assert!((x.grad() - dx).abs() < 1e-2, "{} vs {}", x.grad(), dx);
+    assert!((y.grad() - dy).abs() < 1e-2, "{} vs {}", y.grad(), dy);
+}
+
+#[test]
+fn div_by_zero_is_not_finite() {
+    let x = Value::new(1.0);
+    let zero = Value::new(0.0);
+    let q = &x / &zero;
+    assert_eq!(q.data(), Scalar::INFINITY);
+    q.backward();
+    assert_eq!(x.grad(), Scalar::INFINITY);
+    assert!(!zero.grad().is_finite());
+}
+
+#[test]
+fn pow_grad_matches_self_product() {
+    let a = Value::new(1.5);
+    let b = Value::new(-0.5);
+    let diff = &a - &b;
+    (&diff * &diff).backward();
+    let by_mul = (a.grad(), b.grad());
+
+    let a = Value::new(1.5);
+    let b = Value::new(-0.5);
+    let diff = &a - &b;
+    let square = diff.pow(2.0);
+    square.backward();
+    assert_eq!(square.data(), 4.0);
+    assert_eq!((a.grad(), b.grad()), by_mul);
+}
+
+#[test]
+fn pow_negative_and_fractional_exponents() {
+    let x = Value::new(4.0);
+    let y = x.pow(-0.5);
+    y.backward();
+    assert_eq!(y.data(), 0.5);
+    // -0.5 * 4^-1.5
+    assert_eq!(x.grad(), -0.0625);
+
+    let negative = Value::new(-4.0);
+    let root = negative.pow(0.5);
+    root.backward();
+    assert!(root.data().is_nan());
+    assert!(negative.grad().is_nan());
+}
+
+#[test]
+fn exp_grad_matches_finite_differences() {
+    fn f(a: &Value, b: &Value, c: &Value) -> Value {
+        &(a * b).exp() + c
+    }
+    let (a0, b0, c0): (Scalar, Scalar, Scalar) = (0.5, -1.2, 3.0);
+    let (a, b, c) = (Value::new(a0), Value::new(b0), Value::new(c0));
+    let out = f(&a, &b, &c);
+    out.backward();
+    assert!((out.data() - ((a0 * b0).exp() + c0)).abs() < 1e-6);
+
+    let h = 1e-3;
+    let eval = |a: Scalar, b: Scalar| f(&Value::new(a), &Value::new(b), &Value::new(c0)).data();
+    let da = (eval(a0 + h, b0) - eval(a0 - h, b0)) / (2.0 * h);
+    let db = (eval(a0, b0 + h) - eval(a0, b0 - h)) / (2.0 * h);
+    assert!((a.grad() - da).abs() < 1e-2, "{} vs {}", a.grad(), da);
+    assert!((b.grad() - db).abs() < 1e-2, "{} vs {}", b.grad(), db);
+    assert_eq!(c.grad(), 1.0);
+}
+
+#[test]
+fn negative_log_likelihood_grad() {
+    let p = Value::new(0.25);
+    let nll = -p.ln();
+    nll.backward();
+    assert!((nll.data() - Scalar::ln(4.0)).abs() < 1e-6);
+    assert_eq!(p.grad(), -4.0);
+}
+
+#[test]
+fn ln_outside_its_domain() {
+    let zero = Value::new(0.0);
+    let y = zero.ln();
+    assert_eq!(y.data(), Scalar::NEG_INFINITY);
+    y.backward();
+    assert_eq!(zero.grad(), Scalar::INFINITY);
+
+    let negative = Value::new(-1.0);
+    let y = negative.ln();
+    assert!(y.data().is_nan());
+    y.backward();
+    assert_eq!(negative.grad(), -1.0);
+}
+
+#[test]
+fn relu_passes_grad_only_for_positive_inputs() {
+    for (x0, expected_data, expected_grad) in [(2.0, 2.0, 1.0), (-2.0, 0.0, 0.0), (0.0, 0.0, 0.0)] {
+        let x = Value::new(x0);
+        let y = x.relu();
+        y.backward();
+        assert_eq!(y.data(), expected_data);
+        assert_eq!(x.grad(), expected_grad, "at {}", x0);
+    }
+}
+
+#[test]
+fn sigmoid_grad_matches_finite_differences() {
+    let x0: Scalar = 0.7;
+    let x = Value::new(x0);
+    let y = (&x * &Value::new(2.0)).sigmoid();
+    y.backward();
+
+    let h = 1e-3;
+    let eval = |x: Scalar| (&Value::new(x) * &Value::new(2.0)).sigmoid().data();
+    let dx = (eval(x0 + h) - eval(x0 - h)) / (2.0 * h);
+    assert!((x.grad() - dx).abs() < 1e-3, "{} vs {}", x.grad(), dx);
+}
+
+#[test]
+fn sigmoid_saturates_without_nan() {
+    for (x0, expected) in [(100.0, 1.0), (-100.0, 0.0)] {
+        let x = Value::new(x0);
+        let y = x.sigmoid();
+        y.backward();
+        assert!((y.data() - expected).abs() < 1e-6);
+        assert!(x.grad().is_finite() && x.grad().abs() < 1e-6);
+    }
+}
+
+#[test]
+fn gelu_grads_match_finite_differences() {
+    for x in [-4.0, -1.0, 0.0, 1.0, 4.0] {
+        check_gradients(|v| v[0].gelu(), &[x], 1e-3, 1e-2).unwrap();
+
+        // the graph of the derivative agrees with the numeric backward
+        let v = Value::new(x);
+        let y = v.gelu();
+        let dy = backward_graph(&y, std::slice::from_ref(&v)).remove(0);
+        y.backward();
+        assert!((dy.data() - v.grad()).abs() < 1e-6, "at {}", x);
+    }
+    assert_eq!(Value::new(0.0).gelu().data(), 0.0);
+}
+
+#[test]
+fn gelu_saturates_without_nan() {
+    for (x0, slope) in [(20.0, 1.0), (1e30, 1.0), (-20.0, 0.0), (-1e30, 0.0)] {
+        let x = Value::new(x0);
+        let y = x.gelu();
+        y.backward();
+        let expected = if slope == 1.0 { x0 } else { 0.0 };
+        assert!((y.data() - expected).abs() <= 1e-6 * x0.abs(), "at {}", x0);
+        assert_eq!(x.grad(), slope, "at {}", x0);
+    }
+}
+
+#[test]
+fn grad_wrt_returns_grads_in_leaf_order() {
+    let (a, b) = (Value::new(2.0), Value::new(-3.0));
+    let f = &a * &b + &a;
+    assert_eq!(f.grad_wrt(&[b.clone(), a.clone()]), Ok(vec![2.0, -2.0]));
+    // a second call starts over instead of adding on
+    assert_eq!(f.grad_wrt(&[a, b]), Ok(vec![-2.0, 2.0]));
+}
+
+#[test]
+fn grad_wrt_rejects_an_unreachable_leaf() {
+    let (a, b, c) = (Value::new(2.0), Value::new(3.0), Value::new(4.0));
+    let f = &a * &b;
+    let err = f.grad_wrt(&[a.clone(), c.clone()]).unwrap_err();
+    assert_eq!(
+        err,
+        UnreachableLeaf {
+            index: 1,
+            id: c.id()
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        format!("leaf 1 (node {}) is not reachable from the root", c.id())
+    );
+    assert_eq!(a.grad(), 0.0, "no backward ran");
+}
+
+#[test]
+fn mul_add_matches_the_unfused_product_and_sum() {
+    let mut rng = StdRng::seed_from_u64(3);
+    for _ in 0..20 {
+        let [a, b, c] = [(); 3].map(|_| Value::new(rng.gen_range(-3.0..=3.0)));
+        let fused = a.mul_add(&b, &c).tanh();
+        let fused_grads = fused.grad_wrt(&[a.clone(), b.clone(), c.clone()]).unwrap();
+        let unfused = (&(&a * &b) + &c).tanh();
+        let unfused_grads = unfused
+            .grad_wrt(&[a.clone(), b.clone(), c.clone()])
+            .unwrap();
+        assert_eq!(fused.data().to_bits(), unfused.data().to_bits());
+        assert_eq!(fused_grads, unfused_grads);
+
+        let [dd, d] = [&fused, &unfused].map(|y| backward_graph(y, &[a.clone(), b.clone()]));
+        for (x, y) in dd.iter().zip(&d) {
+            assert_eq!(x.data(), y.data());
+        }
+    }
+
+    // a node feeding two of the operands gets both shares
+    let x = Value::new(3.0);
+    assert_eq!(
+        x.mul_add(&x, &x).grad_wrt(std::slice::from_ref(&x)),
+        Ok(vec![7.0])
+    );
+}
+
+#[test]
+fn neg_grad() {
+    let a = Value::new(2.0);
+    let b = Value::new(-3.0);
+    let y = -(&a * &b);
+    y.backward();
+    assert_eq!(y.data(), 6.0);
+    assert_eq!(a.grad(), 3.0);
+    assert_eq!(b.grad(), -2.0);
+    // a dedicated node rather than a subtraction from a zero constant
+    assert_eq!(y.op().name(), "neg");
+}
+
+#[test]
+fn scalar_operands_on_either_side() {
+    let v = Value::new(3.0);
+    let left = &v + 1.0;
+    let right = 1.0 + &v;
+    assert_eq!(left.data(), 4.0);
+    assert_eq!(right.data(), 4.0);
+    // the scalar is a constant leaf in the graph
+    assert_eq!(left.bloat_report(), vec![("untagged".to_string(), 3)]);
+
+    left.backward();
+    let from_left = v.grad();
+    let v = Value::new(3.0);
+    (1.0 + &v).backward();
+    assert_eq!(v.grad(), from_left);
+
+    let v = Value::new(3.0);
+    let y = &(2.0 * &v) / 4.0 - 1.0;
+    y.backward();
+    assert_eq!(y.data(), 0.5);
+    assert_eq!(v.grad(), 0.5);
+    assert_eq!((1.0 / Value::new(4.0)).data(), 0.25);
+    assert_eq!((10.0 - Value::new(4.0)).data(), 6.0);
+}
+
+#[test]
+fn owned_and_borrowed_operands_build_the_same_graph() {
+    let (a, b, c) = (Value::new(2.0), Value::new(3.0), Value::new(4.0));
+    let borrowed = &(&a * &b) + &c;
+    let graphs = [
+        a.clone() * b.clone() + c.clone(),
+        &a * b.clone() + &c,
+        a.clone() * &b + c.clone(),
+        (&a * &b) + c.clone(),
+    ];
+    for g in &graphs {
+        assert_eq!(g.data(), borrowed.data());
+        assert_eq!(g.structural_hash(), borrowed.structural_hash());
+    }
+}
+
+#[test]
+fn compound_assignment_records_every_step() {
+    let terms: Vec<Value> = (0..10).map(|i| Value::new(i as Scalar)).collect();
+    let mut sum = Value::new(0.0);
+    let start = sum.clone();
+    for t in &terms {
+        sum += t;
+    }
+    assert_eq!(sum.data(), 45.0);
+    // the old handle still refers to the original node
+    assert_eq!(start.data(), 0.0);
+    sum.backward();
+    for t in &terms {
+        assert_eq!(t.grad(), 1.0);
+    }
+
+    let x = Value::new(3.0);
+    let mut y = x.clone();
+    y *= &x;
+    y -= Value::new(1.0);
+    y.backward();
+    assert_eq!(y.data(), 8.0);
+    assert_eq!(x.grad(), 6.0);
+}
+
+#[test]
+fn labels_are_shared_by_clones() {
+    let w = Value::new(0.5).with_label("w1");
+    let copy = w.clone();
+    assert_eq!(copy.label(), "w1");
+    copy.set_label("renamed");
+    assert_eq!(w.label(), "renamed");
+
+    let out = &w * 2.0;
+    assert_eq!(out.display_name(), format!("#{}", out.id()));
+    assert_eq!(w.display_name(), "renamed");
+}
+
+#[test]
+fn display_is_compact() {
+    let x = Value::new(0.5);
+    let y = x.tanh();
+    y.backward();
+    assert_eq!(
+        format!("{}", x),
+        format!("Value(id={}, data=0.5, grad={}, op=none)", x.id(), x.grad())
+    );
+    assert_eq!(
+        format!("{}", y),
+        format!("Value(id={}, data={}, grad=1, op=tanh)", y.id(), y.data())
+    );
+}
+
+#[test]
+fn debug_does_not_recurse() {
+    let mut chain = Value::new(1.0);
+    for _ in 0..10_000 {
+        chain = &chain + 1.0;
+    }
+    let printed = format!("{:?}", chain);
+    assert!(printed.len() < 200, "{}", printed);
+    assert!(printed.contains("op: \"plus\""));
+}
+
+#[test]
+fn to_dot_lists_nodes_and_edges() {
+    let a = Value::new(2.0).with_label("a");
+    let b = Value::new(3.0).with_label("b \"q\"");
+    let c = Value::new(1.0).with_label("c");
+    let ab = &a * &b;
+    let out = (&ab + &c).with_label("out");
+    let dot = out.to_dot();
+
+    assert!(dot.starts_with("digraph computation {\n"));
+    assert!(dot.ends_with("}\n"));
+    assert!(dot.contains(&format!(
+        "    n{} [label=\"out\\n+\\ndata=7 grad=0\"];\n",
+        out.id()
+    )));
+    assert!(dot.contains(&format!(
+        "    n{} [label=\"#{}\\n*\\ndata=6 grad=0\"];\n",
+        ab.id(),
+        ab.id()
+    )));
+    assert!(dot.contains(&format!(
+        "    n{} [label=\"b \\\"q\\\"\\ndata=3 grad=0\"];\n",
+        b.id()
+    )));
+    for (from, to) in [(&a, &ab), (&b, &ab), (&ab, &out), (&c, &out)] {
+        assert!(dot.contains(&format!("    n{} -> n{};\n", from.id(), to.id())));
+    }
+    assert_eq!(dot.matches("->").count(), 4);
+    // deterministic
+    assert_eq!(dot, out.to_dot());
+}
+
+#[test]
+fn print_tree_refers_back_to_shared_nodes() {
+    let a = Value::new(2.0).with_label("a");
+    let b = Value::new(3.0);
+    let ab = &a * &b;
+    let out = &ab + &a;
+    out.backward();
+    assert_eq!(
+        out.print_tree(None),
+        [
+            format!("+ #{} (data=8.0 grad=1.0)", out.id()),
+            format!("  * #{} (data=6.0 grad=1.0)", ab.id()),
+            format!("    a #{} (data=2.0 grad=4.0)", a.id()),
+            format!("    #{} (data=3.0 grad=2.0)", b.id()),
+            format!("  a (see #{})", a.id()),
+        ]
+        .join("\n")
+    );
+    assert_eq!(
+        out.print_tree(Some(0)),
+        format!("+ #{} (data=8.0 grad=1.0)\n  …", out.id())
+    );
+}
+
+#[test]
+fn print_tree_depth_limit_bounds_an_mlp_loss() {
+    let mlp = micrograd::MLP::new_with_seed(3, &[8, 8, 1], 2);
+    let pred = mlp
+        .apply(&micrograd::values![0.5, -1.0, 2.0])
+        .pop()
+        .unwrap();
+    let loss = micrograd::losses::mse(&[pred], &micrograd::values![1.0]);
+    assert!(loss.print_tree(None).lines().count() > 100);
+
+    let tree = loss.print_tree(Some(3));
+    assert!(tree.lines().count() <= 20, "{}", tree);
+    assert!(tree.lines().all(|l| l.len() - l.trim_start().len() <= 8));
+    assert!(tree.contains('…'));
+}
+
+#[test]
+fn print_tree_handles_deep_chains() {
+    let leaf = Value::new(0.0);
+    let mut chain = leaf.clone();
+    for _ in 0..100_000 {
+        chain = &chain + 1.0;
+    }
+    let tree = chain.print_tree(Some(10));
+    assert_eq!(tree.lines().count(), 11 + 10 + 1);
+}
+
+#[test]
+#[should_panic(expected = "computed by div has data inf; operands [x=1, zero=0]")]
+fn detect_anomaly_reports_division_by_zero() {
+    let x = Value::new(1.0).with_label("x");
+    let zero = Value::new(0.0).with_label("zero");
+    detect_anomaly(|| &x / &zero);
+}
+
+#[test]
+#[should_panic(expected = "computed by exp has data inf")]
+fn detect_anomaly_reports_overflow() {
+    detect_anomaly(|| Value::new(1e10).exp());
+}
+
+#[test]
+#[should_panic(expected = "\"root\" computed by pow with grad 1 wrote grad inf into operand")]
+fn detect_anomaly_reports_non_finite_grads() {
+    // sqrt(0) is fine, its derivative is not
+    let y = Value::new(0.0).pow(0.5).with_label("root");
+    detect_anomaly(|| y.backward());
+}
+
+#[test]
+fn anomalies_pass_silently_outside_detect_anomaly() {
+    let q = Value::new(1.0) / Value::new(0.0);
+    assert_eq!(q.data(), Scalar::INFINITY);
+    let x = Value::new(0.0);
+    x.pow(0.5).backward();
+    assert_eq!(x.grad(), Scalar::INFINITY);
+
+    // finite values are untouched inside it
+    let y = detect_anomaly(|| {
+        let y = (Value::new(2.0) * 3.0).tanh();
+        y.backward();
+        y
+    });
+    assert!(y.data().is_finite());
+}
+
+#[test]
+fn scopes_are_restored_after_a_caught_panic() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let x = Value::new(0.0);
+    let caught = catch_unwind(AssertUnwindSafe(|| {
+        with_tag("inner", || {
+            no_grad(|| detect_anomaly(|| &Value::new(1.0) / &x))
+        })
+    }));
+    assert!(caught.is_err());
+
+    // the graph is recorded, untagged and unchecked again
+    let q = Value::new(1.0) / &x;
+    assert!(!q.is_leaf());
+    assert_eq!(q.tag(), None);
+    assert_eq!(q.data(), Scalar::INFINITY);
+
+    // and an outer scope gets its own setting back
+    let tagged = with_tag("outer", || {
+        let _ = catch_unwind(|| with_tag("inner", || panic!("dropped")));
+        Value::new(1.0)
+    });
+    assert_eq!(tagged.tag(), Some("outer"));
+}
+
+#[test]
+fn backward_through_a_deep_chain() {
+    let leaf = Value::new(0.0);
+    let mut chain = leaf.clone();
+    for _ in 0..100_000 {
+        chain = &chain + 1.0;
+    }
+    chain.backward();
+    assert_eq!(leaf.grad(), 1.0);
+    assert_eq!(chain.data(), 100_000.0);
+}
+
+#[test]
+fn dropping_a_deep_chain_does_not_recurse() {
+    let handle = std::thread::Builder::new()
+        .stack_size(64 * 1024)
+        .spawn(|| {
+            let leaf = Value::new(0.0);
+            let mut chain = leaf.clone();
+            for _ in 0..200_000 {
+                chain = &chain + 1.0;
+            }
+            drop(chain);
+            // the leaf is still owned here and survives
+            leaf.data()
+        })
+        .unwrap();
+    assert_eq!(handle.join().unwrap(), 0.0);
+}
+
+#[test]
+fn dropping_keeps_shared_subgraphs() {
+    let a = Value::new(2.0);
+    let shared = &a * 3.0;
+    let out = &shared + 1.0;
+    drop(out);
+    assert_eq!(shared.data(), 6.0);
+    assert_eq!(shared.op().name(), "mul");
+    shared.backward();
+    assert_eq!(a.grad(), 3.0);
+}
+
+#[test]
+fn detach_stops_the_grad() {
+    let x = Value::new(3.0);
+    let baseline = (&x * 2.0).detach();
+    assert!(baseline.is_leaf());
+    let y = &x * &baseline;
+    y.backward();
+    // only the direct use of x contributes
+    assert_eq!(y.data(), 18.0);
+    assert_eq!(x.grad(), 6.0);
+}
+
+#[test]
+fn no_grad_builds_no_graph() {
+    let x = Value::new(0.3);
+    let tracked = (&x * 2.0).tanh().exp();
+    let untracked = no_grad(|| (&x * 2.0).tanh().exp());
+    assert_eq!(tracked.data().to_bits(), untracked.data().to_bits());
+    assert!(untracked.is_leaf());
+
+    // tracking is back once the scope ends
+    assert!(!(&x * 2.0).is_leaf());
+}
+
+#[test]
+fn free_graph_releases_intermediates() {
+    let w = Value::new(0.5);
+    let before = w.ref_count();
+    let loss = ((&w * 3.0).tanh() + 1.0).pow(2.0);
+    assert!(w.ref_count() > before);
+    loss.backward();
+    let grad = w.grad();
+
+    loss.free_graph();
+    assert_eq!(w.ref_count(), before);
+    assert_eq!(w.grad(), grad);
+    assert!(loss.data() > 0.0);
+}
+
+#[test]
+#[should_panic(expected = "after its graph was released by free_graph")]
+fn backward_after_free_graph_panics() {
+    let w = Value::new(0.5);
+    let loss = &w * 3.0;
+    loss.free_graph();
+    loss.backward();
+}
+
+/// d/dx of x * 1.0001^10000, against the exact e^(10000 ln 1.0001)
+fn deep_product_grad_error() -> f64 {
+    let x = Value::new(1.0);
+    let mut y = x.clone();
+    for _ in 0..10_000 {
+        y = &y * 1.0001;
+    }
+    y.backward();
+    let exact = 1.0001f64.powi(10_000);
+    #[cfg(not(feature = "f64"))]
+    let grad = x.grad() as f64;
+    #[cfg(feature = "f64")]
+    let grad = x.grad();
+    (grad - exact).abs() / exact
+}
+
+#[cfg(not(feature = "f64"))]
+#[test]
+fn f32_grads_drift_on_deep_chains() {
+    assert!(deep_product_grad_error() > 1e-5);
+}
+
+#[cfg(feature = "f64")]
+#[test]
+fn f64_grads_stay_accurate_on_deep_chains() {
+    assert!(deep_product_grad_error() < 1e-10);
+}
+
+#[test]
+fn fused_sum_matches_chained_adds() {
+    let terms: Vec<Value> = (0..100).map(|i| Value::new(i as Scalar * 0.01)).collect();
+    let weights: Vec<Value> = (0..100)
+        .map(|i| Value::new(1.0 - i as Scalar * 0.02))
+        .collect();
+    let products: Vec<Value> = terms.iter().zip(&weights).map(|(x, w)| x * w).collect();
+
+    let mut chained = products[0].clone();
+    for p in &products[1..] {
+        chained += p;
+    }
+    let chained = chained.pow(2.0);
+    chained.backward();
+    let chained_grads: Vec<Scalar> = weights.iter().map(Value::grad).collect();
+
+    let fused = Value::sum(&products).pow(2.0);
+    assert!((fused.data() - chained.data()).abs() < 1e-4);
+    fused.backward();
+    for (w, g) in weights.iter().zip(&chained_grads) {
+        assert!((w.grad() - g).abs() < 1e-4, "{} vs {}", w.grad(), g);
+    }
+
+    // 200 leaves and 100 products either way, then 99 adds versus a single sum
+    assert_eq!(chained.node_count(), 400);
+    assert_eq!(fused.node_count(), 302);
+}
+
+#[test]
+fn sum_of_nothing_is_a_zero_leaf() {
+    let s = Value::sum(&[]);
+    assert!(s.is_leaf());
+    assert_eq!(s.data(), 0.0);
+
+    let a = Value::new(2.0);
+    let twice = Value::sum(&[a.clone(), a.clone()]);
+    twice.backward();
+    assert_eq!((twice.data(), a.grad()), (4.0, 2.0));
+}
+
+#[test]
+fn max_routes_the_grad_to_the_larger_operand() {
+    let (a, b) = (Value::new(2.0), Value::new(-1.0));
+    let m = a.max(&b);
+    assert_eq!(m.data(), 2.0);
+    m.backward();
+    assert_eq!((a.grad(), b.grad()), (1.0, 0.0));
+
+    let m = b.max(&a);
+    m.backward();
+    assert_eq!((a.grad(), b.grad()), (1.0, 0.0));
+
+    // ties go to the first operand
+    let c = Value::new(2.0);
+    let m = c.max(&a);
+    m.backward();
+    assert_eq!((c.grad(), a.grad()), (1.0, 0.0));
+}
+
+#[test]
+fn hinge_loss_separates_two_points() {
+    // f(x) = w * x + b with labels y = ±1, trained on Σ max(0, 1 - y f(x))
+    let (w, b) = (Value::new(-0.5), Value::new(0.3));
+    let mut optim = Sgd::new(vec![w.clone(), b.clone()], 0.1);
+    let points: [(Scalar, Scalar); 2] = [(1.0, 1.0), (-2.0, -1.0)];
+    let zero = Value::new(0.0);
+    let loss = || {
+        let terms: Vec<Value> = points
+            .iter()
+            .map(|&(x, y)| zero.max(&(1.0 - (&w * x + &b) * y)))
+            .collect();
+        Value::sum(&terms)
+    };
+    for _ in 0..100 {
+        let l = loss();
+        if l.data() == 0.0 {
+            break;
+        }
+        l.backward();
+        optim.step();
+    }
+    assert_eq!(loss().data(), 0.0);
+    for (x, y) in points {
+        assert!((w.data() * x + b.data()) * y >= 1.0);
+    }
+}
+
+#[test]
+fn clamp_passes_the_grad_only_inside() {
+    let grad_at = |x: Scalar| {
+        let v = Value::new(x);
+        let c = v.clamp(-1.0, 1.0);
+        c.backward();
+        (c.data(), v.grad())
+    };
+    assert_eq!(grad_at(0.5), (0.5, 1.0));
+    assert_eq!(grad_at(1.0), (1.0, 0.0));
+    assert_eq!(grad_at(-1.0), (-1.0, 0.0));
+    assert_eq!(grad_at(3.0), (1.0, 0.0));
+    assert_eq!(grad_at(-7.0), (-1.0, 0.0));
+}
+
+#[test]
+fn clamp_keeps_ln_finite() {
+    let p = Value::new(0.0);
+    let l = p.clamp(1e-6, 1.0).ln();
+    assert!(l.data().is_finite());
+    l.backward();
+    assert_eq!(p.grad(), 0.0);
+}
+
+#[test]
+#[should_panic(expected = "clamp bounds out of order: lo 1 > hi 0")]
+fn clamp_rejects_reversed_bounds() {
+    Value::new(0.5).clamp(1.0, 0.0);
+}
+
+#[test]
+fn leaky_relu_grad() {
+    let grad_at = |x: Scalar| {
+        let v = Value::new(x);
+        let y = v.leaky_relu(0.1);
+        y.backward();
+        (y.data(), v.grad())
+    };
+    assert_eq!(grad_at(2.0), (2.0, 1.0));
+    let (y, g) = grad_at(-2.0);
+    assert!((y + 0.2).abs() < 1e-6);
+    assert!((g - 0.1).abs() < 1e-6);
+    // 0 takes the negative side's slope
+    let (y, g) = grad_at(0.0);
+    assert_eq!(y, 0.0);
+    assert!((g - 0.1).abs() < 1e-6);
+
+    check_gradients(|x| x[0].leaky_relu(0.1) * &x[1], &[1.5, -0.5], 1e-3, 1e-2).unwrap();
+    check_gradients(|x| x[0].leaky_relu(0.1) * &x[1], &[-1.5, 0.5], 1e-3, 1e-2).unwrap();
+}
+
+#[test]
+fn sin_and_cos_grads_over_a_grid() {
+    let half_pi = consts::FRAC_PI_2;
+    for k in -4..=4 {
+        for offset in [0.0, 0.3] {
+            let x = k as Scalar * half_pi + offset;
+            check_gradients(|v| v[0].sin(), &[x], 1e-3, 1e-2).unwrap();
+            check_gradients(|v| v[0].cos(), &[x], 1e-3, 1e-2).unwrap();
+        }
+    }
+    let x = Value::new(half_pi);
+    x.sin().backward();
+    assert!(x.grad().abs() < 1e-6);
+    x.cos().backward();
+    assert!((x.grad() + 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn second_derivative_of_a_cube() {
+    let x = Value::new(2.0);
+    let y = &x * &x * &x;
+    let dy = backward_graph(&y, std::slice::from_ref(&x)).remove(0);
+    assert_eq!(dy.data(), 12.0);
+    let d2y = backward_graph(&dy, std::slice::from_ref(&x)).remove(0);
+    assert_eq!(d2y.data(), 12.0);
+
+    // the numeric path agrees, and backward_graph left the grads alone
+    assert_eq!(x.grad(), 0.0);
+    dy.backward();
+    assert_eq!(x.grad(), 12.0);
+}
+
+#[test]
+fn hessian_vector_product_of_a_quadratic() {
+    // f = x^T A x / 2 with symmetric A, so grad f = A x and H v = A v
+    let a: [[Scalar; 2]; 2] = [[2.0, 1.0], [1.0, 3.0]];
+    let x = [Value::new(0.5), Value::new(-1.5)];
+    let mut f = Value::new(0.0);
+    for i in 0..2 {
+        for j in 0..2 {
+            f += &x[i] * &x[j] * (a[i][j] / 2.0);
+        }
+    }
+    let grads = backward_graph(&f, &x);
+    for i in 0..2 {
+        let expected = a[i][0] * x[0].data() + a[i][1] * x[1].data();
+        assert!((grads[i].data() - expected).abs() < 1e-6);
+    }
+
+    let v = [Value::new(1.0), Value::new(2.0)];
+    let hv = backward_graph(&dot(&grads, &v), &x);
+    assert_eq!(hv[0].data(), 2.0 + 2.0);
+    assert_eq!(hv[1].data(), 1.0 + 6.0);
+}
+
+#[test]
+fn backward_graph_matches_backward() {
+    let x = [Value::new(0.7), Value::new(-0.4), Value::new(1.3)];
+    let build = || {
+        let s = Value::sum(&[x[0].tanh(), x[1].sigmoid() * &x[2], x[2].ln()]);
+        let t = (&x[0] / &x[2]).exp() - x[1].sin() + x[0].cos().pow(2.0);
+        let u = x[1].leaky_relu(0.1).abs() + x[0].max(&x[1]) + x[2].clamp(0.0, 2.0).relu();
+        bce_with_logits(&(s * t), 1.0) + binary_cross_entropy(&u.sigmoid(), 0.2) + -&u
+    };
+    let root = build();
+    root.backward();
+    let graph = backward_graph(&root, &x);
+    for (xi, g) in x.iter().zip(&graph) {
+        assert!(
+            (xi.grad() - g.data()).abs() < 1e-5,
+            "{} vs {}",
+            xi.grad(),
+            g.data()
+        );
+    }
+
+    let unrelated = Value::new(1.0);
+    assert_eq!(backward_graph(&root, &[unrelated])[0].data(), 0.0);
+}
+
+#[test]
+fn graph_stats_of_a_small_expression() {
+    let (a, b, c) = (Value::new(2.0), Value::new(3.0), Value::new(4.0));
+    let stats = (&a * &b + &c).graph_stats();
+    assert_eq!(stats.nodes, 5);
+    assert_eq!(stats.edges, 4);
+    assert_eq!(stats.depth, 2);
+    assert_eq!(
+        stats.ops.into_iter().collect::<Vec<_>>(),
+        vec![("mul", 1), ("none", 3), ("plus", 1)]
+    );
+
+    // a shared operand is one node but two edges
+    let squared = (&a * &a).graph_stats();
+    assert_eq!((squared.nodes, squared.edges, squared.depth), (2, 2, 1));
+    assert_eq!(
+        squared.to_string(),
+        "2 nodes, 2 edges, depth 1 (mul: 1, none: 1)"
+    );
+    assert_eq!(
+        a.graph_stats().to_string(),
+        "1 nodes, 0 edges, depth 0 (none: 1)"
+    );
+}
+
+#[test]
+fn iter_graph_visits_shared_nodes_once() {
+    let a = Value::new(2.0);
+    let b = &a * &a;
+    let c = &b + &a;
+    let root = &c * &b;
+    let ids: Vec<usize> = root.iter_graph().map(|v| v.id()).collect();
+    assert_eq!(ids.len(), 4);
+    let mut unique = ids.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(unique.len(), 4);
+    // children before parents
+    assert_eq!(ids[0], a.id());
+    assert_eq!(*ids.last().unwrap(), root.id());
+
+    assert!(a.children().is_empty());
+    let operands: Vec<usize> = root.children().iter().map(Value::id).collect();
+    assert_eq!(operands, vec![c.id(), b.id()]);
+    assert_eq!(b.children().len(), 2);
+}
+
+#[test]
+fn equality_is_node_identity() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashMap;
+    use std::hash::{Hash, Hasher};
+
+    let hash = |v: &Value| {
+        let mut h = DefaultHasher::new();
+        v.hash(&mut h);
+        h.finish()
+    };
+    let a = Value::new(1.5);
+    let alias = a.clone();
+    assert_eq!(a, alias);
+    assert!(a.ptr_eq(&alias));
+    assert_eq!(hash(&a), hash(&alias));
+
+    let same_data = Value::new(1.5);
+    assert_ne!(a, same_data);
+    assert!(!a.ptr_eq(&same_data));
+
+    let mut seen: HashMap<Value, usize> = HashMap::new();
+    for v in [&a, &alias, &same_data] {
+        *seen.entry(v.clone()).or_default() += 1;
+    }
+    assert_eq!(seen[&a], 2);
+    assert_eq!(seen[&same_data], 1);
+}
+
+#[cfg(feature = "serde")]
+mod json {
+    use micrograd::losses::{bce_with_logits, binary_cross_entropy, mse};
+    use micrograd::{graph_from_json, ParseError, Value, MLP};
+
+    #[test]
+    fn mlp_loss_graph_round_trips() {
+        let mlp = MLP::new_with_seed(2, &[3, 1], 5);
+        let x = [Value::new(0.5), Value::new(-1.0)];
+        let loss = mse(&mlp.apply(&x), &[Value::new(0.25)]);
+        loss.backward();
+        let json = loss.to_json();
+
+        let rebuilt = graph_from_json(&json).unwrap();
+        assert_ne!(rebuilt.id(), loss.id());
+        assert_eq!(rebuilt.to_json(), json);
+        assert_eq!(rebuilt.structural_hash(), loss.structural_hash());
+        assert_eq!(rebuilt.node_count(), loss.node_count());
+
+        // the rebuilt graph back-propagates to the same grads
+        rebuilt.backward();
+        assert_eq!(rebuilt.to_json(), json);
+    }
+
+    #[test]
+    fn every_op_round_trips() {
+        let a = Value::new(0.7).with_label("a");
+        let b = Value::new(1.3);
+        let terms = [
+            &a + &b,
+            &a - &b,
+            &a * &b,
+            &a / &b,
+            a.max(&b),
+            a.pow(3.0),
+            a.tanh(),
+            a.exp(),
+            b.ln(),
+            a.relu(),
+            (-&a).leaky_relu(0.1),
+            a.sigmoid(),
+            binary_cross_entropy(&a.sigmoid(), 1.0),
+            bce_with_logits(&a, 0.0),
+            a.abs(),
+            b.powv(&a),
+            b.clamp(-1.0, 1.0),
+            a.sin(),
+            b.cos(),
+            a.gelu(),
+            a.mul_add(&b, &a),
+            Value::mean(&[a.clone(), b.clone()]),
+        ];
+        let root = Value::sum(&terms);
+        root.backward();
+        let json = root.to_json();
+        let rebuilt = graph_from_json(&json).unwrap();
+        assert_eq!(rebuilt.to_json(), json);
+        assert_eq!(rebuilt.structural_hash(), root.structural_hash());
+    }
+
+    #[test]
+    fn records_are_plain_and_stable() {
+        let a = Value::new(2.0);
+        let b = Value::new(3.0).with_label("b");
+        let y = (&a * &b).pow(2.0);
+        assert_eq!(
+            y.to_json(),
+            concat!(
+                r#"[{"id":0,"data":2.0,"grad":0.0,"op":"none","children":[]},"#,
+                r#"{"id":1,"data":3.0,"grad":0.0,"op":"none","children":[],"label":"b"},"#,
+                r#"{"id":2,"data":6.0,"grad":0.0,"op":"mul","children":[0,1]},"#,
+                r#"{"id":3,"data":36.0,"grad":0.0,"op":"pow","params":[2.0],"children":[2]}]"#
+            )
+        );
+    }
+
+    #[test]
+    fn malformed_graphs_are_rejected() {
+        let node = |id: usize, op: &str, children: &str| {
+            format!(
+                r#"{{"id":{},"data":1.0,"grad":0.0,"op":"{}","children":[{}]}}"#,
+                id, op, children
+            )
+        };
+        let parse = |nodes: &[String]| graph_from_json(&format!("[{}]", nodes.join(",")));
+
+        let err = parse(&[node(0, "none", ""), node(1, "mul", "0,2")]).unwrap_err();
+        assert!(matches!(err, ParseError::DanglingChild { id: 1, child: 2 }));
+        assert_eq!(
+            err.to_string(),
+            "node 1 refers to child 2, which is not an earlier node"
+        );
+
+        // a node can't refer to itself either
+        let err = parse(&[node(0, "tanh", "0")]).unwrap_err();
+        assert!(matches!(err, ParseError::DanglingChild { id: 0, child: 0 }));
+
+        let err = parse(&[node(0, "none", ""), node(1, "softplus", "0")]).unwrap_err();
+        assert!(matches!(&err, ParseError::UnknownOp { id: 1, op } if op == "softplus"));
+        assert_eq!(err.to_string(), "node 1 has unknown op \"softplus\"");
+
+        let err = parse(&[node(0, "none", ""), node(1, "plus", "0")]).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::Arity {
+                id: 1,
+                expected: 2,
+                got: 1
+            }
+        ));
+
+        let err = parse(&[node(0, "none", ""), node(1, "pow", "0")]).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::Params {
+                id: 1,
+                expected: 1,
+                got: 0
+            }
+        ));
+
+        let err = parse(&[node(1, "none", "")]).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::UnexpectedId {
+                expected: 0,
+                got: 1
+            }
+        ));
+
+        assert!(matches!(parse(&[]).unwrap_err(), ParseError::Empty));
+        assert!(matches!(
+            graph_from_json("{").unwrap_err(),
+            ParseError::Json(_)
+        ));
+    }
+}
+
+#[test]
+fn conversions_make_ordinary_leaves() {
+    let manual: Vec<Value> = [1.0, -2.0, 0.5].iter().map(|&x| Value::new(x)).collect();
+    let from_slice = Value::from_slice(&[1.0, -2.0, 0.5]);
+    let from_macro = micrograd::values![1.0, -2.0, 0.5];
+    let from_into: Vec<Value> = [1.0, -2.0, 0.5].into_iter().map(Value::from).collect();
+    for leaves in [&from_slice, &from_macro, &from_into] {
+        assert!(leaves.iter().all(|v| v.is_leaf()));
+        let data: Vec<Scalar> = leaves.iter().map(Value::data).collect();
+        assert_eq!(data, vec![1.0, -2.0, 0.5]);
+    }
+
+    let loss = |xs: &[Value]| (&xs[0] * &xs[1] + xs[2].tanh()).pow(2.0);
+    loss(&manual).backward();
+    loss(&from_macro).backward();
+    let grads = |xs: &[Value]| xs.iter().map(Value::grad).collect::<Vec<_>>();
+    assert_eq!(grads(&manual), grads(&from_macro));
+    assert!(grads(&from_macro).iter().all(|&g| g != 0.0));
+
+    let x: Value = 3.0.into();
+    assert_eq!(x.data(), 3.0);
+    assert!(micrograd::values![].is_empty());
+}
+
+#[test]
+fn forward_matches_a_fresh_build() {
+    let build = |x: &Value, y: &Value| {
+        let s = Value::sum(&[x.tanh(), (x * y).sigmoid(), y.relu()]);
+        let t = (x / y).exp() - x.sin().max(&y.cos()) + (-x).leaky_relu(0.2).abs();
+        bce_with_logits(&(s * t.clamp(-3.0, 3.0)), 1.0)
+            + binary_cross_entropy(&y.sigmoid(), 0.3)
+            + y.pow(2.0).ln()
+    };
+    let (x, y) = (Value::new(0.4), Value::new(-1.1));
+    let root = build(&x, &y);
+    for (xd, yd) in [(2.0, 0.3), (-0.7, 1.9), (0.0, -0.5)] {
+        x.set_data(xd);
+        y.set_data(yd);
+        root.forward();
+        let fresh = build(&Value::new(xd), &Value::new(yd));
+        assert_eq!(root.data().to_bits(), fresh.data().to_bits());
+    }
+}
+
+#[test]
+fn build_once_training_loop() {
+    let xs: [[Scalar; 2]; 4] = [[0.0, 1.0], [1.0, 0.5], [-1.0, 0.2], [0.3, -0.8]];
+    let ys: [Scalar; 4] = [0.5, -0.2, 0.9, 0.1];
+    let model = |w: &[Value]| {
+        let errors: Vec<Value> = xs
+            .iter()
+            .zip(ys)
+            .map(|(x, y)| ((&w[0] * x[0] + &w[1] * x[1] + &w[2]).tanh() - y).pow(2.0))
+            .collect();
+        Value::sum(&errors)
+    };
+
+    let reused = Value::from_slice(&[0.1, -0.3, 0.2]);
+    let loss = model(&reused);
+    let nodes = loss.node_count();
+    let mut sgd = Sgd::new(reused.clone(), 0.1);
+
+    let rebuilt = Value::from_slice(&[0.1, -0.3, 0.2]);
+    let mut rebuilt_sgd = Sgd::new(rebuilt.clone(), 0.1);
+    for _ in 0..100 {
+        loss.forward();
+        loss.backward();
+        sgd.step();
+        assert_eq!(loss.node_count(), nodes);
+
+        model(&rebuilt).backward();
+        rebuilt_sgd.step();
+    }
+    let data = |w: &[Value]| w.iter().map(Value::data).collect::<Vec<_>>();
+    assert_eq!(data(&reused), data(&rebuilt));
+}
+
+#[test]
+fn setters_change_the_next_forward() {
+    let w = Value::new(2.0);
+    let x = Value::new(3.0);
+    let y = (&w * &x).tanh();
+    w.set_data(-0.5);
+    assert_eq!(w.data(), -0.5);
+    y.forward();
+    assert_eq!(y.data(), (-1.5 as Scalar).tanh());
+    w.add_data(1.0);
+    y.forward();
+    assert_eq!(y.data(), (1.5 as Scalar).tanh());
+}
+
+#[test]
+#[should_panic(expected = "set_data on node")]
+fn set_data_rejects_computed_nodes() {
+    let product = Value::new(2.0) * Value::new(3.0);
+    product.set_data(1.0);
+}
+
+#[test]
+#[should_panic(expected = "computed by mul: only leaves can be set")]
+fn add_data_rejects_computed_nodes() {
+    let product = Value::new(2.0) * Value::new(3.0);
+    product.add_data(1.0);
+}
+
+#[test]
+fn set_data_unchecked_lasts_until_forward() {
+    let a = Value::new(2.0);
+    let product = &a * 3.0;
+    product.set_data_unchecked(1.0);
+    assert_eq!(product.data(), 1.0);
+    product.forward();
+    assert_eq!(product.data(), 6.0);
+}
+
+#[test]
+fn powv_gradients() {
+    let build = |x: &[Value]| x[0].powv(&x[1]);
+    for inputs in [[2.0, 3.0], [0.5, -1.5]] {
+        check_gradients(build, &inputs, 1e-3, 1e-2).unwrap();
+    }
+
+    let (a, b) = (Value::new(2.0), Value::new(3.0));
+    let y = a.powv(&b);
+    y.backward();
+    assert_eq!(y.data(), 8.0);
+    assert_eq!(a.grad(), 12.0);
+    assert!((b.grad() - 8.0 * consts::LN_2).abs() < 1e-5);
+
+    // the higher-order path agrees
+    let grads = backward_graph(&y, &[a.clone(), b.clone()]);
+    assert_eq!(grads[0].data(), a.grad());
+    assert!((grads[1].data() - b.grad()).abs() < 1e-5);
+}
+
+#[test]
+fn powv_exponent_has_no_grad_for_non_positive_bases() {
+    for base in [0.0, -2.0] {
+        let (a, b) = (Value::new(base), Value::new(2.0));
+        a.powv(&b).backward();
+        assert_eq!(b.grad(), 0.0);
+        assert_eq!(a.grad(), 2.0 * base);
+    }
+}
+
+#[test]
+fn powv_learns_an_exponent() {
+    let xs: [Scalar; 5] = [0.5, 1.0, 1.5, 2.0, 2.5];
+    let k = Value::new(1.0);
+    let mut sgd = Sgd::new(vec![k.clone()], 0.01);
+    for _ in 0..500 {
+        let errors: Vec<Value> = xs
+            .iter()
+            .map(|&x| (Value::new(x).powv(&k) - x.powf(2.5)).pow(2.0))
+            .collect();
+        sgd.zero_grad();
+        Value::sum(&errors).backward();
+        sgd.step();
+    }
+    assert!((k.data() - 2.5).abs() < 1e-3, "exponent {}", k.data());
+}
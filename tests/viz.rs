@@ -0,0 +1,188 @@
+use graphviz_rust::cmd::CommandArg::Output;
+use graphviz_rust::dot_generator::*;
+use graphviz_rust::dot_structures::*;
+use graphviz_rust::{cmd::Format, exec, printer::PrinterContext};
+
+use micrograd::Value;
+
+fn viz_computation_graph(value: &Value, graph: &mut Graph) {
+    value.visit(graph, |graph, v, children| {
+        let v_node_id = format!("n{}", v.id());
+        let text = format!("{} | {}", v.display_name(), v.data());
+        let v_node = node!(v_node_id, vec![attr!("label", esc text)]);
+        graph.add_stmt(v_node.into());
+        for c in children {
+            let e = edge!(node_id!(format!("n{}", c.id())) => node_id!(v_node_id), vec![attr!("label", esc format!("{}", *v.op()))]);
+            graph.add_stmt(e.into());
+        }
+    });
+}
+
+#[test]
+fn it_works() {
+    let v1 = Value::new(1.0).with_label("v1");
+    let v2 = Value::new(1.0).with_label("v2");
+    let v3 = (v1 + v2).with_label("v3");
+    let v4 = Value::new(3.0);
+    let v5 = (v4 * v3).with_label("v5");
+
+    let mut g = graph!(id!("computation"));
+    viz_computation_graph(&v5, &mut g);
+    let out = std::env::temp_dir().join("micrograd_computation.svg");
+    exec(
+        g,
+        &mut PrinterContext::default(),
+        vec![
+            Format::Svg.into(),
+            Output(out.to_string_lossy().into_owned()),
+        ],
+    )
+    .unwrap();
+}
+
+#[cfg(feature = "graphviz")]
+mod options {
+    use micrograd::viz::{RankDir, VizOptions};
+    use micrograd::Value;
+
+    /// `y = a * b + a` after backward
+    fn graph() -> Value {
+        let a = Value::new(2.0).with_label("a");
+        let b = Value::new(-3.0).with_label("b");
+        let c = (&a * &b).with_label("c");
+        let y = (&c + &a).with_label("y");
+        y.backward();
+        y
+    }
+
+    #[test]
+    fn split_op_nodes_by_default() {
+        assert_eq!(
+            graph().to_dot_with(&VizOptions::default()),
+            r#"digraph {
+  rankdir=LR;
+  node [shape=record];
+  n0 [label="{ a | data 2.0000 | grad -2.0000 }"];
+  n1 [label="{ b | data -3.0000 | grad 2.0000 }"];
+  n2 [label="{ c | data -6.0000 | grad 1.0000 }"];
+  n2_op [label="*", shape=circle];
+  n2_op -> n2;
+  n0 -> n2_op;
+  n1 -> n2_op;
+  n3 [label="{ y | data -4.0000 | grad 1.0000 }"];
+  n3_op [label="+", shape=circle];
+  n3_op -> n3;
+  n2 -> n3_op;
+  n0 -> n3_op;
+}
+"#
+        );
+    }
+
+    #[test]
+    fn edge_labels_and_top_to_bottom() {
+        let options = VizOptions {
+            split_op_nodes: false,
+            rankdir: RankDir::TB,
+            ..Default::default()
+        };
+        assert_eq!(
+            graph().to_dot_with(&options),
+            r#"digraph {
+  rankdir=TB;
+  node [shape=record];
+  n0 [label="{ a | data 2.0000 | grad -2.0000 }"];
+  n1 [label="{ b | data -3.0000 | grad 2.0000 }"];
+  n2 [label="{ c | data -6.0000 | grad 1.0000 }"];
+  n0 -> n2 [label="*"];
+  n1 -> n2 [label="*"];
+  n3 [label="{ y | data -4.0000 | grad 1.0000 }"];
+  n2 -> n3 [label="+"];
+  n0 -> n3 [label="+"];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn fill_follows_the_largest_grad() {
+        let options = VizOptions {
+            color_by_grad: true,
+            ..Default::default()
+        };
+        let dot = graph().to_dot_with(&options);
+        let fills: Vec<&str> = dot
+            .lines()
+            .filter_map(|l| l.split("fillcolor=").nth(1))
+            .collect();
+        // |grad| of a, b, c, y: 2, 2, 1, 1
+        assert_eq!(
+            fills,
+            vec![
+                r##""#ff0000"];"##,
+                r##""#ff0000"];"##,
+                r##""#ff8080"];"##,
+                r##""#ff8080"];"##
+            ]
+        );
+
+        let unrun = (Value::new(1.0) * Value::new(2.0)).to_dot_with(&options);
+        assert_eq!(unrun.matches(r##"fillcolor="#ffffff""##).count(), 3);
+    }
+
+    #[test]
+    fn max_nodes_collapses_the_leaves() {
+        let options = VizOptions {
+            max_nodes: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            graph().to_dot_with(&options),
+            r#"digraph {
+  rankdir=LR;
+  node [shape=record];
+  more [label="... 2 more", shape=plaintext];
+  n0 [label="{ c | data -6.0000 | grad 1.0000 }"];
+  n0_op [label="*", shape=circle];
+  n0_op -> n0;
+  more -> n0_op;
+  n1 [label="{ y | data -4.0000 | grad 1.0000 }"];
+  n1_op [label="+", shape=circle];
+  n1_op -> n1;
+  n0 -> n1_op;
+  more -> n1_op;
+}
+"#
+        );
+
+        // the root is always drawn, and a large enough limit changes nothing
+        let root_only = VizOptions {
+            max_nodes: Some(0),
+            ..Default::default()
+        };
+        assert!(graph().to_dot_with(&root_only).contains("... 3 more"));
+        let roomy = VizOptions {
+            max_nodes: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            graph().to_dot_with(&roomy),
+            graph().to_dot_with(&VizOptions::default())
+        );
+    }
+
+    #[test]
+    fn labels_are_escaped_and_output_parses() {
+        let y = Value::new(1.0).with_label("{x|\"y\"}").tanh();
+        let dot = y.to_dot_with(&VizOptions::default());
+        assert!(dot.contains(r#"label="{ \{x\|\"y\"\} | data 1.0000"#));
+        graphviz_rust::parse(&dot).unwrap();
+        graphviz_rust::parse(&graph().to_dot_with(&VizOptions {
+            split_op_nodes: false,
+            color_by_grad: true,
+            max_nodes: Some(1),
+            ..Default::default()
+        }))
+        .unwrap();
+    }
+}